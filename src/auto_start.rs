@@ -0,0 +1,234 @@
+use crate::calendar::DayModel;
+use crate::category::CategoryResolver;
+use crate::config::{Config, FocusBlock};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveTime, Utc};
+use log::warn;
+use std::path::{Path, PathBuf};
+
+/// A focus session that should start now.
+pub struct AutoStart {
+    pub minutes: i64,
+    pub reason: String,
+}
+
+/// Decides when to start a focus session without being asked: after a
+/// warm-up period of sustained work in productive apps, or when a scheduled
+/// focus block begins.
+pub struct AutoStarter {
+    enabled: bool,
+    categories: CategoryResolver,
+    warmup: Duration,
+    work_minutes: i64,
+    blocks: Vec<FocusBlock>,
+    day_model: DayModel,
+    productive_since: Option<DateTime<Utc>>,
+    last_block: Option<(NaiveDate, NaiveTime)>,
+    state_path: Option<PathBuf>, // remembers `last_block` across restarts
+}
+
+impl AutoStarter {
+    pub fn new() -> Self {
+        Self {
+            enabled: false,
+            categories: CategoryResolver::new(),
+            warmup: Duration::minutes(5),
+            work_minutes: 25,
+            blocks: Vec::new(),
+            day_model: DayModel::default(),
+            productive_since: None,
+            last_block: None,
+            state_path: None,
+        }
+    }
+
+    pub fn apply_config(&mut self, config: &Config) {
+        self.enabled = config.auto_start_focus;
+        self.categories = CategoryResolver::from_config(config);
+        self.warmup = Duration::minutes(config.auto_start_warmup_minutes.max(1));
+        self.work_minutes = config.default_focus_duration;
+        self.blocks = config.focus_blocks.clone();
+        self.day_model = DayModel::from_config(config);
+        self.productive_since = None;
+
+        // A block that already fired must not fire again after a restart
+        let state_path = config.data_dir.join("auto_start_state.json");
+        if self.last_block.is_none() {
+            self.last_block = load_last_block(&state_path);
+        }
+        self.state_path = Some(state_path);
+    }
+
+    /// Checks the focused app (and website) after a tracker update.
+    pub fn check(
+        &mut self,
+        app_name: Option<&str>,
+        domain: Option<&str>,
+        session_running: bool,
+        now: DateTime<Utc>,
+    ) -> Option<AutoStart> {
+        // The warm-up starts over after every session
+        if !self.enabled || session_running {
+            self.productive_since = None;
+            return None;
+        }
+
+        let local = self.day_model.local_time(now);
+        let block = self.blocks.iter().find(|block| {
+            let today = block.days.is_empty() || block.days.contains(&local.weekday());
+            let since_start = local.time() - block.start;
+            today && since_start >= Duration::zero() && since_start < Duration::minutes(1)
+        });
+        if let Some(block) = block {
+            let key = (local.date(), block.start);
+            if self.last_block != Some(key) {
+                self.last_block = Some(key);
+                self.save_last_block();
+                self.productive_since = None;
+                return Some(AutoStart {
+                    minutes: block.minutes.max(1),
+                    reason: format!("Scheduled focus block at {}", block.start.format("%H:%M")),
+                });
+            }
+        }
+
+        match app_name {
+            Some(app_name) if self.categories.is_productive(app_name, domain) => {
+                let since = *self.productive_since.get_or_insert(now);
+                if now - since >= self.warmup {
+                    self.productive_since = None;
+                    return Some(AutoStart {
+                        minutes: self.work_minutes,
+                        reason: format!("{} minutes of work in {}", self.warmup.num_minutes(), app_name),
+                    });
+                }
+            }
+            _ => self.productive_since = None,
+        }
+        None
+    }
+
+    fn save_last_block(&self) {
+        if let (Some(path), Some(last_block)) = (&self.state_path, &self.last_block) {
+            let result = serde_json::to_string(last_block)
+                .map_err(anyhow::Error::from)
+                .and_then(|json| {
+                    if let Some(dir) = path.parent() {
+                        crate::utils::ensure_directory(&dir.to_path_buf())?;
+                    }
+                    Ok(std::fs::write(path, json)?)
+                });
+            if let Err(e) = result {
+                warn!("Failed to save auto start state: {}", e);
+            }
+        }
+    }
+}
+
+fn load_last_block(path: &Path) -> Option<(NaiveDate, NaiveTime)> {
+    let contents = std::fs::read_to_string(path).ok()?;
+    match serde_json::from_str(&contents) {
+        Ok(last_block) => Some(last_block),
+        Err(e) => {
+            warn!("Ignoring unreadable auto start state {:?}: {}", path, e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Weekday};
+
+    fn config(name: &str, focus_blocks: Vec<FocusBlock>) -> Config {
+        let data_dir = std::env::temp_dir().join(format!("rizeclone-auto-start-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&data_dir);
+        Config {
+            data_dir,
+            timezone: Some("UTC".to_string()),
+            auto_start_focus: true,
+            auto_start_warmup_minutes: 5,
+            default_focus_duration: 25,
+            focus_blocks,
+            ..Config::default()
+        }
+    }
+
+    fn starter(config: &Config) -> AutoStarter {
+        let mut starter = AutoStarter::new();
+        starter.apply_config(config);
+        starter
+    }
+
+    // Tuesday
+    fn at(hour: u32, minute: u32, second: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 3, 5, hour, minute, second).unwrap()
+    }
+
+    fn nine_o_clock(days: Vec<Weekday>) -> FocusBlock {
+        FocusBlock {
+            days,
+            start: NaiveTime::from_hms_opt(9, 0, 0).unwrap(),
+            minutes: 50,
+        }
+    }
+
+    #[test]
+    fn warmup_starts_over_on_a_non_productive_app() {
+        let config = config("warmup", Vec::new());
+        let mut starter = starter(&config);
+
+        assert!(starter.check(Some("code"), None, false, at(10, 0, 0)).is_none());
+        assert!(starter.check(Some("firefox"), Some("youtube.com"), false, at(10, 3, 0)).is_none());
+        assert!(starter.check(Some("code"), None, false, at(10, 4, 0)).is_none());
+        assert!(starter.check(Some("code"), None, false, at(10, 8, 59)).is_none());
+
+        let start = starter.check(Some("code"), None, false, at(10, 9, 0)).unwrap();
+        assert_eq!(start.minutes, 25);
+        assert_eq!(start.reason, "5 minutes of work in code");
+    }
+
+    #[test]
+    fn nothing_starts_while_a_session_is_running() {
+        let config = config("running", vec![nine_o_clock(Vec::new())]);
+        let mut starter = starter(&config);
+
+        assert!(starter.check(Some("code"), None, true, at(8, 50, 0)).is_none());
+        assert!(starter.check(Some("code"), None, true, at(9, 0, 0)).is_none());
+        // The warm-up only counts from the end of the session
+        assert!(starter.check(Some("code"), None, false, at(9, 1, 0)).is_none());
+        assert!(starter.check(Some("code"), None, false, at(9, 6, 0)).is_some());
+    }
+
+    #[test]
+    fn scheduled_block_fires_once_inside_its_minute() {
+        let config = config("block", vec![nine_o_clock(Vec::new())]);
+        let mut starter = starter(&config);
+
+        assert!(starter.check(None, None, false, at(8, 59, 59)).is_none());
+        let start = starter.check(None, None, false, at(9, 0, 10)).unwrap();
+        assert_eq!(start.minutes, 50);
+        assert_eq!(start.reason, "Scheduled focus block at 09:00");
+        assert!(starter.check(None, None, false, at(9, 0, 30)).is_none());
+
+        // Nor after a restart within the same minute
+        let mut restarted = AutoStarter::new();
+        restarted.apply_config(&config);
+        assert!(restarted.check(None, None, false, at(9, 0, 40)).is_none());
+        assert!(restarted.check(None, None, false, at(9, 1, 0)).is_none());
+
+        // The next day it fires again
+        assert!(restarted.check(None, None, false, at(9, 0, 0) + Duration::days(1)).is_some());
+        std::fs::remove_dir_all(&config.data_dir).unwrap();
+    }
+
+    #[test]
+    fn scheduled_block_only_fires_on_its_days() {
+        let config = config("days", vec![nine_o_clock(vec![Weekday::Mon, Weekday::Wed])]);
+        let mut starter = starter(&config);
+
+        assert!(starter.check(None, None, false, at(9, 0, 0)).is_none());
+        assert!(starter.check(None, None, false, at(9, 0, 0) + Duration::days(1)).is_some());
+        std::fs::remove_dir_all(&config.data_dir).unwrap();
+    }
+}
@@ -0,0 +1,367 @@
+use crate::config::{BlockAction, BlockRule, Config};
+use crate::process_tracker::ProcessTracker;
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use log::{info, warn};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+// A blocked app that currently has focus
+struct Offense {
+    app_name: String,
+    since: DateTime<Utc>,
+    enforced: bool,
+}
+
+/// Keeps blocklisted apps out of the way while a focus phase runs. Nothing
+/// happens until a blocked app has stayed focused for the grace period.
+pub struct DistractionBlocker {
+    enabled: bool,
+    rules: Vec<BlockRule>, // apps lowercase
+    grace: Duration,
+    override_duration: Duration,
+    override_until: Option<DateTime<Utc>>,
+    override_log: Option<PathBuf>,
+    offense: Option<Offense>,
+    nag: Option<String>,
+    suspended: Vec<u32>,
+    suspended_path: Option<PathBuf>, // lists `suspended` while it is not empty
+}
+
+impl DistractionBlocker {
+    pub fn new() -> Self {
+        Self {
+            enabled: false,
+            rules: Vec::new(),
+            grace: Duration::seconds(10),
+            override_duration: Duration::minutes(5),
+            override_until: None,
+            override_log: None,
+            offense: None,
+            nag: None,
+            suspended: Vec::new(),
+            suspended_path: None,
+        }
+    }
+
+    pub fn apply_config(&mut self, config: &Config) {
+        self.enabled = config.block_distractions;
+        self.rules = config
+            .blocked_apps
+            .iter()
+            .filter(|rule| !rule.app.trim().is_empty())
+            .map(|rule| BlockRule {
+                app: rule.app.trim().to_lowercase(),
+                action: rule.action,
+            })
+            .collect();
+        self.grace = Duration::seconds(config.block_grace_seconds.max(0));
+        self.override_duration = Duration::minutes(config.emergency_override_minutes.max(1));
+        self.override_log = Some(config.data_dir.join("blocking_overrides.log"));
+        self.suspended_path = Some(config.data_dir.join("suspended_processes.json"));
+        if !self.enabled {
+            self.release();
+        }
+    }
+
+    /// Checks the focused app after a tracker update. `focusing` is whether a
+    /// focus phase is running right now.
+    pub fn check(&mut self, tracker: &ProcessTracker, focusing: bool, now: DateTime<Utc>) {
+        let overridden = self.override_until.map(|until| now < until).unwrap_or(false);
+        if !self.enabled || !focusing || overridden {
+            self.offense = None;
+            self.nag = None;
+            self.release();
+            return;
+        }
+
+        let app_name = match tracker.current_interval() {
+            Some(interval) => interval.app_name.as_str(),
+            None => return, // e.g. this window or an excluded app
+        };
+        let rule = match self.rule_for(app_name) {
+            Some(rule) => rule.clone(),
+            None => {
+                // The reminder stays up until dismissed, even after switching to it
+                self.offense = None;
+                return;
+            }
+        };
+
+        let switched = self.offense.as_ref().map(|offense| offense.app_name != app_name).unwrap_or(true);
+        if switched {
+            self.offense = Some(Offense {
+                app_name: app_name.to_string(),
+                since: now,
+                enforced: false,
+            });
+        }
+        let due = match &mut self.offense {
+            Some(offense) if !offense.enforced && now - offense.since >= self.grace => {
+                offense.enforced = true;
+                true
+            }
+            _ => false,
+        };
+        if !due {
+            return;
+        }
+
+        info!("Blocking {} during focus ({:?})", app_name, rule.action);
+        let app_name = app_name.to_string();
+        let result = match rule.action {
+            BlockAction::Nag => {
+                self.nag = Some(app_name.clone());
+                Ok(())
+            }
+            BlockAction::Minimize => tracker.minimize_focused_window(),
+            BlockAction::Suspend => match tracker.current_pid() {
+                Some(pid) => self.suspend(pid),
+                None => Ok(()),
+            },
+            BlockAction::Kill => match tracker.current_pid() {
+                Some(pid) => kill_process(pid),
+                None => Ok(()),
+            },
+        };
+
+        // Fall back to a reminder when the app could not be dealt with
+        if let Err(e) = result {
+            warn!("Failed to block {}: {}", app_name, e);
+            self.nag = Some(app_name);
+        }
+    }
+
+    fn rule_for(&self, app_name: &str) -> Option<&BlockRule> {
+        let app = app_name.to_lowercase();
+        self.rules.iter().find(|rule| app.contains(rule.app.as_str()))
+    }
+
+    /// The blocked app to remind the user about, if any.
+    pub fn nag(&self) -> Option<&str> {
+        self.nag.as_deref()
+    }
+
+    pub fn dismiss_nag(&mut self) {
+        self.nag = None;
+    }
+
+    pub fn override_until(&self) -> Option<DateTime<Utc>> {
+        self.override_until
+    }
+
+    /// Lifts blocking for `Config::emergency_override_minutes`. Every override
+    /// is appended to `blocking_overrides.log` in the data directory.
+    pub fn emergency_override(&mut self, reason: &str, now: DateTime<Utc>) {
+        let until = now + self.override_duration;
+        warn!("Distraction blocking overridden until {}", until);
+        self.override_until = Some(until);
+        self.offense = None;
+        self.nag = None;
+        self.release();
+
+        if let Err(e) = self.log_override(reason, now) {
+            warn!("Failed to log blocking override: {}", e);
+        }
+    }
+
+    fn log_override(&self, reason: &str, now: DateTime<Utc>) -> Result<()> {
+        let path = match &self.override_log {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        if let Some(dir) = path.parent() {
+            crate::utils::ensure_directory(&dir.to_path_buf())?;
+        }
+        let mut file = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+        writeln!(
+            file,
+            "{}\t{} min\t{}",
+            now.to_rfc3339(),
+            self.override_duration.num_minutes(),
+            reason.replace(['\n', '\t'], " ")
+        )?;
+        Ok(())
+    }
+
+    // The PID is saved before the process is stopped, so a crash or a kill
+    // never leaves it stopped without a record to resume it from
+    fn suspend(&mut self, pid: u32) -> Result<()> {
+        self.suspended.push(pid);
+        self.save_suspended();
+        let result = suspend_process(pid);
+        if result.is_err() {
+            self.suspended.retain(|suspended| *suspended != pid);
+            self.save_suspended();
+        }
+        result
+    }
+
+    fn save_suspended(&self) {
+        let path = match &self.suspended_path {
+            Some(path) => path,
+            None => return,
+        };
+        let result = if !self.suspended.is_empty() {
+            write_pids(path, &self.suspended)
+        } else if path.exists() {
+            std::fs::remove_file(path).map_err(anyhow::Error::from)
+        } else {
+            Ok(())
+        };
+        if let Err(e) = result {
+            warn!("Failed to save suspended processes: {}", e);
+        }
+    }
+
+    /// Resumes every process suspended by this blocker.
+    pub fn release(&mut self) {
+        if self.suspended.is_empty() {
+            return;
+        }
+        for pid in self.suspended.drain(..) {
+            if let Err(e) = resume_process(pid) {
+                warn!("Failed to resume process {}: {}", pid, e);
+            }
+        }
+        self.save_suspended();
+    }
+
+    /// Resumes processes that a previous run suspended and could not resume,
+    /// e.g. because it crashed. Resuming a PID that was reused since is harmless.
+    pub fn restore(&self) {
+        let path = match &self.suspended_path {
+            Some(path) if path.exists() => path,
+            _ => return,
+        };
+        let pids: Vec<u32> = match std::fs::read_to_string(path).map(|json| serde_json::from_str(&json)) {
+            Ok(Ok(pids)) => pids,
+            Ok(Err(e)) => {
+                warn!("Ignoring unreadable list of suspended processes: {}", e);
+                Vec::new()
+            }
+            Err(e) => {
+                warn!("Failed to read suspended processes: {}", e);
+                return;
+            }
+        };
+
+        for pid in pids {
+            info!("Resuming process {} suspended by a previous run", pid);
+            if let Err(e) = resume_process(pid) {
+                warn!("Failed to resume process {}: {}", pid, e);
+            }
+        }
+        if let Err(e) = std::fs::remove_file(path) {
+            warn!("Failed to remove {:?}: {}", path, e);
+        }
+    }
+}
+
+fn write_pids(path: &Path, pids: &[u32]) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        crate::utils::ensure_directory(&dir.to_path_buf())?;
+    }
+    std::fs::write(path, serde_json::to_string(pids)?)?;
+    Ok(())
+}
+
+impl Drop for DistractionBlocker {
+    fn drop(&mut self) {
+        self.release();
+    }
+}
+
+#[cfg(unix)]
+fn send_signal(pid: u32, signal: &str) -> Result<()> {
+    let status = Command::new("kill")
+        .args([signal, &pid.to_string()])
+        .status()?;
+    if !status.success() {
+        anyhow::bail!("kill {} exited with {}", signal, status);
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn suspend_process(pid: u32) -> Result<()> {
+    send_signal(pid, "-STOP")
+}
+
+#[cfg(not(unix))]
+fn suspend_process(_pid: u32) -> Result<()> {
+    anyhow::bail!("Suspending processes is not supported on this platform")
+}
+
+#[cfg(unix)]
+fn resume_process(pid: u32) -> Result<()> {
+    send_signal(pid, "-CONT")
+}
+
+#[cfg(not(unix))]
+fn resume_process(_pid: u32) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(unix)]
+fn kill_process(pid: u32) -> Result<()> {
+    send_signal(pid, "-TERM")
+}
+
+#[cfg(windows)]
+fn kill_process(pid: u32) -> Result<()> {
+    let status = Command::new("taskkill")
+        .args(["/PID", &pid.to_string()])
+        .status()?;
+    if !status.success() {
+        anyhow::bail!("taskkill exited with {}", status);
+    }
+    Ok(())
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    // The state letter from /proc/<pid>/stat, 'T' when stopped
+    fn process_state(pid: u32) -> char {
+        let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).unwrap();
+        stat[stat.rfind(')').unwrap() + 2..].chars().next().unwrap()
+    }
+
+    // Signals are delivered asynchronously, so wait a little for the state to settle
+    fn wait_for_state(pid: u32, stopped: bool) -> bool {
+        for _ in 0..100 {
+            if (process_state(pid) == 'T') == stopped {
+                return true;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        false
+    }
+
+    #[test]
+    fn processes_left_suspended_by_a_crash_are_resumed() {
+        let path = std::env::temp_dir().join(format!("rizeclone-suspended-{}.json", std::process::id()));
+        let mut child = Command::new("sleep").arg("30").spawn().unwrap();
+
+        let mut blocker = DistractionBlocker::new();
+        blocker.suspended_path = Some(path.clone());
+        blocker.suspend(child.id()).unwrap();
+        assert!(wait_for_state(child.id(), true));
+        // A crash skips Drop
+        std::mem::forget(blocker);
+
+        let mut restarted = DistractionBlocker::new();
+        restarted.suspended_path = Some(path.clone());
+        restarted.release();
+        assert!(path.exists(), "releasing nothing must keep the saved list");
+        restarted.restore();
+        assert!(wait_for_state(child.id(), false));
+        assert!(!path.exists());
+
+        child.kill().unwrap();
+        child.wait().unwrap();
+    }
+}
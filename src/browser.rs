@@ -0,0 +1,172 @@
+use chrono::{DateTime, Duration, Utc};
+use log::debug;
+use regex::Regex;
+use serde::Deserialize;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+const BROWSER_APPS: [&str; 11] = [
+    "firefox", "chrome", "chromium", "brave", "edge", "vivaldi", "opera", "safari", "librewolf",
+    "waterfox", "zen",
+];
+
+// Sites whose titles identify them even when the URL is not shown
+const KNOWN_SITE_TITLES: [(&str, &str); 14] = [
+    ("YouTube", "youtube.com"),
+    ("GitHub", "github.com"),
+    ("GitLab", "gitlab.com"),
+    ("Stack Overflow", "stackoverflow.com"),
+    ("Gmail", "mail.google.com"),
+    ("Google Docs", "docs.google.com"),
+    ("Google Search", "google.com"),
+    ("Wikipedia", "wikipedia.org"),
+    ("Reddit", "reddit.com"),
+    ("X", "x.com"),
+    ("Twitter", "x.com"),
+    ("Netflix", "netflix.com"),
+    ("Twitch", "twitch.tv"),
+    ("LinkedIn", "linkedin.com"),
+];
+
+// Active tab reports older than this are ignored
+const TAB_REPORT_MAX_AGE_SECS: i64 = 10;
+
+pub fn is_browser(app_name: &str) -> bool {
+    let app = app_name.to_lowercase();
+    BROWSER_APPS.iter().any(|browser| app.contains(browser))
+}
+
+/// Extracts the site a browser window is showing from its title, either from a
+/// URL/hostname in the title or from the title of a well-known site.
+pub fn domain_from_title(title: &str) -> Option<String> {
+    let page_title = strip_browser_suffix(title);
+
+    if let Some(captures) = url_regex().captures(page_title) {
+        return Some(normalize_host(&captures[1]));
+    }
+    if let Some(captures) = hostname_regex().captures(page_title) {
+        return Some(normalize_host(&captures[1]));
+    }
+
+    KNOWN_SITE_TITLES
+        .iter()
+        .find(|(marker, _)| {
+            [" - ", " – ", " — ", " · ", " | ", " / "]
+                .iter()
+                .any(|separator| page_title.ends_with(&format!("{}{}", separator, marker)))
+                || page_title == *marker
+        })
+        .map(|(_, domain)| domain.to_string())
+}
+
+/// Returns the host part of a URL without the scheme, port or a leading "www.".
+pub fn domain_from_url(url: &str) -> Option<String> {
+    let rest = url.split_once("://").map(|(_, rest)| rest).unwrap_or(url);
+    let host = rest
+        .split(['/', '?', '#'])
+        .next()?
+        .rsplit('@')
+        .next()?
+        .split(':')
+        .next()?;
+    if host.is_empty() {
+        None
+    } else {
+        Some(normalize_host(host))
+    }
+}
+
+fn strip_browser_suffix(title: &str) -> &str {
+    for separator in [" - ", " — ", " – "] {
+        if let Some((page, browser)) = title.rsplit_once(separator) {
+            if is_browser(browser) {
+                return page.trim();
+            }
+        }
+    }
+    title.trim()
+}
+
+fn normalize_host(host: &str) -> String {
+    let host = host.to_lowercase();
+    host.strip_prefix("www.").map(str::to_string).unwrap_or(host)
+}
+
+fn url_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| Regex::new(r"(?i)\bhttps?://([a-z0-9.-]+)").unwrap())
+}
+
+// Restricted to common TLDs so file names like "main.rs" are not mistaken for hosts
+fn hostname_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| {
+        Regex::new(
+            r"(?i)\b((?:[a-z0-9](?:[a-z0-9-]*[a-z0-9])?\.)+(?:com|org|net|io|dev|edu|gov|app|ai|co|me|tv|info|xyz|uk|de|fr|ru|jp))\b",
+        )
+        .unwrap()
+    })
+}
+
+#[derive(Debug, Deserialize)]
+struct TabReport {
+    url: String,
+    updated: DateTime<Utc>,
+}
+
+/// Reads the active tab URL written by a browser native-messaging host.
+///
+/// The host writes `{"url": "...", "updated": "<RFC 3339 timestamp>"}` to a
+/// local file whenever the active tab changes.
+pub struct ActiveTabSource {
+    path: PathBuf,
+}
+
+impl ActiveTabSource {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    pub fn current_domain(&self) -> Option<String> {
+        let contents = std::fs::read_to_string(&self.path).ok()?;
+        let report: TabReport = match serde_json::from_str(&contents) {
+            Ok(report) => report,
+            Err(e) => {
+                debug!("Ignoring malformed active tab report {:?}: {}", self.path, e);
+                return None;
+            }
+        };
+
+        if Utc::now() - report.updated > Duration::seconds(TAB_REPORT_MAX_AGE_SECS) {
+            return None;
+        }
+        domain_from_url(&report.url)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn domains_from_urls_and_hostnames_in_titles() {
+        assert_eq!(
+            domain_from_title("https://www.Example.com/page - Mozilla Firefox").as_deref(),
+            Some("example.com")
+        );
+        assert_eq!(domain_from_title("news.ycombinator.com").as_deref(), Some("news.ycombinator.com"));
+    }
+
+    #[test]
+    fn domains_from_well_known_site_titles() {
+        assert_eq!(domain_from_title("Rust in 100 Seconds - YouTube - Google Chrome").as_deref(), Some("youtube.com"));
+        assert_eq!(domain_from_title("Inbox (3) - Gmail").as_deref(), Some("mail.google.com"));
+        assert_eq!(domain_from_title("Pull requests · GitHub — Mozilla Firefox").as_deref(), Some("github.com"));
+    }
+
+    #[test]
+    fn file_names_are_not_domains() {
+        assert_eq!(domain_from_title("main.rs - Mozilla Firefox"), None);
+        assert_eq!(domain_from_title("New Tab - Brave"), None);
+    }
+}
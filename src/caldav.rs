@@ -0,0 +1,531 @@
+use crate::calendar::{Calendar, DayModel, FocusSessionSummary, PlannedEvent};
+use crate::config::Config;
+use crate::crypto;
+use crate::ics;
+use anyhow::{anyhow, Result};
+use base64::Engine;
+use chrono::{DateTime, Duration, Utc};
+use log::{error, info, warn};
+use quick_xml::events::Event;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
+
+const REQUEST_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+/// Sessions older than this are not pushed any more.
+const PUSH_DAYS: i64 = 30;
+/// UIDs of forgotten sessions still to be deleted on the server.
+const DELETIONS_FILE: &str = "caldav-deletions.json";
+
+// The GUI queues deletions while the sync thread works through them
+static DELETIONS_LOCK: Mutex<()> = Mutex::new(());
+
+/// Talks to one CalDAV server: focus sessions are written as one event
+/// resource each, named after the event's UID so pushing a changed session
+/// replaces it, and planned events are read with a calendar query.
+pub struct CalDavClient {
+    agent: ureq::Agent,
+    calendar_url: String, // collection URLs end with a slash
+    plan_url: String,
+    authorization: Option<String>,
+    deletions_path: PathBuf,
+}
+
+impl CalDavClient {
+    /// `None` when sync is turned off or no server is configured.
+    pub fn from_config(config: &Config) -> Result<Option<Self>> {
+        let server = match &config.caldav_url {
+            Some(server) if config.caldav_enabled => server.trim_end_matches('/'),
+            _ => return Ok(None),
+        };
+        let collection = |path: &str| format!("{}/{}/", server, path.trim_matches('/'));
+
+        // Configs from before the keyring was used may still hold the password
+        let password = if !config.caldav_password_command.is_empty() {
+            Some(password_from_command(&config.caldav_password_command)?)
+        } else {
+            match crypto::caldav_password() {
+                Ok(Some(password)) => Some(password),
+                Ok(None) => config.caldav_password.clone(),
+                Err(e) => {
+                    warn!("Failed to read the CalDAV password from the keyring: {:#}", e);
+                    config.caldav_password.clone()
+                }
+            }
+        };
+        let authorization = config.caldav_username.as_ref().map(|username| {
+            let credentials = format!("{}:{}", username, password.unwrap_or_default());
+            format!("Basic {}", base64::engine::general_purpose::STANDARD.encode(credentials))
+        });
+
+        Ok(Some(Self {
+            agent: ureq::AgentBuilder::new().timeout(REQUEST_TIMEOUT).build(),
+            calendar_url: collection(&config.caldav_calendar_path),
+            plan_url: collection(config.caldav_plan_path.as_deref().unwrap_or(&config.caldav_calendar_path)),
+            authorization,
+            deletions_path: config.data_dir.join(DELETIONS_FILE),
+        }))
+    }
+
+    pub fn push_session(&self, session: &FocusSessionSummary) -> Result<()> {
+        self.request("PUT", &self.event_url(&ics::session_uid(session)))
+            .set("Content-Type", "text/calendar; charset=utf-8")
+            .send_string(&ics::session_calendar(session))?;
+        Ok(())
+    }
+
+    /// Deletes the event with `uid`. Events that are already gone count as deleted.
+    pub fn delete_event(&self, uid: &str) -> Result<()> {
+        match self.request("DELETE", &self.event_url(uid)).call() {
+            Ok(_) | Err(ureq::Error::Status(404, _)) => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    // Event resources are named after the part of the UID before the '@'
+    fn event_url(&self, uid: &str) -> String {
+        format!("{}{}.ics", self.calendar_url, uid.split('@').next().unwrap_or(uid))
+    }
+
+    /// Events between `from` and `until`, without the ones this app pushed.
+    pub fn pull_planned(&self, day_model: &DayModel, from: DateTime<Utc>, until: DateTime<Utc>) -> Result<Vec<PlannedEvent>> {
+        let format = |time: DateTime<Utc>| time.format("%Y%m%dT%H%M%SZ").to_string();
+        let query = format!(
+            r#"<?xml version="1.0" encoding="utf-8"?>
+<c:calendar-query xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+  <d:prop><c:calendar-data/></d:prop>
+  <c:filter>
+    <c:comp-filter name="VCALENDAR">
+      <c:comp-filter name="VEVENT"><c:time-range start="{}" end="{}"/></c:comp-filter>
+    </c:comp-filter>
+  </c:filter>
+</c:calendar-query>"#,
+            format(from),
+            format(until)
+        );
+        let response = self
+            .request("REPORT", &self.plan_url)
+            .set("Depth", "1")
+            .set("Content-Type", "application/xml; charset=utf-8")
+            .send_string(&query)?
+            .into_string()?;
+
+        let source = self.plan_source();
+        Ok(calendar_data(&response)?
+            .iter()
+            .flat_map(|data| ics::import(data, &source, day_model, from, until))
+            .filter(|event| !ics::is_own_uid(&event.uid))
+            .collect())
+    }
+
+    /// What pulled events are recorded as coming from.
+    pub fn plan_source(&self) -> String {
+        format!("caldav:{}", self.plan_url)
+    }
+
+    fn request(&self, method: &str, url: &str) -> ureq::Request {
+        let request = self.agent.request(method, url);
+        match &self.authorization {
+            Some(authorization) => request.set("Authorization", authorization),
+            None => request,
+        }
+    }
+}
+
+/// Wakes the background sync and reports how the last one went.
+#[derive(Clone)]
+pub struct SyncHandle {
+    wake: Arc<Notify>,
+    status: Arc<Mutex<Option<String>>>,
+}
+
+impl SyncHandle {
+    pub fn sync_now(&self) {
+        self.wake.notify_one();
+    }
+
+    pub fn status(&self) -> Option<String> {
+        self.status.lock().ok().and_then(|status| status.clone())
+    }
+}
+
+/// Syncs whenever the handle asks for it and every `interval` after that.
+/// The first sync waits for `sync_now`, so it can run once the stored
+/// history is loaded. Requests block, so each sync runs on the blocking
+/// thread pool.
+pub fn spawn_sync(
+    client: CalDavClient,
+    calendar: Arc<Mutex<Calendar>>,
+    day_model: DayModel,
+    interval: Duration,
+) -> SyncHandle {
+    let handle = SyncHandle {
+        wake: Arc::new(Notify::new()),
+        status: Arc::new(Mutex::new(None)),
+    };
+    let wake = handle.wake.clone();
+    let status = handle.status.clone();
+    let client = Arc::new(client);
+    let interval = interval.to_std().unwrap_or(std::time::Duration::from_secs(15 * 60));
+
+    tokio::spawn(async move {
+        wake.notified().await;
+        loop {
+            let sync_client = client.clone();
+            let sync_calendar = calendar.clone();
+            let result = tokio::task::spawn_blocking(move || sync(&sync_client, &sync_calendar, &day_model)).await;
+            let message = match result {
+                Ok(Ok(message)) => {
+                    info!("CalDAV sync: {}", message);
+                    format!("{} at {}", message, day_model.local_time(Utc::now()).format("%H:%M"))
+                }
+                Ok(Err(e)) => {
+                    warn!("CalDAV sync failed: {:#}", e);
+                    format!("Sync failed: {:#}", e)
+                }
+                Err(e) => format!("Sync failed: {}", e),
+            };
+            if let Ok(mut status) = status.lock() {
+                *status = Some(message);
+            }
+
+            tokio::select! {
+                _ = tokio::time::sleep(interval) => {}
+                _ = wake.notified() => {}
+            }
+        }
+    });
+
+    handle
+}
+
+fn sync(client: &CalDavClient, calendar: &Mutex<Calendar>, day_model: &DayModel) -> Result<String> {
+    let today = day_model.today();
+    let sessions = calendar
+        .lock()
+        .map_err(|_| anyhow!("Calendar lock poisoned"))?
+        .unsynced_sessions(today - Duration::days(PUSH_DAYS));
+
+    // A failed push is retried with the next sync
+    let mut pushed = 0;
+    for session in &sessions {
+        match client.push_session(session) {
+            Ok(()) => {
+                if let Ok(mut calendar) = calendar.lock() {
+                    calendar.mark_synced(session);
+                }
+                pushed += 1;
+            }
+            Err(e) => warn!("Failed to push focus session: {:#}", e),
+        }
+    }
+
+    let forgotten = read_deletions(&client.deletions_path)?;
+    let deleted: Vec<&String> = forgotten
+        .iter()
+        .filter(|uid| match client.delete_event(uid) {
+            Ok(()) => true,
+            Err(e) => {
+                warn!("Failed to delete forgotten focus session: {:#}", e);
+                false
+            }
+        })
+        .collect();
+    update_deletions(&client.deletions_path, |pending| pending.retain(|uid| !deleted.contains(&uid)))?;
+
+    let events = client.pull_planned(
+        day_model,
+        day_model.start_of(today - Duration::days(ics::PLAN_PAST_DAYS)),
+        day_model.end_of(today + Duration::days(ics::PLAN_FUTURE_DAYS)),
+    )?;
+    let pulled = events.len();
+    let changed = calendar
+        .lock()
+        .map_err(|_| anyhow!("Calendar lock poisoned"))?
+        .replace_planned_events(&client.plan_source(), events);
+
+    let mut message = format!("Pushed {} of {} sessions, pulled {} events", pushed, sessions.len(), pulled);
+    if !changed {
+        message.push_str(" (unchanged)");
+    }
+    if !forgotten.is_empty() {
+        message.push_str(&format!(", deleted {} of {} forgotten sessions", deleted.len(), forgotten.len()));
+    }
+    Ok(message)
+}
+
+/// Stores the CalDAV password in the OS keyring. Without a usable keyring
+/// it stays in the config file.
+pub fn store_password(config: &mut Config, password: String) {
+    match crypto::store_caldav_password(&password) {
+        Ok(()) => config.caldav_password = None,
+        Err(e) => {
+            warn!("Keeping the CalDAV password in the config file: {:#}", e);
+            config.caldav_password = Some(password);
+        }
+    }
+}
+
+/// Remembers the events of forgotten focus sessions, so the next sync
+/// deletes them from the server too.
+pub fn queue_deletions(config: &Config, sessions: &[FocusSessionSummary]) {
+    if !config.caldav_enabled || sessions.is_empty() {
+        return;
+    }
+    let path = config.data_dir.join(DELETIONS_FILE);
+    if let Err(e) = update_deletions(&path, |pending| pending.extend(sessions.iter().map(ics::session_uid))) {
+        error!("Failed to queue forgotten sessions for deletion: {:#}", e);
+    }
+}
+
+fn read_deletions(path: &Path) -> Result<Vec<String>> {
+    let _lock = DELETIONS_LOCK.lock().map_err(|_| anyhow!("Deletions lock poisoned"))?;
+    read_deletions_file(path)
+}
+
+fn read_deletions_file(path: &Path) -> Result<Vec<String>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+}
+
+fn update_deletions(path: &Path, change: impl FnOnce(&mut Vec<String>)) -> Result<()> {
+    let _lock = DELETIONS_LOCK.lock().map_err(|_| anyhow!("Deletions lock poisoned"))?;
+    let mut pending = read_deletions_file(path)?;
+    change(&mut pending);
+    pending.sort();
+    pending.dedup();
+
+    if pending.is_empty() {
+        if path.exists() {
+            std::fs::remove_file(path)?;
+        }
+        return Ok(());
+    }
+    let tmp_path = path.with_extension("json.tmp");
+    std::fs::write(&tmp_path, serde_json::to_string(&pending)?)?;
+    std::fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+// The text of every <calendar-data> element in a multistatus response
+fn calendar_data(response: &str) -> Result<Vec<String>> {
+    let mut reader = quick_xml::Reader::from_str(response);
+    let mut data = Vec::new();
+    let mut current: Option<String> = None;
+    loop {
+        match reader.read_event()? {
+            Event::Start(element) if element.local_name().as_ref() == b"calendar-data" => {
+                current = Some(String::new());
+            }
+            Event::Text(text) => {
+                if let Some(current) = current.as_mut() {
+                    current.push_str(&text.unescape()?);
+                }
+            }
+            Event::CData(text) => {
+                if let Some(current) = current.as_mut() {
+                    current.push_str(&String::from_utf8_lossy(&text));
+                }
+            }
+            Event::End(element) if element.local_name().as_ref() == b"calendar-data" => {
+                data.extend(current.take());
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(data)
+}
+
+fn password_from_command(command: &[String]) -> Result<String> {
+    let output = Command::new(&command[0]).args(&command[1..]).output()?;
+    if !output.status.success() {
+        return Err(anyhow!("{} exited with {}", command[0], output.status));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim_end_matches(['\r', '\n']).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::thread::JoinHandle;
+
+    struct Request {
+        method: String,
+        path: String,
+        authorization: Option<String>,
+        body: String,
+    }
+
+    // Answers one request per canned response, in order, and returns what it was sent
+    fn serve(responses: Vec<(u16, String)>) -> (String, JoinHandle<Vec<Request>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let handle = std::thread::spawn(move || {
+            let mut requests = Vec::new();
+            for (status, body) in responses {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream);
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                let mut parts = line.split_whitespace();
+                let (method, path) = (parts.next().unwrap().to_string(), parts.next().unwrap().to_string());
+
+                let (mut length, mut authorization) = (0, None);
+                loop {
+                    let mut header = String::new();
+                    reader.read_line(&mut header).unwrap();
+                    let header = header.trim_end();
+                    if header.is_empty() {
+                        break;
+                    }
+                    let (name, value) = header.split_once(": ").unwrap();
+                    match name.to_ascii_lowercase().as_str() {
+                        "content-length" => length = value.parse().unwrap(),
+                        "authorization" => authorization = Some(value.to_string()),
+                        _ => {}
+                    }
+                }
+                let mut body_bytes = vec![0; length];
+                reader.read_exact(&mut body_bytes).unwrap();
+                requests.push(Request {
+                    method,
+                    path,
+                    authorization,
+                    body: String::from_utf8(body_bytes).unwrap(),
+                });
+
+                let response = format!(
+                    "HTTP/1.1 {} Status\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+                reader.into_inner().write_all(response.as_bytes()).unwrap();
+            }
+            requests
+        });
+        (url, handle)
+    }
+
+    fn multistatus(calendars: &[String]) -> String {
+        let responses: String = calendars
+            .iter()
+            .map(|data| {
+                format!(
+                    "<d:response><d:href>/e.ics</d:href><d:propstat><d:prop><c:calendar-data><![CDATA[{}]]></c:calendar-data></d:prop></d:propstat></d:response>",
+                    data
+                )
+            })
+            .collect();
+        format!(
+            r#"<?xml version="1.0"?><d:multistatus xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">{}</d:multistatus>"#,
+            responses
+        )
+    }
+
+    fn event(uid: &str, start: DateTime<Utc>) -> String {
+        format!(
+            "BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nUID:{}\r\nDTSTART:{}\r\nDURATION:PT1H\r\nSUMMARY:Planning\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n",
+            uid,
+            start.format("%Y%m%dT%H%M%SZ")
+        )
+    }
+
+    fn session(start_time: DateTime<Utc>) -> FocusSessionSummary {
+        FocusSessionSummary {
+            start_time,
+            duration: Duration::minutes(25),
+            music_used: false,
+            tracks_played: Vec::new(),
+            apps_used: Vec::new(),
+            distracted: Duration::zero(),
+            interruptions: 0,
+            quality_score: None,
+            intent: Some("Write the report".to_string()),
+            tags: Vec::new(),
+            rating: None,
+            note: None,
+            synced: false,
+        }
+    }
+
+    fn config(url: &str, data_dir: &Path) -> Config {
+        Config {
+            data_dir: data_dir.to_path_buf(),
+            caldav_enabled: true,
+            caldav_url: Some(url.to_string()),
+            caldav_calendar_path: "/alice/focus/".to_string(),
+            caldav_username: Some("alice".to_string()),
+            caldav_password_command: vec!["echo".to_string(), "secret".to_string()],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn calendar_data_reads_text_and_cdata() {
+        let response = r#"<d:multistatus xmlns:d="DAV:" xmlns:C="urn:ietf:params:xml:ns:caldav">
+            <d:response><d:propstat><d:prop><C:calendar-data>SUMMARY:Tom &amp; Jerry</C:calendar-data></d:prop></d:propstat></d:response>
+            <d:response><d:propstat><d:prop><C:calendar-data><![CDATA[SUMMARY:<raw>]]></C:calendar-data></d:prop></d:propstat></d:response>
+            <d:response><d:propstat><d:prop><d:getetag>"1"</d:getetag></d:prop></d:propstat></d:response>
+        </d:multistatus>"#;
+        assert_eq!(calendar_data(response).unwrap(), ["SUMMARY:Tom & Jerry", "SUMMARY:<raw>"]);
+    }
+
+    #[test]
+    fn sync_pushes_deletes_and_pulls() {
+        let data_dir = std::env::temp_dir().join(format!("rizeclone-caldav-{}", std::process::id()));
+        std::fs::create_dir_all(&data_dir).unwrap();
+        let day_model = DayModel::default();
+        let start = Utc::now() - Duration::hours(2);
+        let planned = Utc::now() + Duration::days(1);
+        let report = multistatus(&[event("planning-1", planned), event("focus-1@rizeclone", planned)]);
+        let (url, server) = serve(vec![
+            (201, String::new()),
+            (404, String::new()),
+            (207, report.clone()),
+            (207, report),
+        ]);
+
+        let config = config(&url, &data_dir);
+        let client = CalDavClient::from_config(&config).unwrap().unwrap();
+        let calendar = Mutex::new(Calendar::new());
+        calendar.lock().unwrap().add_focus_session(session(start)).unwrap();
+        let forgotten = session(start - Duration::days(3));
+        queue_deletions(&config, std::slice::from_ref(&forgotten));
+
+        let message = sync(&client, &calendar, &day_model).unwrap();
+        assert!(message.starts_with("Pushed 1 of 1 sessions, pulled 1 events,"), "{}", message);
+        assert!(message.ends_with("deleted 1 of 1 forgotten sessions"), "{}", message);
+        {
+            let calendar = calendar.lock().unwrap();
+            assert!(calendar.unsynced_sessions(day_model.today() - Duration::days(1)).is_empty());
+            let events = calendar.planned_events_for_day(day_model.day_of(planned));
+            assert_eq!(events.len(), 1);
+            assert_eq!(events[0].uid, "planning-1");
+        }
+        assert!(!data_dir.join(DELETIONS_FILE).exists());
+
+        // Nothing left to push or delete, and the plan is the same
+        let message = sync(&client, &calendar, &day_model).unwrap();
+        assert_eq!(message, "Pushed 0 of 0 sessions, pulled 1 events (unchanged)");
+
+        let requests = server.join().unwrap();
+        let methods: Vec<_> = requests.iter().map(|request| request.method.as_str()).collect();
+        assert_eq!(methods, ["PUT", "DELETE", "REPORT", "REPORT"]);
+        assert_eq!(requests[0].path, format!("/alice/focus/focus-{}.ics", start.timestamp()));
+        assert!(requests[0].body.contains("SUMMARY:Focus: Write the report"));
+        assert_eq!(requests[1].path, format!("/alice/focus/focus-{}.ics", forgotten.start_time.timestamp()));
+        assert_eq!(requests[2].path, "/alice/focus/");
+        assert!(requests[2].body.contains("<c:time-range"));
+        let expected = format!("Basic {}", base64::engine::general_purpose::STANDARD.encode("alice:secret"));
+        assert!(requests.iter().all(|request| request.authorization.as_deref() == Some(expected.as_str())));
+
+        let _ = std::fs::remove_dir_all(&data_dir);
+    }
+}
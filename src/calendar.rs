@@ -1,67 +1,1220 @@
+use crate::category::CategoryResolver;
+use crate::config::Config;
+use crate::privacy::PrivacyFilter;
+use crate::process_tracker::ActivityInterval;
 use anyhow::Result;
-use chrono::{DateTime, Datelike, Duration, Utc};
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use log::warn;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+
+pub const UNCATEGORIZED: &str = "Uncategorized";
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct DailyActivity {
     pub date: DateTime<Utc>,
     pub process_durations: HashMap<String, Duration>,
     pub focus_sessions: Vec<FocusSessionSummary>,
+    #[serde(default)]
+    pub project_durations: HashMap<String, Duration>,
+    #[serde(default)]
+    pub domain_durations: HashMap<String, Duration>,
+    #[serde(default)]
+    pub intervals: Vec<ActivityInterval>,
+    #[serde(default)]
+    pub compacted: bool, // raw intervals dropped by the retention policy
+    #[serde(default)]
+    pub journal: Journal,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+impl DailyActivity {
+    fn new(date: DateTime<Utc>) -> Self {
+        Self {
+            date,
+            process_durations: HashMap::new(),
+            focus_sessions: Vec::new(),
+            project_durations: HashMap::new(),
+            domain_durations: HashMap::new(),
+            intervals: Vec::new(),
+            compacted: false,
+            journal: Journal::default(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.process_durations.is_empty()
+            && self.focus_sessions.is_empty()
+            && self.intervals.is_empty()
+            && self.journal.is_empty()
+    }
+
+    pub fn total_time(&self) -> Duration {
+        self.process_durations.values().copied().sum()
+    }
+
+    pub fn focus_time(&self) -> Duration {
+        self.focus_sessions.iter().map(|session| session.duration).sum()
+    }
+
+    /// Time per category, longest first. Uses the intervals when the day
+    /// still has them, so websites count towards their own category.
+    pub fn category_durations(&self, categories: &CategoryResolver) -> Vec<(String, Duration)> {
+        let mut totals: HashMap<String, Duration> = HashMap::new();
+        let mut add = |app_name: &str, domain: Option<&str>, duration: Duration| {
+            let category = categories.categorize(app_name, domain).unwrap_or(UNCATEGORIZED);
+            *totals.entry(category.to_string()).or_insert(Duration::zero()) += duration;
+        };
+
+        if self.intervals.is_empty() {
+            for (app_name, duration) in &self.process_durations {
+                add(app_name, None, *duration);
+            }
+        } else {
+            for interval in &self.intervals {
+                add(&interval.app_name, interval.domain.as_deref(), interval.duration());
+            }
+        }
+
+        let mut totals: Vec<_> = totals.into_iter().collect();
+        totals.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        totals
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FocusSessionSummary {
     pub start_time: DateTime<Utc>,
     pub duration: Duration,
     pub music_used: bool,
+    #[serde(default)]
+    pub tracks_played: Vec<String>,
+    #[serde(default)]
+    pub apps_used: Vec<String>, // longest first
+    #[serde(default = "Duration::zero")]
+    pub distracted: Duration,
+    #[serde(default)]
+    pub interruptions: u32,
+    #[serde(default)]
+    pub quality_score: Option<u8>, // 0–100, not recorded for older sessions
+    #[serde(default)]
+    pub intent: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub rating: Option<u8>, // 1–5, given after the session
+    #[serde(default)]
+    pub note: Option<String>,
+    #[serde(default)]
+    pub synced: bool, // pushed to CalDAV since the last change
+}
+
+/// A day's free-text reflection and highlights.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Journal {
+    pub note: String,
+    pub went_well: Vec<String>,
+    pub blockers: Vec<String>,
+}
+
+impl Journal {
+    pub fn is_empty(&self) -> bool {
+        self.note.trim().is_empty() && self.went_well.is_empty() && self.blockers.is_empty()
+    }
+}
+
+/// A meeting or planned block imported from an iCalendar file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlannedEvent {
+    pub uid: String,
+    pub summary: String,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub meeting: bool,  // has attendees
+    pub source: String, // the file it was imported from
+}
+
+/// How much of a planned block was spent on what it was planned for.
+pub struct Adherence {
+    pub planned: Duration,
+    pub tracked: Duration, // any activity during the block
+    pub on_plan: Duration, // activity in the block's target category
+}
+
+impl Adherence {
+    pub fn percent(&self) -> u8 {
+        let planned = self.planned.num_seconds().max(1) as f64;
+        (self.on_plan.num_seconds() as f64 / planned * 100.0).clamp(0.0, 100.0).round() as u8
+    }
+}
+
+/// Selects focus sessions by tag and by text in their intent or note.
+#[derive(Debug, Clone, Default)]
+pub struct SessionFilter {
+    pub tag: Option<String>,
+    pub text: Option<String>,
+}
+
+impl SessionFilter {
+    /// Parses "#tag words": a leading `#tag` selects the tag, the rest is
+    /// matched against intents and notes.
+    pub fn parse(query: &str) -> Self {
+        let mut filter = Self::default();
+        let mut words = Vec::new();
+        for word in query.split_whitespace() {
+            match word.strip_prefix('#') {
+                Some(tag) if filter.tag.is_none() && !tag.is_empty() => filter.tag = Some(tag.to_lowercase()),
+                _ => words.push(word),
+            }
+        }
+        if !words.is_empty() {
+            filter.text = Some(words.join(" ").to_lowercase());
+        }
+        filter
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tag.is_none() && self.text.is_none()
+    }
+
+    pub fn matches(&self, session: &FocusSessionSummary) -> bool {
+        let tag_matches = self
+            .tag
+            .as_ref()
+            .map(|tag| session.tags.iter().any(|session_tag| session_tag.to_lowercase() == *tag))
+            .unwrap_or(true);
+        let text_matches = self
+            .text
+            .as_ref()
+            .map(|text| {
+                [&session.intent, &session.note]
+                    .into_iter()
+                    .flatten()
+                    .any(|field| field.to_lowercase().contains(text.as_str()))
+            })
+            .unwrap_or(true);
+        tag_matches && text_matches
+    }
+}
+
+/// Decides which calendar day a moment belongs to: days follow the
+/// configured timezone (the system's by default) and begin at
+/// `day_start_hour`, so late-night work can count towards the previous day.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DayModel {
+    timezone: Option<Tz>,
+    day_start_hour: u32,
+}
+
+impl DayModel {
+    pub fn from_config(config: &Config) -> Self {
+        let timezone = config.timezone.as_deref().and_then(|name| match name.parse::<Tz>() {
+            Ok(tz) => Some(tz),
+            Err(_) => {
+                warn!("Unknown timezone {:?}, using the system timezone", name);
+                None
+            }
+        });
+
+        Self {
+            timezone,
+            day_start_hour: config.day_start_hour.min(23),
+        }
+    }
+
+    /// Wall-clock time in the configured timezone.
+    pub fn local_time(&self, time: DateTime<Utc>) -> NaiveDateTime {
+        match self.timezone {
+            Some(tz) => time.with_timezone(&tz).naive_local(),
+            None => time.with_timezone(&Local).naive_local(),
+        }
+    }
+
+    pub fn day_of(&self, time: DateTime<Utc>) -> NaiveDate {
+        (self.local_time(time) - Duration::hours(self.day_start_hour as i64)).date()
+    }
+
+    pub fn today(&self) -> NaiveDate {
+        self.day_of(Utc::now())
+    }
+
+    /// The moment `day` begins. When a DST change skips that wall-clock time,
+    /// the day begins at the first valid time after it.
+    pub fn start_of(&self, day: NaiveDate) -> DateTime<Utc> {
+        self.resolve_local(day.and_hms_opt(self.day_start_hour, 0, 0).unwrap_or_default())
+    }
+
+    /// The moment a wall-clock time in the configured timezone refers to,
+    /// moved past DST gaps like `start_of`.
+    pub fn resolve_local(&self, mut local: NaiveDateTime) -> DateTime<Utc> {
+        for _ in 0..3 {
+            let resolved = match self.timezone {
+                Some(tz) => tz.from_local_datetime(&local).earliest().map(|time| time.with_timezone(&Utc)),
+                None => Local.from_local_datetime(&local).earliest().map(|time| time.with_timezone(&Utc)),
+            };
+            if let Some(resolved) = resolved {
+                return resolved;
+            }
+            local += Duration::hours(1);
+        }
+        local.and_utc()
+    }
+
+    pub fn end_of(&self, day: NaiveDate) -> DateTime<Utc> {
+        self.start_of(day + Duration::days(1))
+    }
 }
 
 pub struct Calendar {
-    activities: HashMap<String, DailyActivity>, // Key: YYYY-MM-DD
+    activities: HashMap<String, DailyActivity>, // Key: YYYY-MM-DD of the local day
+    planned: HashMap<String, Vec<PlannedEvent>>, // by the day they start; imported on every start, never saved
+    longest_planned: Duration,                   // how far back an event overlapping a day may start
+    dirty: DirtyDays,
+    day_model: DayModel,
+}
+
+// Days changed since the last save, and the revision each day last changed
+// at, so views can tell when what they derived from a day is stale
+#[derive(Default)]
+struct DirtyDays {
+    unsaved: HashSet<String>,
+    revisions: HashMap<String, u64>,
+    revision: u64,
+}
+
+impl DirtyDays {
+    fn insert(&mut self, key: String) {
+        self.revision += 1;
+        self.revisions.insert(key.clone(), self.revision);
+        self.unsaved.insert(key);
+    }
+}
+
+fn date_key(date: &impl Datelike) -> String {
+    format!("{}-{:02}-{:02}", date.year(), date.month(), date.day())
+}
+
+fn parse_date_key(key: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(key, "%Y-%m-%d").ok()
+}
+
+fn subtract_duration(durations: &mut HashMap<String, Duration>, key: &str, duration: Duration) {
+    if let Some(total) = durations.get_mut(key) {
+        *total -= duration;
+        if *total <= Duration::zero() {
+            durations.remove(key);
+        }
+    }
 }
 
 impl Calendar {
     pub fn new() -> Self {
         Self {
             activities: HashMap::new(),
+            planned: HashMap::new(),
+            longest_planned: Duration::zero(),
+            dirty: DirtyDays::default(),
+            day_model: DayModel::default(),
         }
     }
 
-    pub fn add_activity(&mut self, process_name: String, duration: Duration) -> Result<()> {
-        let today = Utc::now();
-        let date_key = format!("{}-{:02}-{:02}", today.year(), today.month(), today.day());
+    /// Sets how moments map to days and moves recorded data to the day it
+    /// belongs to under the new model. Compacted days can't be split and keep
+    /// their keys; journals stay on the day they were written for.
+    pub fn set_day_model(&mut self, day_model: DayModel) {
+        self.day_model = day_model;
 
-        let activity = self.activities.entry(date_key).or_insert(DailyActivity {
-            date: today,
-            process_durations: HashMap::new(),
-            focus_sessions: Vec::new(),
-        });
+        let misplaced: Vec<String> = self
+            .activities
+            .iter()
+            .filter(|(key, activity)| !self.is_placed(key, activity))
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in misplaced {
+            let Some(activity) = self.activities.remove(&key) else { continue };
+            self.dirty.insert(key.clone());
+            for interval in activity.intervals {
+                let _ = self.add_interval(interval);
+            }
+            for session in activity.focus_sessions {
+                self.day_entry(session.start_time).focus_sessions.push(session);
+            }
+            if let Some(day) = parse_date_key(&key) {
+                self.set_journal(day, activity.journal);
+            }
+        }
+
+        let planned: Vec<PlannedEvent> = self.planned.drain().flat_map(|(_, events)| events).collect();
+        for event in planned {
+            self.add_planned_event(event);
+        }
+    }
+
+    // Whether everything recorded under `key` falls on that day. Days whose
+    // totals don't come from their intervals are left alone.
+    fn is_placed(&self, key: &str, activity: &DailyActivity) -> bool {
+        let Some(day) = parse_date_key(key) else { return true };
+        let tracked: Duration = activity.intervals.iter().map(|interval| interval.duration()).sum();
+        if activity.compacted || tracked != activity.total_time() {
+            return true;
+        }
+        let (start, end) = (self.day_model.start_of(day), self.day_model.end_of(day));
+        activity.intervals.iter().all(|interval| interval.start >= start && interval.end <= end)
+            && activity.focus_sessions.iter().all(|session| self.day_model.day_of(session.start_time) == day)
+    }
 
-        *activity.process_durations.entry(process_name).or_insert(Duration::zero()) += duration;
+    // The day a moment belongs to, created on first use
+    fn day_entry(&mut self, time: DateTime<Utc>) -> &mut DailyActivity {
+        let day = self.day_model.day_of(time);
+        let key = date_key(&day);
+        self.dirty.insert(key.clone());
+        let start = self.day_model.start_of(day);
+        self.activities.entry(key).or_insert_with(|| DailyActivity::new(start))
+    }
+
+    /// Adds a tracked interval, split at day boundaries.
+    pub fn add_interval(&mut self, mut interval: ActivityInterval) -> Result<()> {
+        loop {
+            let next_day = self.day_model.end_of(self.day_model.day_of(interval.start));
+            if interval.end <= next_day {
+                break;
+            }
+            let mut rest = interval.clone();
+            rest.start = next_day;
+            interval.end = next_day;
+            self.add_day_interval(interval);
+            interval = rest;
+        }
+        self.add_day_interval(interval);
         Ok(())
     }
 
-    pub fn add_focus_session(&mut self, session: FocusSessionSummary) -> Result<()> {
-        let date_key = format!(
-            "{}-{:02}-{:02}",
-            session.start_time.year(),
-            session.start_time.month(),
-            session.start_time.day()
-        );
-
-        let activity = self.activities.entry(date_key).or_insert(DailyActivity {
-            date: session.start_time,
-            process_durations: HashMap::new(),
-            focus_sessions: Vec::new(),
+    fn add_day_interval(&mut self, interval: ActivityInterval) {
+        let duration = interval.duration();
+        let activity = self.day_entry(interval.start);
+
+        *activity.process_durations.entry(interval.app_name.clone()).or_insert(Duration::zero()) += duration;
+        if let Some(project) = &interval.project {
+            *activity.project_durations.entry(project.clone()).or_insert(Duration::zero()) += duration;
+        }
+        if let Some(domain) = &interval.domain {
+            *activity.domain_durations.entry(domain.clone()).or_insert(Duration::zero()) += duration;
+        }
+        activity.intervals.push(interval);
+    }
+
+    pub fn set_journal(&mut self, day: NaiveDate, journal: Journal) {
+        if journal.is_empty() && self.get_activity_for_day(day).is_none() {
+            return;
+        }
+        self.day_entry(self.day_model.start_of(day)).journal = journal;
+    }
+
+    /// Days between `start` and `end` (inclusive) with a journal entry, oldest first.
+    pub fn journals_in_range(&self, start: NaiveDate, end: NaiveDate) -> Vec<(NaiveDate, &Journal)> {
+        start
+            .iter_days()
+            .take_while(|day| *day <= end)
+            .filter_map(|day| self.get_activity_for_day(day).map(|activity| (day, &activity.journal)))
+            .filter(|(_, journal)| !journal.is_empty())
+            .collect()
+    }
+
+    /// Replaces everything imported from `source` with `events`. Returns
+    /// false, changing nothing, when they are the events already there.
+    pub fn replace_planned_events(&mut self, source: &str, mut events: Vec<PlannedEvent>) -> bool {
+        let order = |a: &&PlannedEvent, b: &&PlannedEvent| (a.start, &a.uid).cmp(&(b.start, &b.uid));
+        let mut current: Vec<&PlannedEvent> = self.planned.values().flatten().filter(|event| event.source == source).collect();
+        current.sort_by(order);
+        events.sort_by(|a, b| order(&a, &b));
+        if current.len() == events.len() && current.iter().zip(&events).all(|(a, b)| *a == b) {
+            return false;
+        }
+
+        for planned in self.planned.values_mut() {
+            planned.retain(|event| event.source != source);
+        }
+        self.planned.retain(|_, planned| !planned.is_empty());
+        self.longest_planned = self
+            .planned
+            .values()
+            .flatten()
+            .map(|event| event.end - event.start)
+            .max()
+            .unwrap_or_else(Duration::zero);
+        for event in events {
+            self.add_planned_event(event);
+        }
+        true
+    }
+
+    fn add_planned_event(&mut self, event: PlannedEvent) {
+        self.longest_planned = self.longest_planned.max(event.end - event.start);
+        let key = date_key(&self.day_model.day_of(event.start));
+        self.planned.entry(key).or_default().push(event);
+    }
+
+    /// Focus sessions on or after `since` that still have to be pushed to CalDAV.
+    pub fn unsynced_sessions(&self, since: NaiveDate) -> Vec<FocusSessionSummary> {
+        self.activities
+            .iter()
+            .filter(|(key, _)| parse_date_key(key).map(|day| day >= since).unwrap_or(false))
+            .flat_map(|(_, activity)| activity.focus_sessions.iter())
+            .filter(|session| !session.synced)
+            .cloned()
+            .collect()
+    }
+
+    /// Marks a session as pushed, unless it was reflected on since `pushed`
+    /// was taken.
+    pub fn mark_synced(&mut self, pushed: &FocusSessionSummary) {
+        let key = date_key(&self.day_model.day_of(pushed.start_time));
+        let session = self.activities.get_mut(&key).and_then(|activity| {
+            activity.focus_sessions.iter_mut().find(|session| {
+                session.start_time == pushed.start_time && session.rating == pushed.rating && session.note == pushed.note
+            })
         });
+        if let Some(session) = session {
+            session.synced = true;
+            self.dirty.insert(key);
+        }
+    }
+
+    /// Planned events overlapping `day`, earliest first.
+    pub fn planned_events_for_day(&self, day: NaiveDate) -> Vec<&PlannedEvent> {
+        let (start, end) = (self.day_model.start_of(day), self.day_model.end_of(day));
+        // Events that run into this day are stored on the day they started
+        let first = self.day_model.day_of(start - self.longest_planned);
+        let mut events: Vec<_> = first
+            .iter_days()
+            .take_while(|stored| *stored <= day)
+            .filter_map(|stored| self.planned.get(&date_key(&stored)))
+            .flatten()
+            .filter(|event| event.end > start && event.start < end)
+            .collect();
+        events.sort_by_key(|event| event.start);
+        events
+    }
+
+    /// Compares a planned block with the intervals tracked during it.
+    pub fn adherence(&self, event: &PlannedEvent, categories: &CategoryResolver) -> Adherence {
+        let target = categories.planned_category(&event.summary, event.meeting);
+        let mut adherence = Adherence {
+            planned: event.end - event.start,
+            tracked: Duration::zero(),
+            on_plan: Duration::zero(),
+        };
+
+        let (first, last) = (self.day_model.day_of(event.start), self.day_model.day_of(event.end));
+        for activity in self.activities_in_range(first, last) {
+            for interval in &activity.intervals {
+                let overlap = interval.end.min(event.end) - interval.start.max(event.start);
+                if overlap <= Duration::zero() {
+                    continue;
+                }
+                adherence.tracked += overlap;
 
-        activity.focus_sessions.push(session);
+                let category = categories.categorize(&interval.app_name, interval.domain.as_deref());
+                let on_plan = match (target, category) {
+                    (Some(target), Some(category)) => category.eq_ignore_ascii_case(target),
+                    (None, Some(category)) => categories.is_productive_category(category),
+                    (_, None) => false,
+                };
+                if on_plan {
+                    adherence.on_plan += overlap;
+                }
+            }
+        }
+        adherence
+    }
+
+    pub fn add_focus_session(&mut self, session: FocusSessionSummary) -> Result<()> {
+        self.day_entry(session.start_time).focus_sessions.push(session);
         Ok(())
     }
 
-    pub fn get_activity_for_date(&self, date: DateTime<Utc>) -> Option<&DailyActivity> {
-        let date_key = format!("{}-{:02}-{:02}", date.year(), date.month(), date.day());
-        self.activities.get(&date_key)
+    /// Stores the rating and note given after the session that started at
+    /// `start_time`. Returns false when there is no such session.
+    pub fn set_reflection(&mut self, start_time: DateTime<Utc>, rating: Option<u8>, note: Option<String>) -> bool {
+        let key = date_key(&self.day_model.day_of(start_time));
+        let session = self
+            .activities
+            .get_mut(&key)
+            .and_then(|activity| activity.focus_sessions.iter_mut().find(|session| session.start_time == start_time));
+        match session {
+            Some(session) => {
+                session.rating = rating;
+                session.note = note;
+                session.synced = false;
+                self.dirty.insert(key);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Removes recorded data that the current privacy settings no longer allow:
+    /// everything from excluded apps, and redacted titles with the sites derived from them.
+    pub fn apply_privacy(&mut self, privacy: &PrivacyFilter) {
+        for (key, activity) in self.activities.iter_mut() {
+            let app_count = activity.process_durations.len();
+            activity.process_durations.retain(|app, _| !privacy.is_excluded(app));
+            let mut changed = activity.process_durations.len() != app_count;
+
+            let intervals = std::mem::take(&mut activity.intervals);
+            for mut interval in intervals {
+                let duration = interval.duration();
+                if privacy.is_excluded(&interval.app_name) {
+                    changed = true;
+                    if let Some(project) = &interval.project {
+                        subtract_duration(&mut activity.project_durations, project, duration);
+                    }
+                    if let Some(domain) = &interval.domain {
+                        subtract_duration(&mut activity.domain_durations, domain, duration);
+                    }
+                    continue;
+                }
+
+                let redacted = interval
+                    .window_title
+                    .as_deref()
+                    .map(|title| privacy.is_redacted(title))
+                    .unwrap_or(false);
+                if redacted {
+                    changed = true;
+                    interval.window_title = None;
+                    if let Some(domain) = interval.domain.take() {
+                        subtract_duration(&mut activity.domain_durations, &domain, duration);
+                    }
+                } else if interval.window_title.is_some() && !privacy.tracks_window_titles() {
+                    changed = true;
+                    interval.window_title = None;
+                }
+                activity.intervals.push(interval);
+            }
+
+            for session in activity.focus_sessions.iter_mut() {
+                let app_count = session.apps_used.len();
+                session.apps_used.retain(|app| !privacy.is_excluded(app));
+                changed |= session.apps_used.len() != app_count;
+            }
+
+            if changed {
+                self.dirty.insert(key.clone());
+            }
+        }
+    }
+
+    /// Compacts days older than `keep_intervals_days` to their per-app, project
+    /// and site totals, and drops window titles older than `keep_titles_days`.
+    pub fn apply_retention(
+        &mut self,
+        today: NaiveDate,
+        keep_intervals_days: Option<u32>,
+        keep_titles_days: Option<u32>,
+    ) {
+        let cutoff = |days: Option<u32>| days.map(|days| today - Duration::days(days as i64));
+        let intervals_cutoff = cutoff(keep_intervals_days);
+        let titles_cutoff = cutoff(keep_titles_days);
+
+        for (key, activity) in self.activities.iter_mut() {
+            let day = match parse_date_key(key) {
+                Some(day) => day,
+                None => continue,
+            };
+            let mut changed = false;
+
+            if intervals_cutoff.map(|cutoff| day < cutoff).unwrap_or(false) && !activity.compacted {
+                activity.intervals.clear();
+                activity.compacted = true;
+                changed = true;
+            }
+
+            if titles_cutoff.map(|cutoff| day < cutoff).unwrap_or(false) {
+                for interval in activity.intervals.iter_mut() {
+                    changed |= interval.window_title.take().is_some();
+                }
+            }
+
+            if changed {
+                self.dirty.insert(key.clone());
+            }
+        }
+    }
+
+    /// Deletes everything recorded between `start` and `end` (inclusive).
+    pub fn forget_range(&mut self, start: NaiveDate, end: NaiveDate) -> Vec<FocusSessionSummary> {
+        let forgotten: Vec<String> = self
+            .activities
+            .keys()
+            .filter(|key| {
+                parse_date_key(key)
+                    .map(|day| day >= start && day <= end)
+                    .unwrap_or(false)
+            })
+            .cloned()
+            .collect();
+
+        let mut sessions = Vec::new();
+        for key in forgotten {
+            if let Some(activity) = self.activities.remove(&key) {
+                sessions.extend(activity.focus_sessions);
+            }
+            self.dirty.insert(key);
+        }
+        sessions
+    }
+
+    /// Takes over everything in `pending`, e.g. what was recorded or imported
+    /// before the stored history was loaded: intervals and their totals, focus
+    /// sessions (replacing a stored one with the same start, which may have
+    /// been reflected on since), journals and planned events.
+    pub fn carry_over(&mut self, pending: Calendar) {
+        for (key, activity) in pending.activities {
+            let tracked: Duration = activity.intervals.iter().map(|interval| interval.duration()).sum();
+            if !activity.compacted && tracked == activity.total_time() {
+                for interval in activity.intervals {
+                    let _ = self.add_interval(interval);
+                }
+            } else if let Some(day) = parse_date_key(&key) {
+                // Totals not backed by intervals stay on their day
+                let entry = self.day_entry(self.day_model.start_of(day));
+                for (totals, pending_totals) in [
+                    (&mut entry.process_durations, activity.process_durations),
+                    (&mut entry.project_durations, activity.project_durations),
+                    (&mut entry.domain_durations, activity.domain_durations),
+                ] {
+                    for (name, duration) in pending_totals {
+                        *totals.entry(name).or_insert(Duration::zero()) += duration;
+                    }
+                }
+                entry.intervals.extend(activity.intervals);
+                entry.compacted |= activity.compacted;
+            }
+
+            for session in activity.focus_sessions {
+                let sessions = &mut self.day_entry(session.start_time).focus_sessions;
+                match sessions.iter_mut().find(|stored| stored.start_time == session.start_time) {
+                    Some(stored) => *stored = session,
+                    None => sessions.push(session),
+                }
+            }
+            if let Some(day) = parse_date_key(&key).filter(|_| !activity.journal.is_empty()) {
+                self.set_journal(day, activity.journal);
+            }
+        }
+        for event in pending.planned.into_values().flatten() {
+            self.add_planned_event(event);
+        }
+    }
+
+    /// Adds a day read from storage without marking it as changed. Days
+    /// with nothing recorded, left over from when planned events were
+    /// stored with them, are removed on the next save instead.
+    pub fn load_day(&mut self, key: String, activity: DailyActivity) {
+        if activity.is_empty() {
+            self.dirty.insert(key);
+            return;
+        }
+        self.activities.insert(key, activity);
+    }
+
+    pub fn mark_dirty(&mut self, key: &str) {
+        self.dirty.insert(key.to_string());
+    }
+
+    /// Marks every day as changed, so the next save rewrites all of them.
+    pub fn mark_all_dirty(&mut self) {
+        for key in self.activities.keys() {
+            self.dirty.insert(key.clone());
+        }
+    }
+
+    pub fn take_dirty_keys(&mut self) -> Vec<String> {
+        self.dirty.unsaved.drain().collect()
+    }
+
+    /// Increases whenever `day` changes; 0 for days unchanged since loading.
+    pub fn day_revision(&self, day: NaiveDate) -> u64 {
+        self.dirty.revisions.get(&date_key(&day)).copied().unwrap_or(0)
+    }
+
+    /// Changes whenever any day changes.
+    pub fn revision(&self) -> u64 {
+        self.dirty.revision
+    }
+
+    pub fn get_day_by_key(&self, key: &str) -> Option<&DailyActivity> {
+        self.activities.get(key)
+    }
+
+    pub fn get_activity_for_day(&self, day: NaiveDate) -> Option<&DailyActivity> {
+        self.activities.get(&date_key(&day))
+    }
+
+    /// Returns the recorded days between `start` and `end` (inclusive), oldest first.
+    pub fn activities_in_range(&self, start: NaiveDate, end: NaiveDate) -> Vec<&DailyActivity> {
+        start
+            .iter_days()
+            .take_while(|day| *day <= end)
+            .filter_map(|day| self.get_activity_for_day(day))
+            .collect()
+    }
+
+    /// One summary per day between `start` and `end` (inclusive), including
+    /// days without any activity.
+    pub fn day_summaries(&self, start: NaiveDate, end: NaiveDate, categories: &CategoryResolver) -> Vec<DaySummary> {
+        start
+            .iter_days()
+            .take_while(|day| *day <= end)
+            .map(|day| self.day_summary(day, categories))
+            .collect()
+    }
+
+    pub fn day_summary(&self, day: NaiveDate, categories: &CategoryResolver) -> DaySummary {
+        match self.get_activity_for_day(day) {
+            Some(activity) => DaySummary {
+                day,
+                tracked: activity.total_time(),
+                focus: activity.focus_time(),
+                focus_sessions: activity.focus_sessions.len(),
+                categories: activity.category_durations(categories),
+            },
+            None => DaySummary {
+                day,
+                tracked: Duration::zero(),
+                focus: Duration::zero(),
+                focus_sessions: 0,
+                categories: Vec::new(),
+            },
+        }
+    }
+
+    /// Consecutive days with a focus session up to `today`. Today only
+    /// counts once it has a session, so the streak holds until it ends.
+    pub fn current_focus_streak(&self, today: NaiveDate) -> usize {
+        let focused = |day: NaiveDate| {
+            self.get_activity_for_day(day)
+                .map(|activity| !activity.focus_sessions.is_empty())
+                .unwrap_or(false)
+        };
+        let last = if focused(today) { today } else { today - Duration::days(1) };
+        last.iter_days()
+            .rev()
+            .take_while(|day| focused(*day))
+            .count()
+    }
+}
+
+/// Totals for one day, as shown in the week view.
+#[derive(Clone)]
+pub struct DaySummary {
+    pub day: NaiveDate,
+    pub tracked: Duration,
+    pub focus: Duration,
+    pub focus_sessions: usize,
+    pub categories: Vec<(String, Duration)>, // longest first
+}
+
+impl DaySummary {
+    /// Productive minus distracting time as a share of tracked time, 0–100.
+    /// `None` for days without tracked time.
+    pub fn productivity_score(&self, categories: &CategoryResolver) -> Option<u8> {
+        if self.tracked <= Duration::zero() {
+            return None;
+        }
+        let mut balance = 0;
+        for (category, duration) in &self.categories {
+            if categories.is_productive_category(category) {
+                balance += duration.num_seconds();
+            } else if categories.is_distracting_category(category) {
+                balance -= duration.num_seconds();
+            }
+        }
+        let score = balance as f64 / self.tracked.num_seconds().max(1) as f64 * 100.0;
+        Some(score.clamp(0.0, 100.0).round() as u8)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc_day_model() -> DayModel {
+        DayModel {
+            timezone: Some(chrono_tz::UTC),
+            day_start_hour: 0,
+        }
+    }
+
+    fn berlin_day_model(day_start_hour: u32) -> DayModel {
+        DayModel {
+            timezone: Some(chrono_tz::Europe::Berlin),
+            day_start_hour,
+        }
+    }
+
+    fn interval(app_name: &str, title: &str, start: DateTime<Utc>, minutes: i64) -> ActivityInterval {
+        ActivityInterval {
+            app_name: app_name.to_string(),
+            window_title: Some(title.to_string()),
+            project: None,
+            domain: None,
+            start,
+            end: start + Duration::minutes(minutes),
+        }
     }
-} 
\ No newline at end of file
+
+    fn session(intent: Option<&str>, tags: &[&str], note: Option<&str>) -> FocusSessionSummary {
+        FocusSessionSummary {
+            start_time: Utc.with_ymd_and_hms(2024, 3, 5, 9, 0, 0).unwrap(),
+            duration: Duration::minutes(25),
+            music_used: false,
+            tracks_played: Vec::new(),
+            apps_used: Vec::new(),
+            distracted: Duration::zero(),
+            interruptions: 0,
+            quality_score: None,
+            intent: intent.map(str::to_string),
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            rating: None,
+            note: note.map(str::to_string),
+            synced: false,
+        }
+    }
+
+    #[test]
+    fn session_filter_takes_the_first_tag_and_the_remaining_words() {
+        let filter = SessionFilter::parse("  #Writing  Chapter   #two ");
+        assert_eq!(filter.tag.as_deref(), Some("writing"));
+        assert_eq!(filter.text.as_deref(), Some("chapter #two"));
+
+        let filter = SessionFilter::parse("# review");
+        assert_eq!(filter.tag, None);
+        assert_eq!(filter.text.as_deref(), Some("# review"));
+
+        assert!(SessionFilter::parse("   ").is_empty());
+    }
+
+    #[test]
+    fn session_filter_matches_tags_intents_and_notes() {
+        let filter = SessionFilter::parse("#writing chapter");
+        assert!(filter.matches(&session(Some("Draft Chapter 2"), &["Writing"], None)));
+        assert!(filter.matches(&session(None, &["writing"], Some("finished the chapter"))));
+        assert!(!filter.matches(&session(Some("Draft chapter 2"), &["code"], None)));
+        assert!(!filter.matches(&session(Some("Emails"), &["writing"], None)));
+    }
+
+    #[test]
+    fn retention_compacts_old_days_and_drops_old_titles() {
+        let mut calendar = Calendar::new();
+        calendar.set_day_model(utc_day_model());
+        let today = NaiveDate::from_ymd_opt(2024, 3, 31).unwrap();
+        for days_ago in [100, 40, 5] {
+            let day = today - Duration::days(days_ago);
+            let start = Utc.from_utc_datetime(&day.and_hms_opt(10, 0, 0).unwrap());
+            calendar.add_interval(interval("editor", "notes.md", start, 30)).unwrap();
+        }
+        calendar.take_dirty_keys();
+
+        calendar.apply_retention(today, Some(90), Some(30));
+        let day = |days_ago| calendar.get_activity_for_day(today - Duration::days(days_ago)).unwrap();
+        assert!(day(100).compacted && day(100).intervals.is_empty());
+        assert_eq!(day(100).total_time(), Duration::minutes(30));
+        assert!(!day(40).compacted);
+        assert_eq!(day(40).intervals[0].window_title, None);
+        assert_eq!(day(5).intervals[0].window_title.as_deref(), Some("notes.md"));
+
+        let mut dirty = calendar.take_dirty_keys();
+        dirty.sort();
+        assert_eq!(dirty, ["2023-12-22", "2024-02-20"]);
+
+        // Nothing left to change on the next run
+        calendar.apply_retention(today, Some(90), Some(30));
+        assert!(calendar.take_dirty_keys().is_empty());
+    }
+
+    #[test]
+    fn no_retention_keeps_everything() {
+        let mut calendar = Calendar::new();
+        calendar.set_day_model(utc_day_model());
+        let start = Utc.with_ymd_and_hms(2020, 1, 1, 10, 0, 0).unwrap();
+        calendar.add_interval(interval("editor", "notes.md", start, 30)).unwrap();
+        calendar.take_dirty_keys();
+
+        calendar.apply_retention(NaiveDate::from_ymd_opt(2024, 3, 31).unwrap(), None, None);
+        let day = calendar.get_activity_for_day(start.date_naive()).unwrap();
+        assert_eq!(day.intervals[0].window_title.as_deref(), Some("notes.md"));
+        assert!(calendar.take_dirty_keys().is_empty());
+    }
+
+    #[test]
+    fn day_model_follows_the_timezone_and_day_start() {
+        let model = berlin_day_model(4);
+        let day = NaiveDate::from_ymd_opt(2024, 6, 10).unwrap();
+        assert_eq!(model.start_of(day), Utc.with_ymd_and_hms(2024, 6, 10, 2, 0, 0).unwrap());
+        // 03:30 in Berlin still counts towards the previous day
+        assert_eq!(model.day_of(Utc.with_ymd_and_hms(2024, 6, 10, 1, 30, 0).unwrap()), day - Duration::days(1));
+        assert_eq!(model.day_of(Utc.with_ymd_and_hms(2024, 6, 10, 2, 0, 0).unwrap()), day);
+    }
+
+    #[test]
+    fn day_model_moves_day_starts_past_dst_gaps() {
+        // Clocks in Berlin jump from 02:00 to 03:00 on 2024-03-31
+        let model = berlin_day_model(2);
+        let before = NaiveDate::from_ymd_opt(2024, 3, 30).unwrap();
+        let gap = NaiveDate::from_ymd_opt(2024, 3, 31).unwrap();
+        assert_eq!(model.start_of(before), Utc.with_ymd_and_hms(2024, 3, 30, 1, 0, 0).unwrap());
+        assert_eq!(model.start_of(gap), Utc.with_ymd_and_hms(2024, 3, 31, 1, 0, 0).unwrap());
+        assert_eq!(model.end_of(gap) - model.start_of(gap), Duration::hours(23));
+        assert_eq!(model.day_of(Utc.with_ymd_and_hms(2024, 3, 31, 0, 59, 0).unwrap()), before);
+        assert_eq!(model.day_of(Utc.with_ymd_and_hms(2024, 3, 31, 1, 0, 0).unwrap()), gap);
+    }
+
+    #[test]
+    fn intervals_are_split_at_the_configured_day_start() {
+        let mut calendar = Calendar::new();
+        calendar.set_day_model(berlin_day_model(4));
+        let start = Utc.with_ymd_and_hms(2024, 6, 10, 1, 0, 0).unwrap();
+        calendar.add_interval(interval("editor", "notes.md", start, 120)).unwrap();
+
+        let day = NaiveDate::from_ymd_opt(2024, 6, 10).unwrap();
+        let first = calendar.get_activity_for_day(day - Duration::days(1)).unwrap();
+        let second = calendar.get_activity_for_day(day).unwrap();
+        assert_eq!(first.total_time(), Duration::minutes(60));
+        assert_eq!(second.total_time(), Duration::minutes(60));
+        assert_eq!(second.intervals[0].start, Utc.with_ymd_and_hms(2024, 6, 10, 2, 0, 0).unwrap());
+    }
+
+    #[test]
+    fn intervals_are_split_at_day_starts_after_a_dst_gap() {
+        let mut calendar = Calendar::new();
+        calendar.set_day_model(berlin_day_model(2));
+        let start = Utc.with_ymd_and_hms(2024, 3, 31, 0, 30, 0).unwrap();
+        calendar.add_interval(interval("editor", "notes.md", start, 60)).unwrap();
+
+        let day = |d| calendar.get_activity_for_day(NaiveDate::from_ymd_opt(2024, 3, d).unwrap()).unwrap();
+        assert_eq!(day(30).total_time(), Duration::minutes(30));
+        assert_eq!(day(31).total_time(), Duration::minutes(30));
+    }
+
+    #[test]
+    fn changing_the_day_model_moves_recorded_data() {
+        let mut calendar = Calendar::new();
+        calendar.set_day_model(utc_day_model());
+        let start = Utc.with_ymd_and_hms(2024, 3, 5, 23, 30, 0).unwrap();
+        calendar.add_interval(interval("editor", "notes.md", start, 60)).unwrap();
+        let mut late = session(None, &[], None);
+        late.start_time = start + Duration::minutes(15);
+        calendar.add_focus_session(late).unwrap();
+        let fifth = NaiveDate::from_ymd_opt(2024, 3, 5).unwrap();
+        let journal = Journal {
+            note: "Late start".into(),
+            ..Default::default()
+        };
+        calendar.set_journal(fifth, journal);
+        calendar.take_dirty_keys();
+
+        // 23:30 UTC is already the next day in Berlin
+        calendar.set_day_model(berlin_day_model(0));
+        let sixth = calendar.get_activity_for_day(fifth + Duration::days(1)).unwrap();
+        assert_eq!(sixth.total_time(), Duration::minutes(60));
+        assert_eq!(sixth.focus_sessions.len(), 1);
+        let fifth_activity = calendar.get_activity_for_day(fifth).unwrap();
+        assert_eq!(fifth_activity.total_time(), Duration::zero());
+        assert_eq!(fifth_activity.journal.note, "Late start");
+
+        let mut dirty = calendar.take_dirty_keys();
+        dirty.sort();
+        assert_eq!(dirty, ["2024-03-05", "2024-03-06"]);
+
+        // Already in place, nothing moves again
+        calendar.set_day_model(berlin_day_model(0));
+        assert!(calendar.take_dirty_keys().is_empty());
+    }
+
+    #[test]
+    fn current_focus_streak_crosses_years_and_waits_for_today() {
+        let mut calendar = Calendar::new();
+        calendar.set_day_model(utc_day_model());
+        for (month, day) in [(12, 29), (12, 30), (12, 31)] {
+            let mut focused = session(None, &[], None);
+            focused.start_time = Utc.with_ymd_and_hms(2023, month, day, 9, 0, 0).unwrap();
+            calendar.add_focus_session(focused).unwrap();
+        }
+        let mut focused = session(None, &[], None);
+        focused.start_time = Utc.with_ymd_and_hms(2024, 1, 1, 9, 0, 0).unwrap();
+        calendar.add_focus_session(focused).unwrap();
+
+        let new_year = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        assert_eq!(calendar.current_focus_streak(new_year), 4);
+        assert_eq!(calendar.current_focus_streak(new_year + Duration::days(1)), 4);
+        assert_eq!(calendar.current_focus_streak(new_year + Duration::days(2)), 0);
+    }
+
+    #[test]
+    fn day_revisions_change_with_the_day() {
+        let mut calendar = Calendar::new();
+        calendar.set_day_model(utc_day_model());
+        let day = NaiveDate::from_ymd_opt(2024, 3, 5).unwrap();
+        assert_eq!(calendar.day_revision(day), 0);
+
+        let start = Utc.with_ymd_and_hms(2024, 3, 5, 9, 0, 0).unwrap();
+        calendar.add_interval(interval("editor", "notes.md", start, 30)).unwrap();
+        let revision = calendar.day_revision(day);
+        assert!(revision > 0);
+        calendar.take_dirty_keys();
+        assert_eq!(calendar.day_revision(day), revision);
+
+        calendar.add_interval(interval("editor", "notes.md", start + Duration::hours(1), 30)).unwrap();
+        assert!(calendar.day_revision(day) > revision);
+        assert_eq!(calendar.day_revision(day + Duration::days(1)), 0);
+        assert_eq!(calendar.revision(), calendar.day_revision(day));
+    }
+
+    #[test]
+    fn planned_events_are_kept_apart_from_recorded_days() {
+        let mut calendar = Calendar::new();
+        calendar.set_day_model(utc_day_model());
+        let start = Utc.with_ymd_and_hms(2024, 3, 5, 23, 0, 0).unwrap();
+        let event = PlannedEvent {
+            uid: "late-call".into(),
+            summary: "Late call".into(),
+            start,
+            end: start + Duration::hours(2),
+            meeting: true,
+            source: "work.ics".into(),
+        };
+        assert!(calendar.replace_planned_events("work.ics", vec![event.clone()]));
+        assert!(!calendar.replace_planned_events("work.ics", vec![event]));
+        assert!(calendar.take_dirty_keys().is_empty());
+        let day = start.date_naive();
+        assert!(calendar.get_activity_for_day(day).is_none());
+        // Shown on both days it overlaps
+        assert_eq!(calendar.planned_events_for_day(day).len(), 1);
+        assert_eq!(calendar.planned_events_for_day(day + Duration::days(1)).len(), 1);
+
+        assert!(calendar.replace_planned_events("work.ics", Vec::new()));
+        assert!(calendar.planned_events_for_day(day).is_empty());
+    }
+
+    #[test]
+    fn planned_events_longer_than_a_day_show_on_every_day_they_overlap() {
+        let mut calendar = Calendar::new();
+        calendar.set_day_model(berlin_day_model(0));
+        let start = Utc.with_ymd_and_hms(2024, 3, 4, 8, 0, 0).unwrap();
+        let conference = PlannedEvent {
+            uid: "conference".into(),
+            summary: "Conference".into(),
+            start,
+            end: start + Duration::days(3) + Duration::hours(8),
+            meeting: false,
+            source: "work.ics".into(),
+        };
+        assert!(calendar.replace_planned_events("work.ics", vec![conference]));
+
+        let day = NaiveDate::from_ymd_opt(2024, 3, 4).unwrap();
+        let shown: Vec<usize> = (-1..6)
+            .map(|offset| calendar.planned_events_for_day(day + Duration::days(offset)).len())
+            .collect();
+        assert_eq!(shown, [0, 1, 1, 1, 1, 0, 0]);
+
+        // Dropping the long event stops the scan from reaching back that far
+        assert!(calendar.replace_planned_events("work.ics", Vec::new()));
+        assert_eq!(calendar.longest_planned, Duration::zero());
+    }
+
+    #[test]
+    fn empty_stored_days_are_removed_on_load() {
+        let mut calendar = Calendar::new();
+        let start = Utc.with_ymd_and_hms(2024, 3, 5, 0, 0, 0).unwrap();
+        calendar.load_day("2024-03-05".into(), DailyActivity::new(start));
+        assert!(calendar.get_day_by_key("2024-03-05").is_none());
+        assert_eq!(calendar.take_dirty_keys(), ["2024-03-05"]);
+    }
+
+    #[test]
+    fn carry_over_merges_everything_recorded_before_loading() {
+        let start = Utc.with_ymd_and_hms(2024, 3, 5, 9, 0, 0).unwrap();
+        let day = start.date_naive();
+        let mut loaded = Calendar::new();
+        loaded.set_day_model(utc_day_model());
+        loaded.add_interval(interval("editor", "notes.md", start, 30)).unwrap();
+        loaded.add_focus_session(session(Some("Write"), &[], None)).unwrap();
+        loaded.set_journal(day, Journal { note: "Stored".into(), ..Journal::default() });
+        loaded.take_dirty_keys();
+
+        let mut pending = Calendar::new();
+        pending.set_day_model(utc_day_model());
+        let mut browsing = interval("firefox", "Docs", start + Duration::hours(1), 15);
+        browsing.domain = Some("docs.rs".into());
+        pending.add_interval(browsing).unwrap();
+        pending.add_focus_session(session(Some("Write"), &[], None)).unwrap();
+        pending.set_reflection(start, Some(4), Some("Went fine".into()));
+        pending.set_journal(day, Journal { note: "Draft".into(), ..Journal::default() });
+
+        loaded.carry_over(pending);
+        let activity = loaded.get_activity_for_day(day).unwrap();
+        assert_eq!(activity.intervals.len(), 2);
+        assert_eq!(activity.total_time(), Duration::minutes(45));
+        assert_eq!(activity.domain_durations["docs.rs"], Duration::minutes(15));
+        assert_eq!(activity.focus_sessions.len(), 1);
+        assert_eq!(activity.focus_sessions[0].rating, Some(4));
+        assert_eq!(activity.journal.note, "Draft");
+        assert_eq!(loaded.take_dirty_keys(), ["2024-03-05"]);
+    }
+
+    fn privacy(track_window_titles: bool) -> PrivacyFilter {
+        let config = Config {
+            track_window_titles,
+            excluded_apps: vec!["keepass".to_string()],
+            title_redaction_patterns: vec!["(?i)bank".to_string()],
+            ..Default::default()
+        };
+        PrivacyFilter::from_config(&config)
+    }
+
+    #[test]
+    fn apply_privacy_scrubs_days_already_recorded() {
+        let mut calendar = Calendar::new();
+        calendar.set_day_model(utc_day_model());
+        let start = Utc.with_ymd_and_hms(2024, 3, 5, 9, 0, 0).unwrap();
+        calendar.add_interval(interval("KeePassXC", "Passwords", start, 10)).unwrap();
+        let mut bank = interval("firefox", "My Bank - Firefox", start + Duration::minutes(10), 20);
+        bank.domain = Some("bank.example".to_string());
+        calendar.add_interval(bank).unwrap();
+        calendar.add_interval(interval("editor", "notes.md", start + Duration::minutes(30), 30)).unwrap();
+        let mut focused = session(None, &[], None);
+        focused.apps_used = vec!["KeePassXC".to_string(), "editor".to_string()];
+        calendar.add_focus_session(focused).unwrap();
+        calendar.take_dirty_keys();
+
+        calendar.apply_privacy(&privacy(true));
+        let day = calendar.get_activity_for_day(start.date_naive()).unwrap();
+        assert!(!day.process_durations.contains_key("KeePassXC"));
+        assert!(day.intervals.iter().all(|interval| interval.app_name != "KeePassXC"));
+        assert!(day.domain_durations.is_empty());
+        let titles: Vec<_> = day.intervals.iter().map(|interval| interval.window_title.as_deref()).collect();
+        assert_eq!(titles, [None, Some("notes.md")]);
+        assert_eq!(day.focus_sessions[0].apps_used, ["editor"]);
+        assert_eq!(calendar.take_dirty_keys(), ["2024-03-05"]);
+
+        // Nothing left to scrub under the same rules
+        calendar.apply_privacy(&privacy(true));
+        assert!(calendar.take_dirty_keys().is_empty());
+    }
+
+    #[test]
+    fn apply_privacy_clears_titles_when_title_tracking_is_off() {
+        let mut calendar = Calendar::new();
+        calendar.set_day_model(utc_day_model());
+        let start = Utc.with_ymd_and_hms(2024, 3, 5, 9, 0, 0).unwrap();
+        calendar.add_interval(interval("editor", "notes.md", start, 30)).unwrap();
+
+        calendar.apply_privacy(&privacy(false));
+        let day = calendar.get_activity_for_day(start.date_naive()).unwrap();
+        assert_eq!(day.intervals[0].window_title, None);
+        assert_eq!(day.total_time(), Duration::minutes(30));
+    }
+}
@@ -0,0 +1,103 @@
+use crate::config::{CategoryRule, Config};
+
+/// Planned events with attendees are compared against this category.
+const MEETINGS: &str = "Meetings";
+
+/// Sorts apps and websites into categories such as "Social" or "Development".
+pub struct CategoryResolver {
+    rules: Vec<CategoryRule>, // patterns lowercase
+    plan_rules: Vec<CategoryRule>, // matched against planned event titles, patterns lowercase
+    distracting: Vec<String>, // lowercase
+    productive: Vec<String>,  // lowercase
+}
+
+impl CategoryResolver {
+    pub fn new() -> Self {
+        Self {
+            rules: Vec::new(),
+            plan_rules: Vec::new(),
+            distracting: Vec::new(),
+            productive: Vec::new(),
+        }
+    }
+
+    pub fn from_config(config: &Config) -> Self {
+        let lowercase = |rules: &[CategoryRule]| {
+            rules
+                .iter()
+                .map(|rule| CategoryRule {
+                    pattern: rule.pattern.to_lowercase(),
+                    category: rule.category.clone(),
+                })
+                .collect()
+        };
+
+        Self {
+            rules: lowercase(&config.category_rules),
+            plan_rules: lowercase(&config.plan_rules),
+            distracting: config
+                .distracting_categories
+                .iter()
+                .map(|category| category.to_lowercase())
+                .collect(),
+            productive: config
+                .productive_categories
+                .iter()
+                .map(|category| category.to_lowercase())
+                .collect(),
+        }
+    }
+
+    /// The category of the website if a rule matches its domain, otherwise
+    /// the category of the app.
+    pub fn categorize(&self, app_name: &str, domain: Option<&str>) -> Option<&str> {
+        if let Some(domain) = domain.map(str::to_lowercase) {
+            let on_domain = |rule: &&CategoryRule| {
+                domain == rule.pattern || domain.ends_with(&format!(".{}", rule.pattern))
+            };
+            if let Some(rule) = self.rules.iter().find(on_domain) {
+                return Some(&rule.category);
+            }
+        }
+
+        let app = app_name.to_lowercase();
+        self.rules
+            .iter()
+            .find(|rule| app.contains(rule.pattern.as_str()))
+            .map(|rule| rule.category.as_str())
+    }
+
+    /// The category a planned event should be spent in: the first plan rule
+    /// matching its title, meetings for events with attendees, and `None`
+    /// for any productive category.
+    pub fn planned_category(&self, summary: &str, meeting: bool) -> Option<&str> {
+        let summary = summary.to_lowercase();
+        match self.plan_rules.iter().find(|rule| summary.contains(rule.pattern.as_str())) {
+            Some(rule) => Some(&rule.category),
+            None if meeting => Some(MEETINGS),
+            None => None,
+        }
+    }
+
+    pub fn is_distracting(&self, app_name: &str, domain: Option<&str>) -> bool {
+        self.in_categories(app_name, domain, &self.distracting)
+    }
+
+    pub fn is_productive(&self, app_name: &str, domain: Option<&str>) -> bool {
+        self.in_categories(app_name, domain, &self.productive)
+    }
+
+    pub fn is_distracting_category(&self, category: &str) -> bool {
+        self.distracting.contains(&category.to_lowercase())
+    }
+
+    pub fn is_productive_category(&self, category: &str) -> bool {
+        self.productive.contains(&category.to_lowercase())
+    }
+
+    fn in_categories(&self, app_name: &str, domain: Option<&str>, categories: &[String]) -> bool {
+        self.categorize(app_name, domain)
+            .map(|category| categories.contains(&category.to_lowercase()))
+            .unwrap_or(false)
+    }
+}
@@ -0,0 +1,391 @@
+use crate::utils::format_short_duration;
+use chrono::{Datelike, Duration, NaiveDate};
+use eframe::egui;
+use egui_plot::{Bar, BarChart, GridMark, Line, Plot, Points};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+/// A stable color per category or app name.
+pub fn category_color(name: &str) -> egui::Color32 {
+    let mut hasher = DefaultHasher::new();
+    name.hash(&mut hasher);
+    let hue = (hasher.finish() % 360) as f32 / 360.0;
+    egui::ecolor::Hsva::new(hue, 0.55, 0.75, 1.0).into()
+}
+
+/// One bar of a stacked bar chart.
+pub struct StackedBar {
+    pub label: String,
+    pub segments: Vec<(String, Duration)>, // drawn bottom to top
+    pub caption: String,                    // shown under the label
+    pub highlighted: bool,
+}
+
+/// Draws bars side by side, scaled so the tallest reaches the top, and
+/// returns the index of the bar that was clicked.
+pub fn stacked_bars(ui: &mut egui::Ui, bars: &[StackedBar], height: f32) -> Option<usize> {
+    let max_total = bars
+        .iter()
+        .map(|bar| bar.segments.iter().map(|(_, duration)| duration.num_seconds()).sum::<i64>())
+        .max()
+        .unwrap_or(0)
+        .max(1) as f32;
+    let column_width = (ui.available_width() / bars.len().max(1) as f32).clamp(40.0, 120.0);
+    let mut clicked = None;
+
+    ui.horizontal(|ui| {
+        for (index, bar) in bars.iter().enumerate() {
+            ui.vertical(|ui| {
+                ui.set_width(column_width);
+                let (rect, response) =
+                    ui.allocate_exact_size(egui::vec2(column_width - 8.0, height), egui::Sense::click());
+                let painter = ui.painter_at(rect);
+                if bar.highlighted {
+                    painter.rect_stroke(rect, 2.0, ui.visuals().selection.stroke);
+                }
+
+                let mut bottom = rect.bottom();
+                let mut hovered = None;
+                for (name, duration) in &bar.segments {
+                    let segment_height = duration.num_seconds() as f32 / max_total * rect.height();
+                    let segment = egui::Rect::from_x_y_ranges(
+                        rect.left() + 4.0..=rect.right() - 4.0,
+                        bottom - segment_height..=bottom,
+                    );
+                    painter.rect_filled(segment, 0.0, category_color(name));
+                    if response.hover_pos().map(|pos| segment.contains(pos)).unwrap_or(false) {
+                        hovered = Some((name, *duration));
+                    }
+                    bottom -= segment_height;
+                }
+
+                if response.clicked() {
+                    clicked = Some(index);
+                }
+                if let Some((name, duration)) = hovered {
+                    response.on_hover_text_at_pointer(format!("{}: {}", name, format_short_duration(duration)));
+                }
+
+                ui.label(&bar.label);
+                ui.small(&bar.caption);
+            });
+        }
+    });
+
+    clicked
+}
+
+/// Color swatches for the given names.
+pub fn legend<'a>(ui: &mut egui::Ui, names: impl IntoIterator<Item = &'a str>) {
+    ui.horizontal_wrapped(|ui| {
+        for name in names {
+            let (rect, _) = ui.allocate_exact_size(egui::vec2(10.0, 10.0), egui::Sense::hover());
+            ui.painter().rect_filled(rect, 2.0, category_color(name));
+            ui.label(name);
+        }
+    });
+}
+
+const HEATMAP_CELL: f32 = 12.0;
+const HEATMAP_GAP: f32 = 2.0;
+const HEATMAP_LABEL_WIDTH: f32 = 28.0;
+const HEATMAP_LEVELS: f32 = 4.0;
+
+/// One day of a heatmap.
+pub struct HeatmapCell {
+    pub day: NaiveDate,
+    pub intensity: Option<f32>, // 0.0–1.0, `None` for days without data
+    pub tooltip: String,
+}
+
+/// Contribution-graph style heatmap: a column per week, a row per weekday
+/// starting on Monday. Returns the day that was clicked.
+pub fn heatmap(ui: &mut egui::Ui, cells: &[HeatmapCell], selected: NaiveDate) -> Option<NaiveDate> {
+    let first = cells.first()?.day;
+    let offset = first.weekday().num_days_from_monday() as usize;
+    let weeks = (offset + cells.len()).div_ceil(7);
+    let step = HEATMAP_CELL + HEATMAP_GAP;
+    let size = egui::vec2(HEATMAP_LABEL_WIDTH + weeks as f32 * step, step * 8.0);
+    let (rect, response) = ui.allocate_exact_size(size, egui::Sense::click());
+    let painter = ui.painter_at(rect);
+
+    let empty = ui.visuals().faint_bg_color;
+    let full = egui::Color32::from_rgb(33, 110, 57);
+    let text_color = ui.visuals().weak_text_color();
+    let font = egui::FontId::proportional(10.0);
+    let grid_origin = rect.left_top() + egui::vec2(HEATMAP_LABEL_WIDTH, step);
+
+    for (row, name) in [(0, "Mon"), (2, "Wed"), (4, "Fri")] {
+        let position = egui::pos2(rect.left(), grid_origin.y + row as f32 * step + HEATMAP_CELL / 2.0);
+        painter.text(position, egui::Align2::LEFT_CENTER, name, font.clone(), text_color);
+    }
+
+    let mut hovered = None;
+    for (index, cell) in cells.iter().enumerate() {
+        let slot = offset + index;
+        let cell_rect = egui::Rect::from_min_size(
+            grid_origin + egui::vec2((slot / 7) as f32 * step, (slot % 7) as f32 * step),
+            egui::vec2(HEATMAP_CELL, HEATMAP_CELL),
+        );
+
+        if cell.day.day() == 1 {
+            painter.text(
+                egui::pos2(cell_rect.left(), rect.top()),
+                egui::Align2::LEFT_TOP,
+                cell.day.format("%b").to_string(),
+                font.clone(),
+                text_color,
+            );
+        }
+
+        // Quantized like the GitHub graph so similar days read the same
+        let color = match cell.intensity {
+            Some(intensity) if intensity > 0.0 => {
+                let level = (intensity.clamp(0.0, 1.0) * HEATMAP_LEVELS).ceil() / HEATMAP_LEVELS;
+                lerp_color(empty, full, level)
+            }
+            _ => empty,
+        };
+        painter.rect_filled(cell_rect, 2.0, color);
+        if cell.day == selected {
+            painter.rect_stroke(cell_rect, 2.0, ui.visuals().selection.stroke);
+        }
+
+        if response.hover_pos().map(|pos| cell_rect.contains(pos)).unwrap_or(false) {
+            hovered = Some(cell);
+        }
+    }
+
+    let clicked = hovered.filter(|_| response.clicked()).map(|cell| cell.day);
+    if let Some(cell) = hovered {
+        response.on_hover_text_at_pointer(&cell.tooltip);
+    }
+    clicked
+}
+
+fn lerp_color(from: egui::Color32, to: egui::Color32, t: f32) -> egui::Color32 {
+    let channel = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t).round() as u8;
+    egui::Color32::from_rgb(channel(from.r(), to.r()), channel(from.g(), to.g()), channel(from.b(), to.b()))
+}
+
+const DONUT_STEPS_PER_TURN: f32 = 96.0;
+
+/// Donut chart of durations with the total in the middle and a legend
+/// beside it.
+pub fn donut(ui: &mut egui::Ui, slices: &[(String, Duration)], radius: f32) {
+    let total: i64 = slices.iter().map(|(_, duration)| duration.num_seconds()).sum();
+    if total <= 0 {
+        ui.label("Nothing tracked yet.");
+        return;
+    }
+
+    ui.horizontal(|ui| {
+        let (rect, response) = ui.allocate_exact_size(egui::vec2(radius * 2.0, radius * 2.0), egui::Sense::hover());
+        let painter = ui.painter_at(rect);
+        let center = rect.center();
+        let inner = radius * 0.6;
+        let point = |angle: f32, distance: f32| center + distance * egui::vec2(angle.sin(), -angle.cos());
+
+        // Each slice is drawn as convex ring segments, clockwise from the top
+        let mut start = 0.0;
+        for (name, duration) in slices {
+            let sweep = duration.num_seconds() as f32 / total as f32 * std::f32::consts::TAU;
+            let steps = ((sweep / std::f32::consts::TAU * DONUT_STEPS_PER_TURN).ceil() as usize).max(1);
+            for step in 0..steps {
+                let from = start + sweep * step as f32 / steps as f32;
+                let to = start + sweep * (step + 1) as f32 / steps as f32;
+                let points = vec![point(from, inner), point(from, radius), point(to, radius), point(to, inner)];
+                painter.add(egui::Shape::convex_polygon(points, category_color(name), egui::Stroke::NONE));
+            }
+            start += sweep;
+        }
+
+        painter.text(
+            center,
+            egui::Align2::CENTER_CENTER,
+            format_short_duration(Duration::seconds(total)),
+            egui::FontId::proportional(14.0),
+            ui.visuals().text_color(),
+        );
+        let hovered = response
+            .hover_pos()
+            .and_then(|pos| donut_slice_at(slices, pos - center, inner, radius))
+            .map(|index| &slices[index]);
+        if let Some((name, duration)) = hovered {
+            let share = duration.num_seconds() as f32 / total as f32 * 100.0;
+            response.on_hover_text_at_pointer(format!("{}: {} ({:.0}%)", name, format_short_duration(*duration), share));
+        }
+
+        ui.vertical(|ui| {
+            for (name, duration) in slices {
+                ui.horizontal(|ui| {
+                    let (swatch, _) = ui.allocate_exact_size(egui::vec2(10.0, 10.0), egui::Sense::hover());
+                    ui.painter().rect_filled(swatch, 2.0, category_color(name));
+                    ui.label(format!("{} {}", name, format_short_duration(*duration)));
+                });
+            }
+        });
+    });
+}
+
+// The slice under `offset` from the donut's center, clockwise from the top
+fn donut_slice_at(slices: &[(String, Duration)], offset: egui::Vec2, inner: f32, radius: f32) -> Option<usize> {
+    let total: i64 = slices.iter().map(|(_, duration)| duration.num_seconds()).sum();
+    if total <= 0 || !(inner..=radius).contains(&offset.length()) {
+        return None;
+    }
+    let angle = offset.x.atan2(-offset.y).rem_euclid(std::f32::consts::TAU);
+    let mut end = 0.0;
+    slices.iter().position(|(_, duration)| {
+        end += duration.num_seconds() as f32 / total as f32 * std::f32::consts::TAU;
+        angle < end
+    })
+}
+
+/// Horizontal bars, one row per entry, scaled to the longest. Each entry
+/// is colored by its color key.
+pub fn horizontal_bars(ui: &mut egui::Ui, bars: &[(String, Duration, String)]) {
+    if bars.is_empty() {
+        return;
+    }
+    // The first entry goes on top, at the highest position
+    let top = bars.len() - 1;
+    let chart_bars = bars
+        .iter()
+        .enumerate()
+        .map(|(index, (label, duration, color_key))| {
+            Bar::new((top - index) as f64, hours(*duration))
+                .name(format!("{}: {}", label, format_short_duration(*duration)))
+                .fill(category_color(color_key))
+                .width(0.7)
+        })
+        .collect();
+    let labels: Vec<String> = bars.iter().rev().map(|(label, _, _)| label.clone()).collect();
+    let count = labels.len();
+
+    static_plot(ui.next_auto_id())
+        .height(bars.len() as f32 * 22.0 + 24.0)
+        .include_x(0.0)
+        .show_grid([true, false])
+        .y_axis_width(12)
+        .y_grid_spacer(move |_| index_marks(count))
+        .y_axis_formatter(move |mark, max_chars, _| {
+            let label = index_label(&labels, mark.value);
+            label.chars().take(max_chars.max(3)).collect()
+        })
+        .x_axis_formatter(|mark, _, _| hours_label(mark.value))
+        .show(ui, |plot| {
+            plot.bar_chart(
+                BarChart::new(chart_bars)
+                    .horizontal()
+                    .element_formatter(Box::new(|bar, _| bar.name.clone())),
+            );
+        });
+}
+
+/// Line chart of one duration per labeled point, oldest first.
+pub fn line_chart(ui: &mut egui::Ui, points: &[(String, Duration)], height: f32) {
+    if points.is_empty() {
+        return;
+    }
+    let values: Vec<[f64; 2]> = points
+        .iter()
+        .enumerate()
+        .map(|(index, (_, duration))| [index as f64, hours(*duration)])
+        .collect();
+    let labels: Vec<String> = points.iter().map(|(label, _)| label.clone()).collect();
+    let hover_labels = labels.clone();
+    let count = labels.len();
+    let color = ui.visuals().selection.bg_fill;
+
+    static_plot(ui.next_auto_id())
+        .height(height)
+        .include_y(0.0)
+        .include_y(1.0)
+        .x_grid_spacer(move |_| index_marks(count))
+        .x_axis_formatter(move |mark, _, _| index_label(&labels, mark.value))
+        .y_axis_formatter(|mark, _, _| hours_label(mark.value))
+        .label_formatter(move |_, point| {
+            let label = index_label(&hover_labels, point.x.round());
+            format!("{}: {}", label, hours_label(point.y))
+        })
+        .show(ui, |plot| {
+            plot.line(Line::new(values.clone()).color(color).width(2.0));
+            plot.points(Points::new(values).color(color).radius(3.0));
+        });
+}
+
+// A plot that only shows its data: no zooming, dragging or crosshair labels
+fn static_plot(id: egui::Id) -> Plot {
+    Plot::new(id)
+        .allow_zoom(false)
+        .allow_drag(false)
+        .allow_scroll(false)
+        .allow_boxed_zoom(false)
+        .allow_double_click_reset(false)
+        .show_x(false)
+        .show_y(false)
+}
+
+fn hours(duration: Duration) -> f64 {
+    duration.num_seconds() as f64 / 3600.0
+}
+
+fn hours_label(hours: f64) -> String {
+    format_short_duration(Duration::seconds((hours * 3600.0).round() as i64))
+}
+
+// One grid line per entry of a categorical axis
+fn index_marks(count: usize) -> Vec<GridMark> {
+    (0..count)
+        .map(|index| GridMark {
+            value: index as f64,
+            step_size: 1.0,
+        })
+        .collect()
+}
+
+// The label of the entry at `value` on a categorical axis, empty between entries
+fn index_label(labels: &[String], value: f64) -> String {
+    if value < 0.0 || value.fract() != 0.0 {
+        return String::new();
+    }
+    labels.get(value as usize).cloned().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn slices() -> Vec<(String, Duration)> {
+        vec![("Code".into(), Duration::hours(3)), ("Mail".into(), Duration::hours(1))]
+    }
+
+    #[test]
+    fn donut_hover_finds_the_slice_clockwise_from_the_top() {
+        let slices = slices();
+        // Code covers the first three quarters, Mail the last
+        assert_eq!(donut_slice_at(&slices, egui::vec2(0.0, -50.0), 30.0, 70.0), Some(0));
+        assert_eq!(donut_slice_at(&slices, egui::vec2(0.0, 50.0), 30.0, 70.0), Some(0));
+        assert_eq!(donut_slice_at(&slices, egui::vec2(-50.0, -10.0), 30.0, 70.0), Some(1));
+    }
+
+    #[test]
+    fn donut_hover_ignores_the_hole_and_the_outside() {
+        let slices = slices();
+        assert_eq!(donut_slice_at(&slices, egui::vec2(10.0, 10.0), 30.0, 70.0), None);
+        assert_eq!(donut_slice_at(&slices, egui::vec2(80.0, 0.0), 30.0, 70.0), None);
+        assert_eq!(donut_slice_at(&[], egui::vec2(50.0, 0.0), 30.0, 70.0), None);
+    }
+
+    #[test]
+    fn categorical_axes_label_whole_positions_only() {
+        let labels = vec!["Mon".to_string(), "Tue".to_string()];
+        assert_eq!(index_label(&labels, 1.0), "Tue");
+        assert_eq!(index_label(&labels, 0.5), "");
+        assert_eq!(index_label(&labels, -1.0), "");
+        assert_eq!(index_label(&labels, 2.0), "");
+        assert_eq!(index_marks(2).iter().map(|mark| mark.value).collect::<Vec<_>>(), [0.0, 1.0]);
+        assert_eq!(hours_label(1.5), format_short_duration(Duration::minutes(90)));
+    }
+}
@@ -1,14 +1,150 @@
+use crate::utils::ensure_directory;
 use anyhow::Result;
+use chrono::{NaiveTime, Weekday};
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 
 #[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Config {
     pub data_dir: PathBuf,
+    pub timezone: Option<String>, // IANA name, e.g. "Europe/Berlin"; the system timezone when unset
+    pub day_start_hour: u32,      // activity before this hour counts towards the previous day
     pub music_dir: Option<PathBuf>,
+    pub mpris_enabled: bool,               // control an external player during sessions
+    pub mpris_player: Option<String>,      // e.g. "spotify"; nothing is controlled when unset
+    pub mpris_bus_address: Option<String>, // session bus when unset
     pub default_focus_duration: i64, // in minutes
+    pub short_break_duration: i64,   // in minutes
+    pub long_break_duration: i64,    // in minutes
+    pub long_break_interval: u32,    // focus sessions per long break
+    pub focus_end_behavior: FocusEndBehavior,
+    pub extend_minutes: i64,         // used by FocusEndBehavior::Prompt
     pub auto_start_focus: bool,
+    pub auto_start_warmup_minutes: i64, // sustained productive use before a session starts
+    pub focus_blocks: Vec<FocusBlock>,  // scheduled sessions, started when auto start is on
     pub track_window_titles: bool,
+    pub project_paths: Vec<ProjectPathMapping>,
+    pub project_title_rules: Vec<ProjectTitleRule>,
+    pub browser_tab_report: Option<PathBuf>, // written by a browser native-messaging host
+    pub excluded_apps: Vec<String>,           // matched case-insensitively against app names
+    pub title_redaction_patterns: Vec<String>, // regexes; matching titles are never recorded
+    pub category_rules: Vec<CategoryRule>,
+    pub distracting_categories: Vec<String>, // switching to these counts as a focus interruption
+    pub productive_categories: Vec<String>,  // sustained use of these auto-starts a session
+    pub block_distractions: bool,            // enforce `blocked_apps` during focus phases
+    pub blocked_apps: Vec<BlockRule>,
+    pub block_grace_seconds: i64,            // how long a blocked app may stay focused
+    pub emergency_override_minutes: i64,
+    pub do_not_disturb: bool,          // silence notifications during focus phases
+    pub block_websites: bool,          // enforce `blocked_websites` during focus phases
+    pub blocked_websites: Vec<String>, // domains; subdomain "www." is blocked too
+    pub hosts_file: PathBuf,
+    pub hosts_helper: Vec<String>,     // run with the hosts path appended when it is not writable
+    pub encryption: Encryption,
+    pub keep_intervals_days: Option<u32>, // older days keep only per-app totals
+    pub keep_window_titles_days: Option<u32>,
+    pub ics_export_path: Option<PathBuf>, // `data_dir`/rizeclone.ics when unset
+    pub ics_export_days: i64,             // exported range, ending today
+    pub ics_project_blocks: bool,         // also export merged per-project activity blocks
+    pub ics_import_path: Option<PathBuf>, // meetings and planned blocks
+    pub ics_import_watch: bool,           // re-import when the file changes
+    pub plan_rules: Vec<CategoryRule>,    // planned event title pattern -> category it should be spent in
+    pub caldav_enabled: bool,
+    pub caldav_url: Option<String>,           // server, e.g. "http://localhost:5232"
+    pub caldav_calendar_path: String,         // focus sessions are pushed here, e.g. "/alice/focus/"
+    pub caldav_plan_path: Option<String>,     // planned events are pulled from here; `caldav_calendar_path` when unset
+    pub caldav_username: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub caldav_password: Option<String>,      // only where there is no keyring to keep it in
+    pub caldav_password_command: Vec<String>, // prints the password, used instead of the stored one when set
+    pub caldav_sync_minutes: u64,
+}
+
+/// What happens when a focus session's time runs out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum FocusEndBehavior {
+    /// Continue the Pomodoro cycle with a break.
+    #[default]
+    NextPhase,
+    /// Stop the cycle.
+    Stop,
+    /// Keep the session running and count the extra time as overtime.
+    Overtime,
+    /// Wait for the user to extend the session or finish it.
+    Prompt,
+}
+
+/// A focus session planned for `start` (in `Config::timezone`) on `days`, or every day
+/// when `days` is empty.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FocusBlock {
+    #[serde(default)]
+    pub days: Vec<Weekday>,
+    pub start: NaiveTime,
+    pub minutes: i64,
+}
+
+/// What happens when a blocked app stays focused during a focus phase.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum BlockAction {
+    /// Show a reminder over the app.
+    #[default]
+    Nag,
+    /// Minimize its window.
+    Minimize,
+    /// Stop its process (SIGSTOP) until the focus phase ends.
+    Suspend,
+    /// Terminate its process.
+    Kill,
+}
+
+/// Blocks apps whose name contains `app` (case-insensitive).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlockRule {
+    pub app: String,
+    #[serde(default)]
+    pub action: BlockAction,
+}
+
+/// How the activity files under `data_dir` are protected at rest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Encryption {
+    #[default]
+    Off,
+    Passphrase,
+    Keyring,
+}
+
+/// Assigns everything under `path` to `project`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectPathMapping {
+    pub path: PathBuf,
+    pub project: String,
+}
+
+/// Assigns windows whose title matches the regex `pattern` to `project`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectTitleRule {
+    pub pattern: String,
+    pub project: String,
+}
+
+/// Puts apps whose name contains `pattern` (case-insensitive), and websites
+/// on the domain `pattern` or its subdomains, into `category`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CategoryRule {
+    pub pattern: String,
+    pub category: String,
+}
+
+impl CategoryRule {
+    fn new(pattern: &str, category: &str) -> Self {
+        Self {
+            pattern: pattern.to_string(),
+            category: category.to_string(),
+        }
+    }
 }
 
 impl Default for Config {
@@ -17,15 +153,131 @@ impl Default for Config {
             data_dir: dirs::data_dir()
                 .unwrap_or_else(|| PathBuf::from("."))
                 .join("rizeclone"),
+            timezone: None,
+            day_start_hour: 0,
             music_dir: dirs::audio_dir(),
+            mpris_enabled: false,
+            mpris_player: None,
+            mpris_bus_address: None,
             default_focus_duration: 25, // Default to 25 minutes (Pomodoro)
+            short_break_duration: 5,
+            long_break_duration: 15,
+            long_break_interval: 4,
+            focus_end_behavior: FocusEndBehavior::NextPhase,
+            extend_minutes: 5,
             auto_start_focus: false,
+            auto_start_warmup_minutes: 5,
+            focus_blocks: Vec::new(),
             track_window_titles: true,
+            project_paths: Vec::new(),
+            project_title_rules: Vec::new(),
+            browser_tab_report: None,
+            excluded_apps: vec![
+                "keepassxc".to_string(),
+                "1password".to_string(),
+                "bitwarden".to_string(),
+            ],
+            title_redaction_patterns: vec![
+                "(?i)private browsing".to_string(),
+                "(?i)incognito".to_string(),
+                "(?i)inprivate".to_string(),
+            ],
+            category_rules: vec![
+                CategoryRule::new("youtube.com", "Entertainment"),
+                CategoryRule::new("netflix.com", "Entertainment"),
+                CategoryRule::new("twitch.tv", "Entertainment"),
+                CategoryRule::new("reddit.com", "Social"),
+                CategoryRule::new("twitter.com", "Social"),
+                CategoryRule::new("x.com", "Social"),
+                CategoryRule::new("facebook.com", "Social"),
+                CategoryRule::new("instagram.com", "Social"),
+                CategoryRule::new("discord", "Social"),
+                CategoryRule::new("telegram", "Social"),
+                CategoryRule::new("slack", "Communication"),
+                CategoryRule::new("thunderbird", "Communication"),
+                CategoryRule::new("zoom", "Meetings"),
+                CategoryRule::new("teams", "Meetings"),
+                CategoryRule::new("webex", "Meetings"),
+                CategoryRule::new("jitsi", "Meetings"),
+                CategoryRule::new("meet.google.com", "Meetings"),
+                CategoryRule::new("steam", "Games"),
+                CategoryRule::new("code", "Development"),
+                CategoryRule::new("idea", "Development"),
+                CategoryRule::new("vim", "Development"),
+                CategoryRule::new("terminal", "Development"),
+            ],
+            distracting_categories: vec![
+                "Entertainment".to_string(),
+                "Social".to_string(),
+                "Games".to_string(),
+            ],
+            productive_categories: vec!["Development".to_string()],
+            block_distractions: false,
+            blocked_apps: Vec::new(),
+            block_grace_seconds: 10,
+            emergency_override_minutes: 5,
+            do_not_disturb: false,
+            block_websites: false,
+            blocked_websites: Vec::new(),
+            hosts_file: default_hosts_file(),
+            hosts_helper: default_hosts_helper(),
+            encryption: Encryption::Off,
+            keep_intervals_days: None,
+            keep_window_titles_days: None,
+            ics_export_path: None,
+            ics_export_days: 30,
+            ics_project_blocks: false,
+            ics_import_path: None,
+            ics_import_watch: false,
+            plan_rules: vec![
+                CategoryRule::new("meeting", "Meetings"),
+                CategoryRule::new("standup", "Meetings"),
+                CategoryRule::new("call", "Meetings"),
+                CategoryRule::new("1:1", "Meetings"),
+            ],
+            caldav_enabled: false,
+            caldav_url: None,
+            caldav_calendar_path: String::new(),
+            caldav_plan_path: None,
+            caldav_username: None,
+            caldav_password: None,
+            caldav_password_command: Vec::new(),
+            caldav_sync_minutes: 15,
         }
     }
 }
 
+fn default_hosts_file() -> PathBuf {
+    if cfg!(target_os = "windows") {
+        PathBuf::from(r"C:\Windows\System32\drivers\etc\hosts")
+    } else {
+        PathBuf::from("/etc/hosts")
+    }
+}
+
+fn default_hosts_helper() -> Vec<String> {
+    if cfg!(target_os = "linux") {
+        vec!["pkexec".to_string(), "tee".to_string()]
+    } else if cfg!(target_os = "macos") {
+        vec!["sudo".to_string(), "-A".to_string(), "tee".to_string()]
+    } else {
+        Vec::new()
+    }
+}
+
 impl Config {
+    /// Whether focus sessions come with music, built-in or from an MPRIS player.
+    pub fn music_enabled(&self) -> bool {
+        self.music_dir.is_some() || self.mpris_enabled
+    }
+
+    /// Where the iCalendar export is written. Re-exports overwrite it.
+    pub fn ics_export_file(&self) -> PathBuf {
+        self.ics_export_path
+            .clone()
+            .unwrap_or_else(|| self.data_dir.join("rizeclone.ics"))
+    }
+
     pub fn load() -> Result<Self> {
         let config_path = dirs::config_dir()
             .unwrap_or_else(|| PathBuf::from("."))
@@ -45,7 +297,7 @@ impl Config {
             .unwrap_or_else(|| PathBuf::from("."))
             .join("rizeclone");
 
-        std::fs::create_dir_all(&config_dir)?;
+        ensure_directory(&config_dir)?;
 
         let config_path = config_dir.join("config.json");
         let config_str = serde_json::to_string_pretty(self)?;
@@ -0,0 +1,349 @@
+use anyhow::{anyhow, Context, Result};
+use argon2::Argon2;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+const KEY_FILE: &str = "encryption.json";
+const VERIFIER: &[u8] = b"rizeclone-data-key";
+const NONCE_LEN: usize = 24;
+const KEYRING_SERVICE: &str = "rizeclone";
+const KEYRING_ACCOUNT: &str = "data-key";
+const CALDAV_ACCOUNT: &str = "caldav-password";
+
+// Stored next to the data. Holds nothing secret: the salt for passphrase
+// derivation and a known plaintext encrypted with the key, so a wrong
+// passphrase or keyring entry is detected before any data is read.
+#[derive(Serialize, Deserialize)]
+struct KeyFile {
+    salt: Vec<u8>,
+    verifier: Vec<u8>,
+}
+
+/// Key used to encrypt the activity files under `Config::data_dir`.
+pub struct DataKey {
+    cipher: XChaCha20Poly1305,
+    salt: Vec<u8>, // empty for keyring keys
+}
+
+impl DataKey {
+    /// Derives the key from a passphrase. The first unlock sets the passphrase.
+    pub fn from_passphrase(data_dir: &Path, passphrase: &str) -> Result<Self> {
+        if passphrase.is_empty() {
+            return Err(anyhow!("Passphrase must not be empty"));
+        }
+
+        match read_key_file(data_dir)? {
+            Some(key_file) => {
+                let key = Self::new(derive_key(passphrase, &key_file.salt)?, key_file.salt.clone());
+                key.verify(&key_file).context("Incorrect passphrase")?;
+                Ok(key)
+            }
+            None => {
+                let key = Self::generate_from_passphrase(passphrase)?;
+                key.write_key_file(data_dir)?;
+                Ok(key)
+            }
+        }
+    }
+
+    /// Derives a key from a passphrase with a fresh salt. Nothing is written
+    /// until `write_key_file`, so existing data stays readable meanwhile.
+    pub fn generate_from_passphrase(passphrase: &str) -> Result<Self> {
+        if passphrase.is_empty() {
+            return Err(anyhow!("Passphrase must not be empty"));
+        }
+        let mut salt = vec![0u8; 16];
+        OsRng.fill_bytes(&mut salt);
+        Ok(Self::new(derive_key(passphrase, &salt)?, salt))
+    }
+
+    /// Loads the key from the OS keyring, generating and storing one on first use.
+    pub fn from_keyring(data_dir: &Path) -> Result<Self> {
+        match read_key_file(data_dir)? {
+            Some(key_file) => {
+                let secret = keyring_lookup(KEYRING_ACCOUNT)?
+                    .ok_or_else(|| anyhow!("Data key not found in the keyring"))?;
+                let key = Self::new(decode_key(&secret)?, Vec::new());
+                key.verify(&key_file).context("Keyring entry does not match the stored data")?;
+                Ok(key)
+            }
+            None => {
+                let key = Self::generate_in_keyring()?;
+                key.write_key_file(data_dir)?;
+                Ok(key)
+            }
+        }
+    }
+
+    /// Generates a key and stores it in the OS keyring, replacing any earlier
+    /// entry. The key file is only written by `write_key_file`.
+    pub fn generate_in_keyring() -> Result<Self> {
+        let key_bytes = XChaCha20Poly1305::generate_key(&mut OsRng);
+        let secret = encode_hex(&key_bytes);
+        keyring_store(KEYRING_ACCOUNT, "RizeClone data key", &secret)?;
+        // Read it back, a key lost here would lock the data for good
+        if keyring_lookup(KEYRING_ACCOUNT)?.as_deref() != Some(secret.as_str()) {
+            return Err(anyhow!("The data key could not be read back from the keyring"));
+        }
+        Ok(Self::new(key_bytes, Vec::new()))
+    }
+
+    fn new(key: Key, salt: Vec<u8>) -> Self {
+        Self {
+            cipher: XChaCha20Poly1305::new(&key),
+            salt,
+        }
+    }
+
+    /// Returns `nonce || ciphertext`.
+    pub fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>> {
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, plaintext)
+            .map_err(|_| anyhow!("Encryption failed"))?;
+
+        let mut data = nonce.to_vec();
+        data.extend_from_slice(&ciphertext);
+        Ok(data)
+    }
+
+    pub fn decrypt(&self, data: &[u8]) -> Result<Vec<u8>> {
+        if data.len() < NONCE_LEN {
+            return Err(anyhow!("Encrypted data is truncated"));
+        }
+        let (nonce, ciphertext) = data.split_at(NONCE_LEN);
+        self.cipher
+            .decrypt(XNonce::from_slice(nonce), ciphertext)
+            .map_err(|_| anyhow!("Decryption failed: wrong key or corrupted data"))
+    }
+
+    fn verify(&self, key_file: &KeyFile) -> Result<()> {
+        if self.decrypt(&key_file.verifier)? == VERIFIER {
+            Ok(())
+        } else {
+            Err(anyhow!("Key verification failed"))
+        }
+    }
+
+    /// Records this key as the one the data under `data_dir` is encrypted with.
+    pub fn write_key_file(&self, data_dir: &Path) -> Result<()> {
+        let key_file = KeyFile {
+            salt: self.salt.clone(),
+            verifier: self.encrypt(VERIFIER)?,
+        };
+        crate::utils::ensure_directory(&data_dir.to_path_buf())?;
+        let tmp_path = data_dir.join(format!("{}.tmp", KEY_FILE));
+        std::fs::write(&tmp_path, serde_json::to_string_pretty(&key_file)?)?;
+        std::fs::rename(tmp_path, data_dir.join(KEY_FILE))?;
+        Ok(())
+    }
+}
+
+/// Forgets which key the data was encrypted with, after it was decrypted.
+pub fn remove_key_file(data_dir: &Path) -> Result<()> {
+    let path = data_dir.join(KEY_FILE);
+    if path.exists() {
+        std::fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+/// The CalDAV password kept in the OS keyring, if one was stored.
+pub fn caldav_password() -> Result<Option<String>> {
+    keyring_lookup(CALDAV_ACCOUNT)
+}
+
+/// Keeps the CalDAV password in the OS keyring, so it never has to be
+/// written to the config file.
+pub fn store_caldav_password(password: &str) -> Result<()> {
+    keyring_store(CALDAV_ACCOUNT, "RizeClone CalDAV password", password)
+}
+
+fn read_key_file(data_dir: &Path) -> Result<Option<KeyFile>> {
+    let path = data_dir.join(KEY_FILE);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let contents = std::fs::read_to_string(path)?;
+    Ok(Some(serde_json::from_str(&contents)?))
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<Key> {
+    let mut key = Key::default();
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| anyhow!("Key derivation failed: {}", e))?;
+    Ok(key)
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn decode_key(hex: &str) -> Result<Key> {
+    let hex = hex.trim();
+    if hex.len() != 64 {
+        return Err(anyhow!("Keyring entry is not a valid data key"));
+    }
+    let mut key = Key::default();
+    for (i, byte) in key.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16)
+            .map_err(|_| anyhow!("Keyring entry is not a valid data key"))?;
+    }
+    Ok(key)
+}
+
+fn keyring_lookup(account: &str) -> Result<Option<String>> {
+    #[cfg(target_os = "linux")]
+    let output = Command::new("secret-tool")
+        .args(["lookup", "service", KEYRING_SERVICE, "account", account])
+        .output()
+        .context("Failed to run secret-tool")?;
+
+    #[cfg(target_os = "macos")]
+    let output = Command::new("security")
+        .args(["find-generic-password", "-s", KEYRING_SERVICE, "-a", account, "-w"])
+        .output()
+        .context("Failed to run security")?;
+
+    #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+    {
+        let _ = account;
+        return Err(anyhow!("Keyring storage is not supported on this platform"));
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    {
+        let secret = String::from_utf8(output.stdout)?.trim_end_matches(['\r', '\n']).to_string();
+        Ok(if output.status.success() && !secret.is_empty() {
+            Some(secret)
+        } else {
+            None
+        })
+    }
+}
+
+fn keyring_store(account: &str, label: &str, secret: &str) -> Result<()> {
+    #[cfg(target_os = "linux")]
+    {
+        use std::io::Write;
+
+        // secret-tool reads the secret from stdin, keeping it out of the process list
+        let mut child = Command::new("secret-tool")
+            .args([
+                "store",
+                &format!("--label={}", label),
+                "service",
+                KEYRING_SERVICE,
+                "account",
+                account,
+            ])
+            .stdin(Stdio::piped())
+            .spawn()
+            .context("Failed to run secret-tool")?;
+        child
+            .stdin
+            .take()
+            .ok_or_else(|| anyhow!("Failed to open secret-tool stdin"))?
+            .write_all(secret.as_bytes())?;
+        if !child.wait()?.success() {
+            return Err(anyhow!("secret-tool failed to store the {}", label));
+        }
+        Ok(())
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        use std::io::Write;
+
+        // In interactive mode security reads its commands from stdin, so the
+        // secret never appears in the process list. Quoted, as passwords may
+        // contain spaces.
+        let quoted = format!("\"{}\"", secret.replace('\\', "\\\\").replace('"', "\\\""));
+        let mut child = Command::new("security")
+            .arg("-i")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .spawn()
+            .context("Failed to run security")?;
+        writeln!(
+            child.stdin.take().ok_or_else(|| anyhow!("Failed to open security stdin"))?,
+            "add-generic-password -U -s {} -a {} -w {}",
+            KEYRING_SERVICE,
+            account,
+            quoted
+        )?;
+        if !child.wait()?.success() {
+            return Err(anyhow!("security failed to store the {}", label));
+        }
+        Ok(())
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+    {
+        let _ = (account, label, secret);
+        Err(anyhow!("Keyring storage is not supported on this platform"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("rizeclone-crypto-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn passphrase_key_round_trips_and_rejects_a_wrong_passphrase() {
+        let data_dir = temp_dir("passphrase");
+        let key = DataKey::from_passphrase(&data_dir, "correct horse").unwrap();
+        let sealed = key.encrypt(b"day data").unwrap();
+        assert_ne!(&sealed[NONCE_LEN..], b"day data");
+
+        // The second unlock derives the key again from the stored salt
+        let reopened = DataKey::from_passphrase(&data_dir, "correct horse").unwrap();
+        assert_eq!(reopened.decrypt(&sealed).unwrap(), b"day data");
+
+        let error = DataKey::from_passphrase(&data_dir, "wrong horse").err().unwrap();
+        assert_eq!(error.to_string(), "Incorrect passphrase");
+        assert!(DataKey::from_passphrase(&data_dir, "").is_err());
+        std::fs::remove_dir_all(data_dir).unwrap();
+    }
+
+    #[test]
+    fn key_file_verifies_only_the_key_that_wrote_it() {
+        let data_dir = temp_dir("verify");
+        let key_bytes = XChaCha20Poly1305::generate_key(&mut OsRng);
+        let key = DataKey::new(decode_key(&encode_hex(&key_bytes)).unwrap(), Vec::new());
+        key.write_key_file(&data_dir).unwrap();
+
+        let key_file = read_key_file(&data_dir).unwrap().unwrap();
+        assert!(key_file.salt.is_empty());
+        assert!(key.verify(&key_file).is_ok());
+        let other = DataKey::new(XChaCha20Poly1305::generate_key(&mut OsRng), Vec::new());
+        assert!(other.verify(&key_file).is_err());
+
+        remove_key_file(&data_dir).unwrap();
+        assert!(read_key_file(&data_dir).unwrap().is_none());
+        std::fs::remove_dir_all(data_dir).unwrap();
+    }
+
+    #[test]
+    fn tampered_ciphertext_fails_to_decrypt() {
+        let key = DataKey::generate_from_passphrase("secret").unwrap();
+        let mut sealed = key.encrypt(b"day data").unwrap();
+        let last = sealed.len() - 1;
+        sealed[last] ^= 0x01;
+        assert!(key.decrypt(&sealed).is_err());
+        assert!(key.decrypt(&sealed[..NONCE_LEN - 1]).is_err());
+    }
+}
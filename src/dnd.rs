@@ -0,0 +1,254 @@
+use anyhow::{anyhow, Result};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Notification servers whose do-not-disturb state can be controlled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+enum Backend {
+    /// GNOME Shell's `show-banners` setting.
+    Gnome,
+    /// mako's `do-not-disturb` mode, which has to be defined in mako's config.
+    Mako,
+    Dunst,
+}
+
+// Written before notifications are silenced, removed once they are restored
+#[derive(Serialize, Deserialize)]
+struct SavedState {
+    backend: Backend,
+    previous: String,
+}
+
+// Runs a command and returns its trimmed stdout, failing on a non-zero exit
+type CommandRunner = Box<dyn Fn(&str, &[&str]) -> Result<String> + Send>;
+
+/// Silences desktop notifications during focus phases and restores the
+/// previous setting afterwards, or on the next start after a crash.
+pub struct DoNotDisturb {
+    backend: Option<Backend>,
+    state_path: PathBuf,
+    run: CommandRunner,
+}
+
+impl DoNotDisturb {
+    /// Picks the backend for the notification server that is running.
+    pub fn detect(data_dir: &Path) -> Self {
+        Self::with_runner(data_dir, notification_server(), Box::new(run_command))
+    }
+
+    fn with_runner(data_dir: &Path, server: Result<String>, run: CommandRunner) -> Self {
+        let backend = match server {
+            Ok(name) => {
+                let name = name.to_lowercase();
+                if name.contains("gnome") {
+                    Some(Backend::Gnome)
+                } else if name.contains("mako") {
+                    Some(Backend::Mako)
+                } else if name.contains("dunst") {
+                    Some(Backend::Dunst)
+                } else {
+                    info!("Do not disturb is not supported for notification server {}", name);
+                    None
+                }
+            }
+            Err(e) => {
+                info!("No notification server found: {}", e);
+                None
+            }
+        };
+
+        Self {
+            backend,
+            state_path: data_dir.join("dnd_state.json"),
+            run,
+        }
+    }
+
+    pub fn enable(&self) -> Result<()> {
+        let backend = match self.backend {
+            Some(backend) => backend,
+            None => return Ok(()),
+        };
+        if self.state_path.exists() {
+            return Ok(()); // already enabled, keep the original state
+        }
+
+        let state = SavedState {
+            backend,
+            previous: self.current_state(backend)?,
+        };
+        if let Some(dir) = self.state_path.parent() {
+            crate::utils::ensure_directory(&dir.to_path_buf())?;
+        }
+        std::fs::write(&self.state_path, serde_json::to_string(&state)?)?;
+
+        info!("Enabling do not disturb ({:?})", backend);
+        let result = match backend {
+            Backend::Gnome => (self.run)("gsettings", &["set", "org.gnome.desktop.notifications", "show-banners", "false"]),
+            Backend::Mako => (self.run)("makoctl", &["mode", "-a", "do-not-disturb"]),
+            Backend::Dunst => (self.run)("dunstctl", &["set-paused", "true"]),
+        };
+        result.map(|_| ())
+    }
+
+    /// Restores the state saved by `enable`, if any.
+    pub fn restore(&self) -> Result<()> {
+        if !self.state_path.exists() {
+            return Ok(());
+        }
+        let state: SavedState = serde_json::from_str(&std::fs::read_to_string(&self.state_path)?)?;
+
+        info!("Restoring notifications ({:?})", state.backend);
+        let result = match state.backend {
+            Backend::Gnome => (self.run)(
+                "gsettings",
+                &["set", "org.gnome.desktop.notifications", "show-banners", state.previous.as_str()],
+            ),
+            Backend::Mako if !state.previous.lines().any(|mode| mode.trim() == "do-not-disturb") => {
+                (self.run)("makoctl", &["mode", "-r", "do-not-disturb"])
+            }
+            Backend::Mako => Ok(String::new()),
+            Backend::Dunst => (self.run)("dunstctl", &["set-paused", state.previous.as_str()]),
+        };
+
+        match result {
+            Ok(_) => Ok(std::fs::remove_file(&self.state_path)?),
+            Err(e) => {
+                // Keep the saved state so the next start can try again
+                warn!("Failed to restore notifications: {}", e);
+                Err(e)
+            }
+        }
+    }
+
+    fn current_state(&self, backend: Backend) -> Result<String> {
+        match backend {
+            Backend::Gnome => (self.run)("gsettings", &["get", "org.gnome.desktop.notifications", "show-banners"]),
+            Backend::Mako => (self.run)("makoctl", &["mode"]),
+            Backend::Dunst => (self.run)("dunstctl", &["is-paused"]),
+        }
+    }
+}
+
+fn notification_server() -> Result<String> {
+    let connection = zbus::blocking::Connection::session()?;
+    let proxy = zbus::blocking::Proxy::new(
+        &connection,
+        "org.freedesktop.Notifications",
+        "/org/freedesktop/Notifications",
+        "org.freedesktop.Notifications",
+    )?;
+    let (name, _vendor, _version, _spec): (String, String, String, String) =
+        proxy.call("GetServerInformation", &())?;
+    Ok(name)
+}
+
+fn run_command(program: &str, args: &[&str]) -> Result<String> {
+    let output = Command::new(program).args(args).output()?;
+    if !output.status.success() {
+        return Err(anyhow!("{} exited with {}", program, output.status));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("rizeclone-dnd-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    // Records every command and answers state queries with `previous`
+    fn recorder(previous: &'static str, calls: &Arc<Mutex<Vec<String>>>) -> CommandRunner {
+        let calls = Arc::clone(calls);
+        Box::new(move |program, args| {
+            calls.lock().unwrap().push(format!("{} {}", program, args.join(" ")));
+            let query = matches!(args, ["get", ..] | ["is-paused"] | ["mode"]);
+            Ok(if query { previous.to_string() } else { String::new() })
+        })
+    }
+
+    #[test]
+    fn detect_picks_the_backend_of_the_running_server() {
+        let dir = temp_dir("detect");
+        let backend = |server: Result<String>| {
+            DoNotDisturb::with_runner(&dir, server, Box::new(|_, _| Ok(String::new()))).backend
+        };
+        assert_eq!(backend(Ok("gnome-shell".to_string())), Some(Backend::Gnome));
+        assert_eq!(backend(Ok("Mako".to_string())), Some(Backend::Mako));
+        assert_eq!(backend(Ok("dunst".to_string())), Some(Backend::Dunst));
+        assert_eq!(backend(Ok("xfce4-notifyd".to_string())), None);
+        assert_eq!(backend(Err(anyhow!("no session bus"))), None);
+
+        // Without a backend nothing is run or saved
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let dnd = DoNotDisturb::with_runner(&dir, Ok("xfce4-notifyd".to_string()), recorder("", &calls));
+        dnd.enable().unwrap();
+        dnd.restore().unwrap();
+        assert!(calls.lock().unwrap().is_empty());
+        assert!(!dir.exists());
+    }
+
+    #[test]
+    fn enable_saves_the_previous_state_and_restore_brings_it_back() {
+        let dir = temp_dir("restore");
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let dnd = DoNotDisturb::with_runner(&dir, Ok("dunst".to_string()), recorder("false", &calls));
+
+        dnd.enable().unwrap();
+        let saved: SavedState = serde_json::from_str(&std::fs::read_to_string(&dnd.state_path).unwrap()).unwrap();
+        assert_eq!((saved.backend, saved.previous.as_str()), (Backend::Dunst, "false"));
+
+        // A second enable keeps the state from before the first one
+        dnd.enable().unwrap();
+        dnd.restore().unwrap();
+        assert!(!dnd.state_path.exists());
+        assert_eq!(
+            *calls.lock().unwrap(),
+            ["dunstctl is-paused", "dunstctl set-paused true", "dunstctl set-paused false"]
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn restart_restores_notifications_left_silenced_by_a_crash() {
+        let dir = temp_dir("crash");
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        DoNotDisturb::with_runner(&dir, Ok("gnome-shell".to_string()), recorder("true", &calls))
+            .enable()
+            .unwrap();
+
+        // The next start may find another server, the saved backend is restored
+        calls.lock().unwrap().clear();
+        let failing: CommandRunner = Box::new(|program, _| Err(anyhow!("{} is not running", program)));
+        let after_crash = DoNotDisturb::with_runner(&dir, Err(anyhow!("no session bus")), failing);
+        assert!(after_crash.restore().is_err());
+        assert!(after_crash.state_path.exists());
+
+        let after_crash = DoNotDisturb::with_runner(&dir, Err(anyhow!("no session bus")), recorder("", &calls));
+        after_crash.restore().unwrap();
+        assert!(!after_crash.state_path.exists());
+        assert_eq!(
+            *calls.lock().unwrap(),
+            ["gsettings set org.gnome.desktop.notifications show-banners true"]
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn mako_mode_is_kept_when_it_was_already_on() {
+        let dir = temp_dir("mako");
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let dnd = DoNotDisturb::with_runner(&dir, Ok("mako".to_string()), recorder("default\ndo-not-disturb", &calls));
+        dnd.enable().unwrap();
+        dnd.restore().unwrap();
+        assert_eq!(*calls.lock().unwrap(), ["makoctl mode", "makoctl mode -a do-not-disturb"]);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
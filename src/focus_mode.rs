@@ -1,59 +1,1031 @@
+use crate::calendar::FocusSessionSummary;
+use crate::category::CategoryResolver;
+use crate::config::{Config, FocusEndBehavior};
+use crate::dnd::DoNotDisturb;
+use crate::mpris::{MprisController, TrackInfo};
+use crate::music::{MusicPlayer, NowPlaying, PlaybackState};
+use crate::website_blocker::WebsiteBlocker;
 use anyhow::Result;
 use chrono::{DateTime, Duration, Utc};
+use log::{info, warn};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Phase {
+    Work,
+    ShortBreak,
+    LongBreak,
+}
+
+impl Phase {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Phase::Work => "Focus",
+            Phase::ShortBreak => "Short Break",
+            Phase::LongBreak => "Long Break",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FocusSession {
     pub start_time: DateTime<Utc>,
     pub duration: Duration,
     pub music_enabled: bool,
     pub music_path: Option<PathBuf>,
+    #[serde(default = "default_phase")]
+    pub phase: Phase,
+    #[serde(default)]
+    pub paused_at: Option<DateTime<Utc>>,
+    #[serde(default = "Duration::zero")]
+    pub paused_total: Duration,
+    #[serde(default)]
+    pub expired_at: Option<DateTime<Utc>>, // set while running past the planned duration
+    #[serde(default)]
+    pub tracks_played: Vec<String>,
+    #[serde(default)]
+    pub activity: SessionActivity,
+    #[serde(default)]
+    pub intent: Option<String>, // what the session is for, kept across its phases
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+fn default_phase() -> Phase {
+    Phase::Work
+}
+
+impl FocusSession {
+    /// Time spent in this phase so far, not counting pauses.
+    pub fn elapsed(&self, now: DateTime<Utc>) -> Duration {
+        self.paused_at.unwrap_or(now) - self.start_time - self.paused_total
+    }
+
+    pub fn remaining(&self, now: DateTime<Utc>) -> Duration {
+        self.duration - self.elapsed(now)
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused_at.is_some()
+    }
+
+    pub fn overtime(&self, now: DateTime<Utc>) -> Duration {
+        (self.elapsed(now) - self.duration).max(Duration::zero())
+    }
+}
+
+/// What the tracker saw while a focus phase was running.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionActivity {
+    pub app_durations: HashMap<String, Duration>,
+    pub distracted: Duration,
+    pub interruptions: u32, // switches from focused work to a distracting app
+    current_app: Option<String>,
+    in_distraction: bool,
+    last_seen: Option<DateTime<Utc>>,
+}
+
+// Gaps longer than this (sleep, a frozen UI) are not attributed to any app
+const MAX_OBSERVATION_GAP: i64 = 60;
+
+impl Default for SessionActivity {
+    fn default() -> Self {
+        Self {
+            app_durations: HashMap::new(),
+            distracted: Duration::zero(),
+            interruptions: 0,
+            current_app: None,
+            in_distraction: false,
+            last_seen: None,
+        }
+    }
+}
+
+impl SessionActivity {
+    fn observe(&mut self, app_name: &str, distracting: bool, now: DateTime<Utc>) {
+        self.settle(now);
+        if distracting && !self.in_distraction {
+            self.interruptions += 1;
+            info!("Focus interrupted by a distracting app");
+        }
+        self.current_app = Some(app_name.to_string());
+        self.in_distraction = distracting;
+        self.last_seen = Some(now);
+    }
+
+    // Attributes the time since the last observation to the app seen then
+    fn settle(&mut self, now: DateTime<Utc>) {
+        if let (Some(app), Some(last_seen)) = (&self.current_app, self.last_seen) {
+            let elapsed = now - last_seen;
+            if elapsed > Duration::zero() && elapsed <= Duration::seconds(MAX_OBSERVATION_GAP) {
+                *self.app_durations.entry(app.clone()).or_insert(Duration::zero()) += elapsed;
+                if self.in_distraction {
+                    self.distracted += elapsed;
+                }
+            }
+        }
+        self.last_seen = Some(now);
+    }
+
+    // Stops attributing time, e.g. while paused
+    fn suspend(&mut self, now: DateTime<Utc>) {
+        self.settle(now);
+        self.last_seen = None;
+    }
+
+    /// Apps used during the phase, longest first.
+    pub fn apps_used(&self) -> Vec<String> {
+        let mut apps: Vec<_> = self.app_durations.iter().collect();
+        apps.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+        apps.into_iter().map(|(app, _)| app.clone()).collect()
+    }
+
+    /// 0–100: the share of `focused` time not spent in distracting apps,
+    /// minus 5 points per interruption.
+    pub fn quality_score(&self, focused: Duration) -> u8 {
+        let total = focused.num_seconds().max(1) as f64;
+        let undistracted = (1.0 - self.distracted.num_seconds() as f64 / total).clamp(0.0, 1.0);
+        let score = undistracted * 100.0 - 5.0 * self.interruptions as f64;
+        score.clamp(0.0, 100.0).round() as u8
+    }
+}
+
+/// Phase lengths of a Pomodoro cycle.
+#[derive(Debug, Clone, Copy)]
+pub struct CycleSettings {
+    pub work_minutes: i64,
+    pub short_break_minutes: i64,
+    pub long_break_minutes: i64,
+    pub long_break_interval: u32, // work phases per long break
+    pub end_behavior: FocusEndBehavior,
+    pub extend_minutes: i64,
+}
+
+impl Default for CycleSettings {
+    fn default() -> Self {
+        Self {
+            work_minutes: 25,
+            short_break_minutes: 5,
+            long_break_minutes: 15,
+            long_break_interval: 4,
+            end_behavior: FocusEndBehavior::NextPhase,
+            extend_minutes: 5,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum FocusEvent {
+    /// A phase ended. `focused` is the time to record for it and `next` the
+    /// phase that started afterwards, if the cycle continues.
+    PhaseCompleted {
+        finished: Box<FocusSession>,
+        focused: Duration,
+        next: Option<Phase>,
+    },
+    /// A focus phase ran out and keeps counting as overtime.
+    OvertimeStarted,
+    /// A focus phase ran out and waits for the user to extend or finish it.
+    ExtensionPrompt,
+}
+
+impl FocusEvent {
+    /// The summary to store in the calendar. Breaks are not recorded.
+    pub fn focus_summary(&self) -> Option<FocusSessionSummary> {
+        match self {
+            FocusEvent::PhaseCompleted { finished, focused, .. }
+                if finished.phase == Phase::Work && *focused > Duration::zero() =>
+            {
+                Some(FocusSessionSummary {
+                    start_time: finished.start_time,
+                    duration: *focused,
+                    music_used: finished.music_enabled,
+                    tracks_played: finished.tracks_played.clone(),
+                    apps_used: finished.activity.apps_used(),
+                    distracted: finished.activity.distracted,
+                    interruptions: finished.activity.interruptions,
+                    quality_score: Some(finished.activity.quality_score(*focused)),
+                    intent: finished.intent.clone(),
+                    tags: finished.tags.clone(),
+                    rating: None,
+                    note: None,
+                    synced: false,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            FocusEvent::PhaseCompleted { finished, next: Some(next), .. } => {
+                format!("{} finished, {} started", finished.phase.label(), next.label())
+            }
+            FocusEvent::PhaseCompleted { finished, next: None, .. } => {
+                format!("{} finished", finished.phase.label())
+            }
+            FocusEvent::OvertimeStarted => "Time is up, now counting overtime".to_string(),
+            FocusEvent::ExtensionPrompt => "Time is up".to_string(),
+        }
+    }
+}
+
+// What is written to disk so a restart resumes the current phase
+#[derive(Serialize, Deserialize)]
+struct CycleState {
+    session: FocusSession,
+    completed_work_phases: u32,
 }
 
 pub struct FocusMode {
     current_session: Option<FocusSession>,
-    music_playlist: Vec<PathBuf>,
+    music: Option<MusicPlayer>,
+    mpris: Option<MprisController>,
+    system: mpsc::Sender<SystemCommand>,
+    do_not_disturb: Option<bool>, // as last sent to the system worker
+    websites_override_until: Option<DateTime<Utc>>, // emergency override of the website block
+    categories: CategoryResolver,
+    settings: CycleSettings,
+    completed_work_phases: u32,
+    state_path: Option<PathBuf>,
+    events: Vec<FocusEvent>,
 }
 
 impl FocusMode {
     pub fn new() -> Self {
         Self {
             current_session: None,
-            music_playlist: Vec::new(),
+            music: None,
+            mpris: None,
+            system: spawn_system_worker(),
+            do_not_disturb: None,
+            websites_override_until: None,
+            categories: CategoryResolver::new(),
+            settings: CycleSettings::default(),
+            completed_work_phases: 0,
+            state_path: None,
+            events: Vec::new(),
         }
     }
 
-    pub fn start_session(&mut self, duration_minutes: i64, music_enabled: bool) -> Result<()> {
-        let session = FocusSession {
-            start_time: Utc::now(),
-            duration: Duration::minutes(duration_minutes),
+    pub fn apply_config(&mut self, config: &Config) {
+        self.settings = CycleSettings {
+            work_minutes: config.default_focus_duration,
+            short_break_minutes: config.short_break_duration,
+            long_break_minutes: config.long_break_duration,
+            long_break_interval: config.long_break_interval.max(1),
+            end_behavior: config.focus_end_behavior,
+            extend_minutes: config.extend_minutes.max(1),
+        };
+        self.state_path = Some(config.data_dir.join("focus_state.json"));
+        self.categories = CategoryResolver::from_config(config);
+    }
+
+    /// Restores the cycle that was running when the app last exited.
+    pub fn load_state(&mut self) -> Result<()> {
+        let path = match &self.state_path {
+            Some(path) if path.exists() => path,
+            _ => return Ok(()),
+        };
+
+        let state: CycleState = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+        info!("Resuming {} phase", state.session.phase.label());
+        let resume_music = state.session.phase == Phase::Work
+            && state.session.music_enabled
+            && !state.session.is_paused();
+        self.current_session = Some(state.session);
+        self.completed_work_phases = state.completed_work_phases;
+        if resume_music {
+            if let Some(music) = &mut self.music {
+                music.play();
+            }
+            if let Some(mpris) = &mut self.mpris {
+                mpris.play();
+            }
+        }
+        let work = self.current_session.as_ref().map(|session| session.phase == Phase::Work);
+        self.set_distractions_blocked(work.unwrap_or(false));
+        Ok(())
+    }
+
+    fn save_state(&self) {
+        let path = match &self.state_path {
+            Some(path) => path,
+            None => return,
+        };
+
+        let result = match &self.current_session {
+            Some(session) => {
+                let state = CycleState {
+                    session: session.clone(),
+                    completed_work_phases: self.completed_work_phases,
+                };
+                serde_json::to_string_pretty(&state)
+                    .map_err(anyhow::Error::from)
+                    .and_then(|json| {
+                        if let Some(dir) = path.parent() {
+                            crate::utils::ensure_directory(&dir.to_path_buf())?;
+                        }
+                        Ok(std::fs::write(path, json)?)
+                    })
+            }
+            None if path.exists() => std::fs::remove_file(path).map_err(anyhow::Error::from),
+            None => Ok(()),
+        };
+
+        if let Err(e) = result {
+            warn!("Failed to save focus state: {}", e);
+        }
+    }
+
+    pub fn start_session(
+        &mut self,
+        duration_minutes: i64,
+        music_enabled: bool,
+        intent: Option<String>,
+        tags: Vec<String>,
+    ) -> Result<()> {
+        self.completed_work_phases = 0;
+        self.start_phase(
+            Phase::Work,
+            Utc::now(),
+            Duration::minutes(duration_minutes),
             music_enabled,
-            music_path: if music_enabled {
-                self.music_playlist.first().cloned()
+            intent,
+            tags,
+        );
+        Ok(())
+    }
+
+    fn start_phase(
+        &mut self,
+        phase: Phase,
+        start_time: DateTime<Utc>,
+        duration: Duration,
+        music_enabled: bool,
+        intent: Option<String>,
+        tags: Vec<String>,
+    ) {
+        // Music accompanies focus phases and fades out for breaks
+        let mut music_path = None;
+        let play = phase == Phase::Work && music_enabled;
+        if let Some(music) = &mut self.music {
+            if play {
+                music.play();
+                music_path = music.current_track().cloned();
+            } else {
+                music.fade_out_and_pause();
+            }
+        }
+        if let Some(mpris) = &mut self.mpris {
+            if play {
+                mpris.play();
             } else {
-                None
-            },
+                mpris.pause();
+            }
+        }
+        self.set_distractions_blocked(phase == Phase::Work);
+
+        let session = FocusSession {
+            start_time,
+            duration,
+            music_enabled,
+            music_path,
+            phase,
+            paused_at: None,
+            paused_total: Duration::zero(),
+            expired_at: None,
+            tracks_played: Vec::new(),
+            activity: SessionActivity::default(),
+            intent,
+            tags,
         };
 
         self.current_session = Some(session);
-        Ok(())
+        self.save_state();
     }
 
     pub fn end_session(&mut self) -> Result<()> {
-        self.current_session = None;
+        if let Some(mut finished) = self.stop_session() {
+            let now = Utc::now();
+            finished.activity.suspend(now);
+            let focused = finished.elapsed(now);
+            self.events.push(FocusEvent::PhaseCompleted {
+                finished: Box::new(finished),
+                focused,
+                next: None,
+            });
+        }
         Ok(())
     }
 
-    pub fn add_music(&mut self, path: PathBuf) {
-        self.music_playlist.push(path);
+    /// Ends the session without recording it, e.g. when an automatically
+    /// started session was not wanted.
+    pub fn cancel_session(&mut self) {
+        if let Some(cancelled) = self.stop_session() {
+            info!("{} cancelled", cancelled.phase.label());
+        }
+    }
+
+    fn stop_session(&mut self) -> Option<FocusSession> {
+        if let Some(music) = &mut self.music {
+            music.fade_out_and_stop();
+        }
+        if let Some(mpris) = &mut self.mpris {
+            mpris.pause();
+        }
+        self.set_distractions_blocked(false);
+        let stopped = self.current_session.take();
+        self.completed_work_phases = 0;
+        self.save_state();
+        stopped
+    }
+
+    /// Gives an expired focus phase `CycleSettings::extend_minutes` more minutes.
+    pub fn extend(&mut self) {
+        let extend_by = Duration::minutes(self.settings.extend_minutes);
+        if let Some(session) = &mut self.current_session {
+            session.duration = session.elapsed(Utc::now()).max(session.duration) + extend_by;
+            session.expired_at = None;
+            self.save_state();
+        }
+    }
+
+    pub fn pause(&mut self) {
+        if let Some(session) = &mut self.current_session {
+            if session.paused_at.is_none() {
+                let now = Utc::now();
+                session.paused_at = Some(now);
+                session.activity.suspend(now);
+                if let Some(music) = &mut self.music {
+                    music.pause();
+                }
+                if let Some(mpris) = &mut self.mpris {
+                    mpris.pause();
+                }
+                self.save_state();
+            }
+        }
+    }
+
+    pub fn resume(&mut self) {
+        if let Some(session) = &mut self.current_session {
+            if let Some(paused_at) = session.paused_at.take() {
+                session.paused_total += Utc::now() - paused_at;
+                if session.phase == Phase::Work && session.music_enabled {
+                    if let Some(music) = &mut self.music {
+                        music.play();
+                    }
+                    if let Some(mpris) = &mut self.mpris {
+                        mpris.play();
+                    }
+                }
+                self.save_state();
+            }
+        }
+    }
+
+    /// Detects when the current phase runs out and applies the configured
+    /// end behavior. Resulting events are queued for `take_events`.
+    pub fn tick(&mut self, now: DateTime<Utc>) {
+        if let Some(music) = &mut self.music {
+            music.tick();
+        }
+        self.record_track();
+
+        if self.websites_override_until.map(|until| now >= until).unwrap_or(false) {
+            self.websites_override_until = None;
+            let work = self.current_session.as_ref().map(|session| session.phase == Phase::Work);
+            self.set_distractions_blocked(work.unwrap_or(false));
+        }
+
+        let session = match &mut self.current_session {
+            Some(session) => session,
+            None => return,
+        };
+        if session.is_paused() || session.expired_at.is_some() || session.remaining(now) > Duration::zero() {
+            return;
+        }
+
+        // Breaks always lead back into the next focus phase
+        let behavior = if session.phase == Phase::Work {
+            self.settings.end_behavior
+        } else {
+            FocusEndBehavior::NextPhase
+        };
+
+        match behavior {
+            FocusEndBehavior::NextPhase => {
+                session.activity.suspend(now);
+                let focused = session.duration;
+                // The next phase starts where this one ran out, not at the tick that noticed it
+                let boundary = session.start_time + session.duration + session.paused_total;
+                self.advance_phase(focused, boundary);
+            }
+            FocusEndBehavior::Stop => {
+                let finished = self.current_session.take();
+                if let Some(mut finished) = finished {
+                    finished.activity.suspend(now);
+                    info!("{} finished, stopping the cycle", finished.phase.label());
+                    let focused = finished.duration;
+                    self.set_distractions_blocked(false);
+                    self.events.push(FocusEvent::PhaseCompleted {
+                        finished: Box::new(finished),
+                        focused,
+                        next: None,
+                    });
+                }
+                self.completed_work_phases = 0;
+                self.save_state();
+            }
+            FocusEndBehavior::Overtime => {
+                session.expired_at = Some(now);
+                self.events.push(FocusEvent::OvertimeStarted);
+                self.save_state();
+            }
+            FocusEndBehavior::Prompt => {
+                session.expired_at = Some(now);
+                self.events.push(FocusEvent::ExtensionPrompt);
+                self.save_state();
+            }
+        }
+    }
+
+    /// Ends the current phase early and moves on to the next one. Overtime
+    /// is counted as focused time.
+    pub fn skip_phase(&mut self) {
+        if let Some(session) = &mut self.current_session {
+            let now = Utc::now();
+            session.activity.suspend(now);
+            let focused = session.elapsed(now);
+            self.advance_phase(focused, now);
+        }
+    }
+
+    pub fn take_events(&mut self) -> Vec<FocusEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    fn advance_phase(&mut self, focused: Duration, ended_at: DateTime<Utc>) {
+        let finished = match self.current_session.take() {
+            Some(session) => session,
+            None => return,
+        };
+        let from = finished.phase;
+
+        // `u32::is_multiple_of` needs Rust 1.87
+        #[allow(clippy::manual_is_multiple_of)]
+        let to = match from {
+            Phase::Work => {
+                self.completed_work_phases += 1;
+                if self.completed_work_phases % self.settings.long_break_interval == 0 {
+                    Phase::LongBreak
+                } else {
+                    Phase::ShortBreak
+                }
+            }
+            Phase::ShortBreak | Phase::LongBreak => Phase::Work,
+        };
+
+        let minutes = match to {
+            Phase::Work => self.settings.work_minutes,
+            Phase::ShortBreak => self.settings.short_break_minutes,
+            Phase::LongBreak => self.settings.long_break_minutes,
+        };
+        info!("Focus cycle: {} -> {}", from.label(), to.label());
+        self.start_phase(
+            to,
+            ended_at,
+            Duration::minutes(minutes),
+            finished.music_enabled,
+            finished.intent.clone(),
+            finished.tags.clone(),
+        );
+        self.events.push(FocusEvent::PhaseCompleted {
+            finished: Box::new(finished),
+            focused,
+            next: Some(to),
+        });
+    }
+
+    /// Records the app in focus, as seen by the tracker, against the running
+    /// focus phase. Breaks and pauses are not recorded.
+    pub fn observe_activity(&mut self, app_name: &str, domain: Option<&str>, now: DateTime<Utc>) {
+        let distracting = self.categories.is_distracting(app_name, domain);
+        if let Some(session) = &mut self.current_session {
+            if session.phase == Phase::Work && !session.is_paused() {
+                session.activity.observe(app_name, distracting, now);
+            }
+        }
+    }
+
+    pub fn set_music_player(&mut self, player: MusicPlayer) {
+        self.music = Some(player);
+    }
+
+    /// Sets the website blocker and removes a block left behind by a crash.
+    pub fn set_website_blocker(&mut self, blocker: WebsiteBlocker) {
+        let _ = self.system.send(SystemCommand::SetWebsiteBlocker(blocker));
+    }
+
+    /// Turns silencing notifications during focus phases on or off, and
+    /// restores notifications that a crash left silenced. Takes effect for
+    /// the running phase too.
+    pub fn set_do_not_disturb(&mut self, enabled: bool, data_dir: &Path) {
+        if self.do_not_disturb.replace(enabled) == Some(enabled) {
+            return;
+        }
+        let _ = self.system.send(SystemCommand::SetDoNotDisturb {
+            enabled,
+            data_dir: data_dir.to_path_buf(),
+        });
+    }
+
+    /// Lifts the website block until `until`, for an emergency override.
+    pub fn override_website_block(&mut self, until: DateTime<Utc>) {
+        self.websites_override_until = Some(until);
+        let work = self.current_session.as_ref().map(|session| session.phase == Phase::Work);
+        self.set_distractions_blocked(work.unwrap_or(false));
+    }
+
+    // Blocks websites and silences notifications for focus phases
+    fn set_distractions_blocked(&self, blocked: bool) {
+        let _ = self.system.send(SystemCommand::Block(Blocking {
+            websites: blocked && self.websites_override_until.is_none(),
+            notifications: blocked,
+        }));
+    }
+
+    // Waits until the system worker has applied everything sent so far
+    fn wait_for_system(&self, timeout: std::time::Duration) -> bool {
+        let (done, finished) = mpsc::channel();
+        self.system.send(SystemCommand::Flush(done)).is_ok() && finished.recv_timeout(timeout).is_ok()
+    }
+
+    /// Reverts system changes made for the running phase before the app
+    /// exits. The phase itself is resumed on the next start.
+    pub fn shutdown(&mut self) {
+        self.set_distractions_blocked(false);
+        if !self.wait_for_system(std::time::Duration::from_secs(10)) {
+            warn!("Timed out reverting the website block and do not disturb");
+        }
+    }
+
+    pub fn set_mpris_controller(&mut self, controller: MprisController) {
+        self.mpris = Some(controller);
+    }
+
+    pub fn mpris_now_playing(&self) -> Option<TrackInfo> {
+        self.mpris.as_ref().and_then(|mpris| mpris.now_playing())
+    }
+
+    // Remembers what played during a running focus phase for its summary
+    fn record_track(&mut self) {
+        let session = match &mut self.current_session {
+            Some(session) if session.phase == Phase::Work && !session.is_paused() => session,
+            _ => return,
+        };
+
+        let built_in = self
+            .music
+            .as_ref()
+            .and_then(|music| music.now_playing())
+            .filter(|track| track.state == PlaybackState::Playing)
+            .map(|track| track.title);
+        let external = self
+            .mpris
+            .as_ref()
+            .and_then(|mpris| mpris.now_playing())
+            .map(|track| track.display());
+
+        for track in built_in.into_iter().chain(external) {
+            if !session.tracks_played.contains(&track) {
+                session.tracks_played.push(track);
+            }
+        }
+    }
+
+    pub fn now_playing(&self) -> Option<NowPlaying> {
+        self.music.as_ref().and_then(|music| music.now_playing())
+    }
+
+    pub fn next_track(&mut self) {
+        if let Some(music) = &mut self.music {
+            music.next_track();
+            if let Some(session) = &mut self.current_session {
+                session.music_path = music.current_track().cloned();
+            }
+        }
     }
 
     pub fn get_current_session(&self) -> Option<&FocusSession> {
         self.current_session.as_ref()
     }
 
-    pub fn is_session_active(&self) -> bool {
-        self.current_session.is_some()
+    pub fn completed_work_phases(&self) -> u32 {
+        self.completed_work_phases
+    }
+
+    pub fn settings(&self) -> &CycleSettings {
+        &self.settings
+    }
+}
+
+// What the system worker should have blocked right now
+#[derive(Debug, Clone, Copy, Default)]
+struct Blocking {
+    websites: bool,
+    notifications: bool,
+}
+
+enum SystemCommand {
+    SetWebsiteBlocker(WebsiteBlocker),
+    SetDoNotDisturb { enabled: bool, data_dir: PathBuf },
+    Block(Blocking),
+    Flush(mpsc::Sender<()>),
+}
+
+// Website blocks and do not disturb run external commands (gsettings,
+// makoctl, a pkexec prompt for the hosts file) that can take a long time,
+// so they are applied on their own thread instead of under the focus lock.
+fn spawn_system_worker() -> mpsc::Sender<SystemCommand> {
+    let (sender, receiver) = mpsc::channel();
+    std::thread::spawn(move || {
+        let mut websites: Option<WebsiteBlocker> = None;
+        let mut dnd: Option<DoNotDisturb> = None;
+        let mut blocking = Blocking::default();
+
+        for command in receiver {
+            match command {
+                SystemCommand::SetWebsiteBlocker(blocker) => {
+                    for stale in websites.iter().chain(Some(&blocker)) {
+                        if let Err(e) = stale.unblock() {
+                            warn!("Failed to remove a stale website block: {:#}", e);
+                        }
+                    }
+                    websites = Some(blocker);
+                }
+                SystemCommand::SetDoNotDisturb { enabled, data_dir } => {
+                    let detected = DoNotDisturb::detect(&data_dir);
+                    for previous in dnd.iter().chain(Some(&detected)) {
+                        if let Err(e) = previous.restore() {
+                            warn!("Failed to restore notifications: {:#}", e);
+                        }
+                    }
+                    dnd = enabled.then_some(detected);
+                }
+                SystemCommand::Block(wanted) => blocking = wanted,
+                SystemCommand::Flush(done) => {
+                    let _ = done.send(());
+                    continue;
+                }
+            }
+
+            let result = match &websites {
+                Some(websites) if blocking.websites => websites.block(),
+                Some(websites) => websites.unblock(),
+                None => Ok(()),
+            };
+            if let Err(e) = result {
+                warn!("Failed to update the website block: {:#}", e);
+            }
+
+            let result = match &dnd {
+                Some(dnd) if blocking.notifications => dnd.enable(),
+                Some(dnd) => dnd.restore(),
+                None => Ok(()),
+            };
+            if let Err(e) = result {
+                warn!("Failed to update do not disturb: {:#}", e);
+            }
+        }
+    });
+    sender
+}
+
+/// Checks the focus timer several times a second on the runtime, so phases end on time
+/// even when no window is repainting. `on_event` receives every queued event.
+pub fn spawn_timer<F>(focus_mode: Arc<Mutex<FocusMode>>, on_event: F)
+where
+    F: Fn(FocusEvent) + Send + 'static,
+{
+    tokio::spawn(async move {
+        // Frequent enough for smooth music fades
+        let mut interval = tokio::time::interval(std::time::Duration::from_millis(250));
+        loop {
+            interval.tick().await;
+            let events = match focus_mode.lock() {
+                Ok(mut focus) => {
+                    focus.tick(Utc::now());
+                    focus.take_events()
+                }
+                Err(_) => break,
+            };
+            for event in events {
+                on_event(event);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn focus_mode(end_behavior: FocusEndBehavior) -> FocusMode {
+        let mut focus = FocusMode::new();
+        focus.settings = CycleSettings {
+            long_break_interval: 3,
+            end_behavior,
+            ..CycleSettings::default()
+        };
+        focus
+    }
+
+    fn phase(focus: &FocusMode) -> Phase {
+        focus.get_current_session().unwrap().phase
+    }
+
+    fn start_time(focus: &FocusMode) -> DateTime<Utc> {
+        focus.get_current_session().unwrap().start_time
+    }
+
+    #[test]
+    fn every_nth_work_phase_is_followed_by_a_long_break() {
+        let mut focus = focus_mode(FocusEndBehavior::NextPhase);
+        focus.start_session(25, false, None, Vec::new()).unwrap();
+
+        let mut phases = Vec::new();
+        for _ in 0..8 {
+            focus.skip_phase();
+            phases.push(phase(&focus));
+        }
+        use Phase::*;
+        assert_eq!(phases, [ShortBreak, Work, ShortBreak, Work, LongBreak, Work, ShortBreak, Work]);
+        assert_eq!(focus.completed_work_phases(), 4);
+        assert_eq!(focus.get_current_session().unwrap().duration, Duration::minutes(25));
+    }
+
+    #[test]
+    fn tick_moves_on_when_the_phase_runs_out() {
+        let mut focus = focus_mode(FocusEndBehavior::NextPhase);
+        focus.start_session(25, false, Some("Write docs".to_string()), vec!["docs".to_string()]).unwrap();
+        let start = start_time(&focus);
+
+        focus.tick(start + Duration::minutes(24));
+        assert_eq!(phase(&focus), Phase::Work);
+        assert!(focus.take_events().is_empty());
+
+        focus.tick(start + Duration::minutes(25));
+        assert_eq!(phase(&focus), Phase::ShortBreak);
+        assert_eq!(focus.get_current_session().unwrap().duration, Duration::minutes(5));
+        assert_eq!(focus.get_current_session().unwrap().intent.as_deref(), Some("Write docs"));
+        let events = focus.take_events();
+        assert_eq!(events.len(), 1);
+        let summary = events[0].focus_summary().unwrap();
+        assert_eq!(summary.duration, Duration::minutes(25));
+        assert_eq!(summary.tags, ["docs"]);
+
+        // Breaks lead back to work and are not recorded
+        focus.tick(start_time(&focus) + Duration::minutes(5));
+        assert_eq!(phase(&focus), Phase::Work);
+        assert!(focus.take_events()[0].focus_summary().is_none());
+    }
+
+    #[test]
+    fn next_phase_starts_at_the_boundary_of_the_last_one() {
+        let mut focus = focus_mode(FocusEndBehavior::NextPhase);
+        focus.start_session(25, false, None, Vec::new()).unwrap();
+        let start = start_time(&focus);
+        focus.current_session.as_mut().unwrap().paused_total = Duration::minutes(3);
+
+        // A late tick, e.g. after the machine slept, must not stretch the phase
+        focus.tick(start + Duration::minutes(40));
+        assert_eq!(phase(&focus), Phase::ShortBreak);
+        assert_eq!(start_time(&focus), start + Duration::minutes(28));
+    }
+
+    #[test]
+    fn stop_ends_the_cycle() {
+        let mut focus = focus_mode(FocusEndBehavior::Stop);
+        focus.start_session(25, false, None, Vec::new()).unwrap();
+        focus.tick(start_time(&focus) + Duration::minutes(25));
+
+        assert!(focus.get_current_session().is_none());
+        assert_eq!(focus.completed_work_phases(), 0);
+        assert!(matches!(focus.take_events()[..], [FocusEvent::PhaseCompleted { next: None, .. }]));
+    }
+
+    #[test]
+    fn overtime_keeps_the_phase_until_extended() {
+        let mut focus = focus_mode(FocusEndBehavior::Overtime);
+        focus.start_session(25, false, None, Vec::new()).unwrap();
+        let start = start_time(&focus);
+
+        focus.tick(start + Duration::minutes(25));
+        focus.tick(start + Duration::minutes(30));
+        assert_eq!(phase(&focus), Phase::Work);
+        assert!(matches!(focus.take_events()[..], [FocusEvent::OvertimeStarted]));
+        let session = focus.get_current_session().unwrap();
+        assert_eq!(session.overtime(start + Duration::minutes(30)), Duration::minutes(5));
+
+        focus.extend();
+        let session = focus.get_current_session().unwrap();
+        assert!(session.expired_at.is_none());
+        assert!(session.duration >= Duration::minutes(30));
     }
-} 
\ No newline at end of file
+
+    #[test]
+    fn paused_time_is_not_counted() {
+        let mut focus = focus_mode(FocusEndBehavior::NextPhase);
+        focus.start_session(25, false, None, Vec::new()).unwrap();
+        focus.pause();
+        let paused_at = focus.get_current_session().unwrap().paused_at.unwrap();
+
+        // A paused phase never runs out
+        focus.tick(paused_at + Duration::hours(2));
+        assert_eq!(phase(&focus), Phase::Work);
+        assert!(focus.get_current_session().unwrap().is_paused());
+
+        let session = focus.current_session.as_mut().unwrap();
+        let start = paused_at - Duration::minutes(10);
+        session.start_time = start;
+        session.paused_at = Some(start + Duration::minutes(10));
+        assert_eq!(session.elapsed(start + Duration::hours(1)), Duration::minutes(10));
+
+        // Resuming adds the pause to the total and the phase ends 15 minutes later
+        session.paused_at = Some(Utc::now() - Duration::minutes(20));
+        focus.resume();
+        let session = focus.get_current_session().unwrap();
+        assert!(!session.is_paused());
+        assert!(session.paused_total >= Duration::minutes(20));
+        let ends_at = start + session.paused_total + Duration::minutes(25);
+        focus.tick(ends_at - Duration::seconds(1));
+        assert_eq!(phase(&focus), Phase::Work);
+        focus.tick(ends_at);
+        assert_eq!(phase(&focus), Phase::ShortBreak);
+    }
+
+    #[test]
+    fn emergency_override_lifts_the_website_block_until_it_expires() {
+        let dir = std::env::temp_dir().join(format!("rizeclone-focus-hosts-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let hosts = dir.join("hosts");
+        std::fs::write(&hosts, "127.0.0.1 localhost\n").unwrap();
+        let config = Config {
+            block_websites: true,
+            blocked_websites: vec!["example.com".to_string()],
+            hosts_file: hosts.clone(),
+            ..Config::default()
+        };
+        let is_blocked = |focus: &FocusMode| {
+            assert!(focus.wait_for_system(std::time::Duration::from_secs(5)));
+            std::fs::read_to_string(&hosts).unwrap().contains("example.com")
+        };
+
+        let mut focus = focus_mode(FocusEndBehavior::NextPhase);
+        focus.set_website_blocker(WebsiteBlocker::from_config(&config));
+        focus.start_session(25, false, None, Vec::new()).unwrap();
+        assert!(is_blocked(&focus));
+
+        let now = Utc::now();
+        focus.override_website_block(now + Duration::minutes(5));
+        assert!(!is_blocked(&focus));
+        focus.tick(now + Duration::minutes(4));
+        assert!(!is_blocked(&focus));
+        focus.tick(now + Duration::minutes(5));
+        assert!(is_blocked(&focus));
+
+        focus.end_session().unwrap();
+        assert!(!is_blocked(&focus));
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn restart_resumes_the_saved_phase() {
+        let path = std::env::temp_dir().join(format!("rizeclone-focus-state-{}.json", std::process::id()));
+        let mut focus = focus_mode(FocusEndBehavior::NextPhase);
+        focus.state_path = Some(path.clone());
+        focus.start_session(25, false, None, vec!["writing".to_string()]).unwrap();
+        focus.skip_phase();
+        let saved_start = start_time(&focus);
+
+        let mut restored = focus_mode(FocusEndBehavior::NextPhase);
+        restored.state_path = Some(path.clone());
+        restored.load_state().unwrap();
+        assert_eq!(phase(&restored), Phase::ShortBreak);
+        assert_eq!(start_time(&restored), saved_start);
+        assert_eq!(restored.completed_work_phases(), 1);
+        assert_eq!(restored.get_current_session().unwrap().tags, ["writing"]);
+
+        // Ending the cycle removes the saved state
+        restored.end_session().unwrap();
+        assert!(!path.exists());
+    }
+}
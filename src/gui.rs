@@ -1,31 +1,120 @@
 use crate::{
-    calendar::Calendar,
-    config::Config,
-    focus_mode::FocusMode,
+    auto_start::AutoStarter,
+    caldav::{self, SyncHandle},
+    blocker::DistractionBlocker,
+    calendar::{Calendar, DayModel, DaySummary, FocusSessionSummary, Journal, PlannedEvent, SessionFilter, UNCATEGORIZED},
+    category::CategoryResolver,
+    charts::{donut, heatmap, horizontal_bars, legend, line_chart, stacked_bars, HeatmapCell, StackedBar},
+    config::{BlockAction, BlockRule, Config, Encryption, FocusBlock},
+    config::FocusEndBehavior,
+    focus_mode::{FocusEvent, FocusMode, Phase},
+    ics,
+    music::PlaybackState,
+    privacy::PrivacyFilter,
     process_tracker::ProcessTracker,
-    utils::format_duration,
+    report::Report,
+    timeline::show_day_timeline,
+    storage::ActivityStore,
+    utils::{format_duration, format_short_duration, send_notification},
 };
-use chrono::{DateTime, Datelike, Duration, Local, Utc};
+use chrono::{DateTime, Datelike, Duration, Months, NaiveDate, NaiveTime, Timelike, Utc, Weekday};
 use eframe::egui;
+use log::error;
+use std::collections::HashMap;
+use std::sync::mpsc::Receiver;
 use std::sync::{Arc, Mutex};
-use std::time::Instant;
+use std::time::{Instant, SystemTime};
 
 pub struct RizeCloneApp {
     config: Config,
     process_tracker: Arc<Mutex<ProcessTracker>>,
     focus_mode: Arc<Mutex<FocusMode>>,
     calendar: Arc<Mutex<Calendar>>,
-    selected_date: DateTime<Local>,
+    selected_day: NaiveDate,
+    day_model: DayModel,
+    categories: CategoryResolver,
+    timezone_text: String,
     current_tab: Tab,
     last_update: Instant,
     update_interval: std::time::Duration,
+    excluded_apps_text: String,
+    mpris_player_text: String,
+    blocked_websites_text: String,
+    redaction_patterns_text: String,
+    storage: Option<ActivityStore>,
+    unlock_passphrase: String,
+    unlock_error: Option<String>,
+    new_passphrase: String, // when switching to passphrase encryption
+    encryption_status: Option<String>,
+    last_save: Instant,
+    forget_start: String,
+    forget_end: String,
+    forget_status: Option<String>,
+    focus_notice: Option<String>,
+    focus_events: Receiver<FocusEvent>,
+    blocker: DistractionBlocker,
+    override_reason: String,
+    auto_starter: AutoStarter,
+    auto_start_notice: Option<String>,
+    intent_text: String,
+    tags_text: String,
+    reflection: Option<Reflection>,
+    session_query: String, // "#tag text", filters sessions in reports and the calendar
+    heatmap_metric: HeatmapMetric,
+    day_summaries: HashMap<NaiveDate, (u64, DaySummary)>, // with the calendar revision they were taken at
+    week_summaries: Option<(NaiveDate, u64, Vec<DaySummary>, Vec<DaySummary>)>, // week start, calendar revision, this and last week
+    ics_path_text: String,
+    export_status: Option<String>,
+    ics_import_text: String,
+    import_status: Option<String>,
+    import_modified: Option<SystemTime>, // of the imported file, to notice changes
+    last_import_check: Instant,
+    caldav_sync: Option<SyncHandle>,
+    caldav_password_text: String, // a new password, moved to the keyring on save
+    journal: Option<JournalDraft>,
+}
+
+// The selected day's journal while it is being edited
+struct JournalDraft {
+    day: NaiveDate,
+    note: String,
+    went_well: String, // one highlight per line
+    blockers: String,  // one per line
+}
+
+// Rating and note being entered for a finished focus phase
+struct Reflection {
+    start_time: DateTime<Utc>,
+    rating: u8, // 0 until chosen
+    note: String,
+}
+
+const WEEKDAYS: [Weekday; 7] = [
+    Weekday::Mon,
+    Weekday::Tue,
+    Weekday::Wed,
+    Weekday::Thu,
+    Weekday::Fri,
+    Weekday::Sat,
+    Weekday::Sun,
+];
+const SAVE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+const IMPORT_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+
+// What the year heatmap in the Calendar tab is colored by
+#[derive(PartialEq, Clone, Copy)]
+enum HeatmapMetric {
+    FocusTime,
+    Productivity,
 }
 
 #[derive(PartialEq)]
 enum Tab {
     Dashboard,
     Calendar,
+    Week,
     Focus,
+    Reports,
     Settings,
 }
 
@@ -35,27 +124,363 @@ impl RizeCloneApp {
         process_tracker: Arc<Mutex<ProcessTracker>>,
         focus_mode: Arc<Mutex<FocusMode>>,
         calendar: Arc<Mutex<Calendar>>,
+        storage: Option<ActivityStore>,
+        focus_events: Receiver<FocusEvent>,
     ) -> Self {
-        Self {
+        let excluded_apps_text = config.excluded_apps.join("\n");
+        let mpris_player_text = config.mpris_player.clone().unwrap_or_default();
+        let blocked_websites_text = config.blocked_websites.join("\n");
+        let redaction_patterns_text = config.title_redaction_patterns.join("\n");
+        let ics_path_text = config.ics_export_file().display().to_string();
+        let ics_import_text = config
+            .ics_import_path
+            .as_ref()
+            .map(|path| path.display().to_string())
+            .unwrap_or_default();
+        let day_model = DayModel::from_config(&config);
+        let categories = CategoryResolver::from_config(&config);
+        let timezone_text = config.timezone.clone().unwrap_or_default();
+        let mut blocker = DistractionBlocker::new();
+        blocker.apply_config(&config);
+        blocker.restore();
+        let mut auto_starter = AutoStarter::new();
+        auto_starter.apply_config(&config);
+        let mut app = Self {
             config,
             process_tracker,
             focus_mode,
             calendar,
-            selected_date: Local::now(),
+            selected_day: day_model.today(),
+            day_model,
+            categories,
+            timezone_text,
             current_tab: Tab::Dashboard,
             last_update: Instant::now(),
             update_interval: std::time::Duration::from_millis(100), // Update every 100ms
+            excluded_apps_text,
+            mpris_player_text,
+            blocked_websites_text,
+            redaction_patterns_text,
+            storage: None,
+            unlock_passphrase: String::new(),
+            unlock_error: None,
+            new_passphrase: String::new(),
+            encryption_status: None,
+            last_save: Instant::now(),
+            forget_start: String::new(),
+            forget_end: String::new(),
+            forget_status: None,
+            focus_notice: None,
+            focus_events,
+            blocker,
+            override_reason: String::new(),
+            auto_starter,
+            auto_start_notice: None,
+            intent_text: String::new(),
+            tags_text: String::new(),
+            reflection: None,
+            session_query: String::new(),
+            heatmap_metric: HeatmapMetric::FocusTime,
+            day_summaries: HashMap::new(),
+            week_summaries: None,
+            ics_path_text,
+            export_status: None,
+            ics_import_text,
+            import_status: None,
+            import_modified: None,
+            last_import_check: Instant::now(),
+            caldav_sync: None,
+            caldav_password_text: String::new(),
+            journal: None,
+        };
+        if let Some(store) = storage {
+            app.open_storage(store);
+        }
+        app
+    }
+
+    /// Starts syncing, now that the stored history is loaded if it can be.
+    pub fn set_caldav_sync(&mut self, sync: SyncHandle) {
+        sync.sync_now();
+        self.caldav_sync = Some(sync);
+    }
+
+    // Replaces the in-memory calendar with the stored history
+    fn open_storage(&mut self, store: ActivityStore) {
+        match store.load_calendar() {
+            Ok(mut loaded) => {
+                loaded.set_day_model(self.day_model);
+                loaded.apply_privacy(&PrivacyFilter::from_config(&self.config));
+                if let Ok(mut calendar) = self.calendar.lock() {
+                    // Keep focus sessions that finished and events imported while the history was locked
+                    let pending = std::mem::replace(&mut *calendar, loaded);
+                    calendar.carry_over(pending);
+                }
+                self.storage = Some(store);
+                self.unlock_error = None;
+                self.day_summaries.clear();
+                self.week_summaries = None;
+                // Sessions of the loaded history may still have to be pushed
+                if let Some(sync) = &self.caldav_sync {
+                    sync.sync_now();
+                }
+            }
+            Err(e) => {
+                error!("Failed to load activity history: {:#}", e);
+                self.unlock_error = Some(format!("{:#}", e));
+            }
+        }
+    }
+
+    // While encrypted storage is locked, intervals stay queued in the tracker
+    fn is_locked(&self) -> bool {
+        self.storage.is_none() && self.config.encryption != Encryption::Off
+    }
+
+    fn sync_calendar(&mut self) {
+        if self.is_locked() {
+            return;
+        }
+
+        if let Ok(mut tracker) = self.process_tracker.lock() {
+            let intervals = tracker.take_completed_intervals();
+            if let Ok(mut calendar) = self.calendar.lock() {
+                for interval in intervals {
+                    let _ = calendar.add_interval(interval);
+                }
+            }
+        }
+    }
+
+    fn save_calendar(&mut self) {
+        if let (Some(store), Ok(mut calendar)) = (&self.storage, self.calendar.lock()) {
+            calendar.apply_retention(
+                self.day_model.today(),
+                self.config.keep_intervals_days,
+                self.config.keep_window_titles_days,
+            );
+            if let Err(e) = store.save(&mut calendar) {
+                error!("Failed to save activity history: {:#}", e);
+            }
+        }
+        self.last_save = Instant::now();
+    }
+
+    // Re-encrypts the history when the encryption setting changed. On failure
+    // the setting goes back to the mode the data is still stored in.
+    fn change_encryption(&mut self) {
+        let store = match &self.storage {
+            Some(store) if store.encryption() != self.config.encryption => store,
+            _ => return,
+        };
+        let stored_encryption = store.encryption();
+        let passphrase = std::mem::take(&mut self.new_passphrase);
+        let result = match self.calendar.lock() {
+            Ok(mut calendar) => store.change_encryption(
+                self.config.encryption,
+                Some(passphrase.as_str()).filter(|passphrase| !passphrase.is_empty()),
+                &mut calendar,
+            ),
+            Err(_) => return,
+        };
+
+        match result {
+            Ok(store) => {
+                self.storage = Some(store);
+                self.encryption_status = Some("Activity history re-encrypted".to_string());
+            }
+            Err(e) => {
+                error!("Failed to change encryption: {:#}", e);
+                self.encryption_status = Some(format!("{:#}", e));
+                self.config.encryption = stored_encryption;
+            }
+        }
+    }
+
+    fn render_unlock(&mut self, ui: &mut egui::Ui) {
+        ui.heading("Unlock Activity Data");
+
+        if self.config.encryption == Encryption::Passphrase {
+            ui.label("Your activity history is encrypted. Enter your passphrase to unlock it.");
+            let response = ui.add(
+                egui::TextEdit::singleline(&mut self.unlock_passphrase).password(true),
+            );
+            let submitted = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            if ui.button("Unlock").clicked() || submitted {
+                let passphrase = std::mem::take(&mut self.unlock_passphrase);
+                match ActivityStore::unlock(&self.config, Some(&passphrase)) {
+                    Ok(store) => self.open_storage(store),
+                    Err(e) => self.unlock_error = Some(format!("{:#}", e)),
+                }
+            }
+        } else {
+            ui.label("The data key could not be read from the system keyring.");
+            if ui.button("Retry").clicked() {
+                match ActivityStore::unlock(&self.config, None) {
+                    Ok(store) => self.open_storage(store),
+                    Err(e) => self.unlock_error = Some(format!("{:#}", e)),
+                }
+            }
+        }
+
+        if let Some(error) = &self.unlock_error {
+            ui.colored_label(egui::Color32::RED, error);
         }
     }
 
+    fn forget_range(&mut self) {
+        let parse = |text: &str| NaiveDate::parse_from_str(text.trim(), "%Y-%m-%d");
+        let (start, end) = match (parse(&self.forget_start), parse(&self.forget_end)) {
+            (Ok(start), Ok(end)) if start <= end => (start, end),
+            _ => {
+                self.forget_status = Some("Enter a valid range as YYYY-MM-DD".to_string());
+                return;
+            }
+        };
+
+        if let Ok(mut tracker) = self.process_tracker.lock() {
+            tracker.forget_range(self.day_model.start_of(start), self.day_model.end_of(end));
+        }
+        if let Ok(mut calendar) = self.calendar.lock() {
+            let sessions = calendar.forget_range(start, end);
+            caldav::queue_deletions(&self.config, &sessions);
+        }
+        self.save_calendar();
+        self.forget_status = Some(format!("Forgot all data from {} to {}", start, end));
+    }
+
+    fn export_ics(&mut self) {
+        let end = self.day_model.today();
+        let start = end - Duration::days(self.config.ics_export_days.max(1) - 1);
+        let contents = match self.calendar.lock() {
+            Ok(calendar) => ics::export(&calendar, start, end, self.config.ics_project_blocks),
+            Err(_) => return,
+        };
+
+        let path = self.config.ics_export_file();
+        let result = path
+            .parent()
+            .map(|dir| crate::utils::ensure_directory(&dir.to_path_buf()))
+            .unwrap_or(Ok(()))
+            .and_then(|_| Ok(std::fs::write(&path, contents)?));
+        self.export_status = Some(match result {
+            Ok(()) => format!("Exported {} to {} to {}", start, end, path.display()),
+            Err(e) => {
+                error!("Failed to export calendar: {}", e);
+                format!("Export failed: {}", e)
+            }
+        });
+    }
+
+    /// Imports the configured `.ics` file. Unless `force` is set, nothing
+    /// happens while the file is unchanged since the last import.
+    fn import_ics(&mut self, force: bool) {
+        let path = match &self.config.ics_import_path {
+            Some(path) if !self.is_locked() => path.clone(),
+            _ => return,
+        };
+        let modified = std::fs::metadata(&path).and_then(|metadata| metadata.modified()).ok();
+        if !force && modified.is_some() && modified == self.import_modified {
+            return;
+        }
+
+        let contents = match std::fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) => {
+                error!("Failed to read calendar import: {}", e);
+                self.import_status = Some(format!("Import failed: {}", e));
+                return;
+            }
+        };
+        let today = self.day_model.today();
+        let events = ics::import(
+            &contents,
+            &path.display().to_string(),
+            &self.day_model,
+            self.day_model.start_of(today - Duration::days(ics::PLAN_PAST_DAYS)),
+            self.day_model.end_of(today + Duration::days(ics::PLAN_FUTURE_DAYS)),
+        );
+
+        let count = events.len();
+        if let Ok(mut calendar) = self.calendar.lock() {
+            calendar.replace_planned_events(&path.display().to_string(), events);
+        }
+        self.import_modified = modified;
+        self.import_status = Some(format!("Imported {} events from {}", count, path.display()));
+    }
+
     fn render_dashboard(&mut self, ui: &mut egui::Ui) {
         ui.heading("Dashboard");
-        
+
+        let today = self.day_model.today();
+        let (mut categories, mut apps, focus_days) = match self.calendar.lock() {
+            Ok(calendar) => {
+                let (categories, apps) = match calendar.get_activity_for_day(today) {
+                    Some(activity) => (
+                        activity.category_durations(&self.categories),
+                        activity.process_durations.iter().map(|(app, duration)| (app.clone(), *duration)).collect(),
+                    ),
+                    None => (Vec::new(), Vec::new()),
+                };
+                let focus_days = calendar.day_summaries(today - Duration::days(13), today, &self.categories);
+                (categories, apps, focus_days)
+            }
+            Err(_) => return,
+        };
+
+        // The interval in progress is still in the tracker
+        let current = self.process_tracker.lock().ok().and_then(|tracker| tracker.current_interval().cloned());
+        if let Some(interval) = current {
+            let start = interval.start.max(self.day_model.start_of(today));
+            let duration = interval.end - start;
+            if duration > Duration::zero() {
+                let category = self
+                    .categories
+                    .categorize(&interval.app_name, interval.domain.as_deref())
+                    .unwrap_or(UNCATEGORIZED);
+                add_duration(&mut categories, category, duration);
+                add_duration(&mut apps, &interval.app_name, duration);
+            }
+        }
+        categories.sort_by_key(|(_, duration)| std::cmp::Reverse(*duration));
+        apps.sort_by_key(|(_, duration)| std::cmp::Reverse(*duration));
+        apps.truncate(8);
+
+        egui::CollapsingHeader::new("Today by Category")
+            .default_open(true)
+            .show(ui, |ui| donut(ui, &categories, 70.0));
+
+        egui::CollapsingHeader::new("Top Apps Today")
+            .default_open(true)
+            .show(ui, |ui| {
+                let bars: Vec<_> = apps
+                    .into_iter()
+                    .map(|(app, duration)| {
+                        let color_key = self.categories.categorize(&app, None).unwrap_or(&app).to_string();
+                        (app, duration, color_key)
+                    })
+                    .collect();
+                if bars.is_empty() {
+                    ui.label("Nothing tracked yet.");
+                }
+                horizontal_bars(ui, &bars);
+            });
+
+        egui::CollapsingHeader::new("Focus Time, Last 14 Days")
+            .default_open(true)
+            .show(ui, |ui| {
+                let points: Vec<_> = focus_days
+                    .iter()
+                    .map(|day| (day.day.format("%-d %b").to_string(), day.focus))
+                    .collect();
+                line_chart(ui, &points, 140.0);
+            });
+
         // Active applications section
         ui.collapsing("Active Applications", |ui| {
             if let Ok(tracker) = self.process_tracker.lock() {
                 let mut apps: Vec<_> = tracker.get_active_apps().iter().collect();
-                apps.sort_by(|a, b| b.1.duration.cmp(&a.1.duration));
+                apps.sort_by_key(|(_, info)| std::cmp::Reverse(info.duration));
 
                 for (name, info) in apps {
                     ui.horizontal(|ui| {
@@ -65,6 +490,9 @@ impl RizeCloneApp {
                             ui.label("○"); // Inactive indicator
                         }
                         ui.label(name);
+                        if let Some(project) = &info.project {
+                            ui.label(format!("[{}]", project));
+                        }
                         if let Some(title) = &info.window_title {
                             ui.label(format!(" - {}", title));
                         }
@@ -74,26 +502,59 @@ impl RizeCloneApp {
             }
         });
 
+        // Projects section
+        ui.collapsing("Projects", |ui| {
+            if let Ok(tracker) = self.process_tracker.lock() {
+                let mut projects: Vec<_> = tracker.get_project_durations().iter().collect();
+                projects.sort_by_key(|(_, duration)| std::cmp::Reverse(**duration));
+
+                for (project, duration) in projects {
+                    ui.horizontal(|ui| {
+                        ui.label(project);
+                        ui.label(format_duration(*duration));
+                    });
+                }
+            }
+        });
+
+        // Websites section
+        ui.collapsing("Websites", |ui| {
+            if let Ok(tracker) = self.process_tracker.lock() {
+                let mut domains: Vec<_> = tracker.get_domain_durations().iter().collect();
+                domains.sort_by_key(|(_, duration)| std::cmp::Reverse(**duration));
+
+                for (domain, duration) in domains {
+                    ui.horizontal(|ui| {
+                        ui.label(domain);
+                        ui.label(format_duration(*duration));
+                    });
+                }
+            }
+        });
+
         // Focus mode section
         ui.collapsing("Focus Mode", |ui| {
-            if let Ok(focus) = self.focus_mode.lock() {
+            if let Ok(mut focus) = self.focus_mode.lock() {
                 if let Some(session) = focus.get_current_session() {
                     ui.label(format!(
-                        "Current Session: {} minutes",
-                        session.duration.num_minutes()
+                        "{}: {} remaining{}",
+                        session.phase.label(),
+                        format_duration(session.remaining(Utc::now()).max(Duration::zero())),
+                        if session.is_paused() { " (paused)" } else { "" }
                     ));
-                    if session.music_enabled {
-                        ui.label("Music: Playing");
+                    if let Some(track) = focus.now_playing() {
+                        ui.label(format!("Music: {} ({:?})", track.title, track.state));
                     }
-                } else {
-                    if ui.button("Start Focus Session").clicked() {
-                        if let Ok(mut focus) = self.focus_mode.lock() {
-                            let _ = focus.start_session(
-                                self.config.default_focus_duration,
-                                self.config.music_dir.is_some(),
-                            );
-                        }
+                    if let Some(track) = focus.mpris_now_playing() {
+                        ui.label(format!("Music: {} ({})", track.display(), track.player));
                     }
+                } else if ui.button("Start Focus Session").clicked() {
+                    let _ = focus.start_session(
+                        self.config.default_focus_duration,
+                        self.config.music_enabled(),
+                        None,
+                        Vec::new(),
+                    );
                 }
             }
         });
@@ -104,81 +565,824 @@ impl RizeCloneApp {
 
         // Month navigation
         ui.horizontal(|ui| {
+            // Month arithmetic keeps the day, clamped to the length of the month
             if ui.button("←").clicked() {
-                self.selected_date = self.selected_date - chrono::Duration::days(30);
+                if let Some(day) = self.selected_day.checked_sub_months(Months::new(1)) {
+                    self.selected_day = day;
+                }
             }
             ui.label(format!(
                 "{} {}",
-                self.selected_date.format("%B"),
-                self.selected_date.year()
+                self.selected_day.format("%B"),
+                self.selected_day.year()
             ));
             if ui.button("→").clicked() {
-                self.selected_date = self.selected_date + chrono::Duration::days(30);
+                if let Some(day) = self.selected_day.checked_add_months(Months::new(1)) {
+                    self.selected_day = day;
+                }
+            }
+            if ui.button("Today").clicked() {
+                self.selected_day = self.day_model.today();
             }
         });
 
-        // Calendar grid
-        egui::Grid::new("calendar_grid").show(ui, |ui| {
-            // Day headers
-            for day in ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"] {
-                ui.label(day);
+        // Calendar grid: six weeks starting on the Monday on or before the 1st
+        let selected_day = self.selected_day;
+        let first_of_month = selected_day.with_day(1).unwrap_or(selected_day);
+        let grid_start = first_of_month - Duration::days(first_of_month.weekday().num_days_from_monday() as i64);
+        let today = self.day_model.today();
+        let mut clicked_day = None;
+
+        if let Ok(calendar) = self.calendar.lock() {
+            egui::Grid::new("calendar_grid").spacing([4.0, 4.0]).show(ui, |ui| {
+                for day in ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"] {
+                    ui.label(day);
+                }
+                ui.end_row();
+
+                for week in 0..6 {
+                    for weekday in 0..7 {
+                        let day = grid_start + Duration::days(week * 7 + weekday);
+                        let mut text = format!("{}", day.day());
+                        if let Some(activity) = calendar.get_activity_for_day(day) {
+                            text.push_str(&format!("\n{}", format_short_duration(activity.total_time())));
+                            let focus_time = activity.focus_time();
+                            if focus_time > Duration::zero() {
+                                text.push_str(&format!("\n◎ {}", format_short_duration(focus_time)));
+                            }
+                        }
+
+                        let mut text = egui::RichText::new(text);
+                        if day.month() != first_of_month.month() {
+                            text = text.weak();
+                        }
+                        if day == today {
+                            text = text.strong();
+                        }
+                        let cell = egui::Button::new(text).selected(day == selected_day);
+                        if ui.add_sized([72.0, 52.0], cell).clicked() {
+                            clicked_day = Some(day);
+                        }
+                    }
+                    ui.end_row();
+                }
+            });
+        }
+
+        if let Some(day) = clicked_day {
+            self.selected_day = day;
+        }
+
+        ui.collapsing("Year Overview", |ui| self.render_year_heatmap(ui));
+
+        ui.separator();
+        ui.heading(self.selected_day.format("%A, %-d %B %Y").to_string());
+
+        if let Ok(calendar) = self.calendar.lock() {
+            let planned = calendar.planned_events_for_day(self.selected_day);
+            if let Some(activity) = calendar.get_activity_for_day(self.selected_day) {
+                ui.label(format!(
+                    "Tracked: {}   Focus: {}",
+                    format_duration(activity.total_time()),
+                    format_duration(activity.focus_time())
+                ));
+
+                egui::CollapsingHeader::new("Timeline")
+                    .default_open(true)
+                    .show(ui, |ui| {
+                        show_day_timeline(ui, activity, &planned, self.selected_day, &self.day_model, &self.categories);
+                    });
+                plan_vs_actual(ui, &calendar, &planned, &self.day_model, &self.categories);
+
+                ui.collapsing("Focus Sessions", |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("Filter (#tag text):");
+                        ui.text_edit_singleline(&mut self.session_query);
+                    });
+                    let filter = SessionFilter::parse(&self.session_query);
+                    for session in activity.focus_sessions.iter().filter(|session| filter.matches(session)) {
+                        session_row(ui, session, &self.day_model);
+                    }
+                });
+
+                ui.collapsing("Projects", |ui| {
+                    let mut projects: Vec<_> = activity.project_durations.iter().collect();
+                    projects.sort_by_key(|(_, duration)| std::cmp::Reverse(**duration));
+
+                    for (project, duration) in projects {
+                        ui.horizontal(|ui| {
+                            ui.label(project);
+                            ui.label(format_duration(*duration));
+                        });
+                    }
+                });
+
+                ui.collapsing("Applications", |ui| {
+                    let mut apps: Vec<_> = activity.process_durations.iter().collect();
+                    apps.sort_by_key(|(_, duration)| std::cmp::Reverse(**duration));
+
+                    for (app, duration) in apps {
+                        ui.horizontal(|ui| {
+                            ui.label(app);
+                            ui.label(format_duration(*duration));
+                        });
+                    }
+                });
+            } else {
+                ui.label("No activity recorded.");
+                plan_vs_actual(ui, &calendar, &planned, &self.day_model, &self.categories);
             }
-            ui.end_row();
+        }
 
-            // Calendar days
-            if let Ok(calendar) = self.calendar.lock() {
-                if let Some(activity) = calendar.get_activity_for_date(self.selected_date.into()) {
-                    ui.label(format!(
-                        "Total Focus Time: {}",
-                        format_duration(
-                            activity
-                                .focus_sessions
-                                .iter()
-                                .map(|s| s.duration)
-                                .sum::<Duration>()
-                        )
-                    ));
+        self.render_journal(ui);
+    }
+
+    fn render_journal(&mut self, ui: &mut egui::Ui) {
+        // Edits to the previous day's journal are kept when another day is selected
+        if self.journal.as_ref().map(|draft| draft.day) != Some(self.selected_day) {
+            self.save_journal();
+            let journal = match self.calendar.lock() {
+                Ok(calendar) => calendar
+                    .get_activity_for_day(self.selected_day)
+                    .map(|activity| activity.journal.clone())
+                    .unwrap_or_default(),
+                Err(_) => return,
+            };
+            self.journal = Some(JournalDraft {
+                day: self.selected_day,
+                note: journal.note,
+                went_well: journal.went_well.join("\n"),
+                blockers: journal.blockers.join("\n"),
+            });
+        }
+
+        let mut save = false;
+        egui::CollapsingHeader::new("Journal")
+            .default_open(true)
+            .show(ui, |ui| {
+                if let Some(draft) = self.journal.as_mut() {
+                    ui.label("Reflection:");
+                    ui.text_edit_multiline(&mut draft.note);
+                    ui.label("What went well (one per line):");
+                    ui.text_edit_multiline(&mut draft.went_well);
+                    ui.label("Blockers (one per line):");
+                    ui.text_edit_multiline(&mut draft.blockers);
+                    save = ui.button("Save Journal").clicked();
                 }
+            });
+
+        if save {
+            self.save_journal();
+        }
+    }
+
+    // Stores the journal being edited, if it differs from the stored one
+    fn save_journal(&mut self) {
+        let draft = match &self.journal {
+            Some(draft) => draft,
+            None => return,
+        };
+        let journal = Journal {
+            note: draft.note.trim().to_string(),
+            went_well: non_empty_lines(&draft.went_well),
+            blockers: non_empty_lines(&draft.blockers),
+        };
+        let changed = match self.calendar.lock() {
+            Ok(mut calendar) => {
+                let stored = calendar.get_activity_for_day(draft.day).map(|activity| &activity.journal);
+                let changed = stored.unwrap_or(&Journal::default()) != &journal;
+                if changed {
+                    calendar.set_journal(draft.day, journal);
+                }
+                changed
             }
-        });
+            Err(_) => false,
+        };
+        if changed {
+            self.save_calendar();
+        }
     }
 
     fn render_focus(&mut self, ui: &mut egui::Ui) {
         ui.heading("Focus Mode");
 
-        if let Ok(mut focus) = self.focus_mode.lock() {
-            if focus.is_session_active() {
-                if let Some(session) = focus.get_current_session() {
-                    let elapsed = Utc::now() - session.start_time;
-                    let remaining = session.duration - elapsed;
+        if let Some(notice) = &self.focus_notice {
+            ui.label(notice);
+        }
+        if let Some(until) = self.blocker.override_until().filter(|until| *until > Utc::now()) {
+            ui.label(format!(
+                "Blocking paused until {}",
+                self.day_model.local_time(until).format("%H:%M")
+            ));
+        } else if self.config.block_websites || self.config.block_distractions {
+            // Blocked websites show no reminder, so the override is offered here too
+            let working = self
+                .focus_mode
+                .lock()
+                .ok()
+                .and_then(|focus| focus.get_current_session().map(|session| session.phase == Phase::Work))
+                .unwrap_or(false);
+            if working {
+                let mut override_clicked = false;
+                ui.horizontal(|ui| {
+                    ui.label("Emergency override (reason is logged):");
+                    ui.text_edit_singleline(&mut self.override_reason);
+                    let label = format!("Unblock for {} min", self.config.emergency_override_minutes);
+                    let has_reason = !self.override_reason.trim().is_empty();
+                    override_clicked = ui.add_enabled(has_reason, egui::Button::new(label)).clicked();
+                });
+                if override_clicked {
+                    self.emergency_override();
+                }
+            }
+        }
 
+        if let Ok(mut focus) = self.focus_mode.lock() {
+            if let Some(session) = focus.get_current_session().cloned() {
+                let now = Utc::now();
+                ui.label(format!(
+                    "{} ({} of {} completed before the long break)",
+                    session.phase.label(),
+                    focus.completed_work_phases() % focus.settings().long_break_interval,
+                    focus.settings().long_break_interval
+                ));
+                if let Some(intent) = &session.intent {
+                    ui.label(format!("Working on: {}", intent));
+                }
+                if session.expired_at.is_some() {
+                    ui.label(format!("Overtime: +{}", format_duration(session.overtime(now))));
+                } else {
                     ui.label(format!(
                         "Time Remaining: {}",
-                        format_duration(remaining)
+                        format_duration(session.remaining(now).max(Duration::zero()))
+                    ));
+                }
+                let progress = session.elapsed(now).num_seconds() as f32
+                    / session.duration.num_seconds().max(1) as f32;
+                ui.add(egui::ProgressBar::new(progress.clamp(0.0, 1.0)));
+                if session.phase == Phase::Work && session.activity.interruptions > 0 {
+                    ui.label(format!(
+                        "Interruptions: {} ({} distracted)",
+                        session.activity.interruptions,
+                        format_duration(session.activity.distracted)
                     ));
+                }
+
+                if let Some(track) = focus.now_playing() {
+                    ui.horizontal(|ui| {
+                        ui.label(format!("♪ {}", track.title))
+                            .on_hover_text(track.path.display().to_string());
+                        if track.state == PlaybackState::Paused {
+                            ui.label("(paused)");
+                        }
+                        if ui.small_button("Next Track").clicked() {
+                            focus.next_track();
+                        }
+                    });
+                }
+                if let Some(track) = focus.mpris_now_playing() {
+                    ui.label(format!("♪ {}", track.display()))
+                        .on_hover_text(format!("Playing in {}", track.player));
+                }
+
+                if session.expired_at.is_some() && focus.settings().end_behavior == FocusEndBehavior::Prompt {
+                    ui.horizontal(|ui| {
+                        let extend_label = format!("Extend by {} min", focus.settings().extend_minutes);
+                        if ui.button(extend_label).clicked() {
+                            focus.extend();
+                            self.focus_notice = None;
+                        }
+                        if ui.button("Take a Break").clicked() {
+                            focus.skip_phase();
+                        }
+                        if ui.button("Finish").clicked() {
+                            let _ = focus.end_session();
+                        }
+                    });
+                    return;
+                }
+
+                ui.horizontal(|ui| {
+                    if session.is_paused() {
+                        if ui.button("Resume").clicked() {
+                            focus.resume();
+                        }
+                    } else if ui.button("Pause").clicked() {
+                        focus.pause();
+                    }
+
+                    if ui.button("Skip").clicked() {
+                        focus.skip_phase();
+                    }
 
                     if ui.button("End Session").clicked() {
                         let _ = focus.end_session();
                     }
-                }
+                });
             } else {
+                let mut changed = false;
                 ui.horizontal(|ui| {
                     ui.label("Duration (minutes):");
-                    let mut duration = self.config.default_focus_duration;
-                    if ui.add(egui::DragValue::new(&mut duration).speed(1)).changed() {
-                        self.config.default_focus_duration = duration;
-                    }
+                    changed |= ui
+                        .add(egui::DragValue::new(&mut self.config.default_focus_duration).speed(1).clamp_range(1..=240))
+                        .changed();
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Short break (minutes):");
+                    changed |= ui
+                        .add(egui::DragValue::new(&mut self.config.short_break_duration).speed(1).clamp_range(1..=60))
+                        .changed();
                 });
+                ui.horizontal(|ui| {
+                    ui.label("Long break (minutes):");
+                    changed |= ui
+                        .add(egui::DragValue::new(&mut self.config.long_break_duration).speed(1).clamp_range(1..=120))
+                        .changed();
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Long break every:");
+                    changed |= ui
+                        .add(egui::DragValue::new(&mut self.config.long_break_interval).clamp_range(1..=12).suffix(" sessions"))
+                        .changed();
+                });
+                ui.horizontal(|ui| {
+                    ui.label("When time runs out:");
+                    egui::ComboBox::from_id_source("focus_end_behavior")
+                        .selected_text(end_behavior_label(self.config.focus_end_behavior))
+                        .show_ui(ui, |ui| {
+                            for behavior in [
+                                FocusEndBehavior::NextPhase,
+                                FocusEndBehavior::Stop,
+                                FocusEndBehavior::Overtime,
+                                FocusEndBehavior::Prompt,
+                            ] {
+                                changed |= ui
+                                    .selectable_value(&mut self.config.focus_end_behavior, behavior, end_behavior_label(behavior))
+                                    .changed();
+                            }
+                        });
+                });
+                if self.config.focus_end_behavior == FocusEndBehavior::Prompt {
+                    ui.horizontal(|ui| {
+                        ui.label("Extend by:");
+                        changed |= ui
+                            .add(egui::DragValue::new(&mut self.config.extend_minutes).clamp_range(1..=60).suffix(" min"))
+                            .changed();
+                    });
+                }
+                if changed {
+                    focus.apply_config(&self.config);
+                }
 
-                ui.checkbox(&mut self.config.auto_start_focus, "Auto-start focus sessions");
+                let mut auto_start_changed = ui
+                    .checkbox(&mut self.config.auto_start_focus, "Auto-start focus sessions")
+                    .changed();
+                if self.config.auto_start_focus {
+                    ui.horizontal(|ui| {
+                        ui.label("After productive work for:");
+                        auto_start_changed |= ui
+                            .add(egui::DragValue::new(&mut self.config.auto_start_warmup_minutes).clamp_range(1..=60).suffix(" min"))
+                            .changed();
+                    });
+                }
+                if auto_start_changed {
+                    self.auto_starter.apply_config(&self.config);
+                }
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.label("Working on:");
+                    ui.text_edit_singleline(&mut self.intent_text);
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Tags (comma separated):");
+                    ui.text_edit_singleline(&mut self.tags_text);
+                });
 
                 if ui.button("Start Focus Session").clicked() {
+                    let intent = self.intent_text.trim();
+                    let tags = self
+                        .tags_text
+                        .split(',')
+                        .map(|tag| tag.trim().trim_start_matches('#').to_string())
+                        .filter(|tag| !tag.is_empty())
+                        .collect();
                     let _ = focus.start_session(
                         self.config.default_focus_duration,
-                        self.config.music_dir.is_some(),
+                        self.config.music_enabled(),
+                        (!intent.is_empty()).then(|| intent.to_string()),
+                        tags,
                     );
+                    self.intent_text.clear();
+                    self.focus_notice = None;
+                }
+            }
+        }
+
+    }
+
+    fn render_year_heatmap(&mut self, ui: &mut egui::Ui) {
+        let year = self.selected_day.year();
+        ui.horizontal(|ui| {
+            if ui.button("←").clicked() {
+                if let Some(day) = self.selected_day.checked_sub_months(Months::new(12)) {
+                    self.selected_day = day;
+                }
+            }
+            ui.label(year.to_string());
+            if ui.button("→").clicked() {
+                if let Some(day) = self.selected_day.checked_add_months(Months::new(12)) {
+                    self.selected_day = day;
+                }
+            }
+            ui.separator();
+            ui.radio_value(&mut self.heatmap_metric, HeatmapMetric::FocusTime, "Focus time");
+            ui.radio_value(&mut self.heatmap_metric, HeatmapMetric::Productivity, "Productivity score");
+        });
+
+        let (first, last) = match (NaiveDate::from_ymd_opt(year, 1, 1), NaiveDate::from_ymd_opt(year, 12, 31)) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return,
+        };
+        match self.calendar.lock() {
+            Ok(calendar) => {
+                for day in first.iter_days().take_while(|day| *day <= last) {
+                    let revision = calendar.day_revision(day);
+                    if self.day_summaries.get(&day).map(|(cached, _)| *cached != revision).unwrap_or(true) {
+                        self.day_summaries.insert(day, (revision, calendar.day_summary(day, &self.categories)));
+                    }
+                }
+            }
+            Err(_) => return,
+        }
+        let days: Vec<&DaySummary> = first
+            .iter_days()
+            .take_while(|day| *day <= last)
+            .filter_map(|day| self.day_summaries.get(&day).map(|(_, summary)| summary))
+            .collect();
+
+        // Focus time is relative to the best day of the year, but at least four hours
+        let max_focus = days
+            .iter()
+            .map(|day| day.focus)
+            .max()
+            .unwrap_or_else(Duration::zero)
+            .max(Duration::hours(4))
+            .num_seconds() as f32;
+        let cells: Vec<_> = days
+            .iter()
+            .map(|day| {
+                let score = day.productivity_score(&self.categories);
+                let intensity = match self.heatmap_metric {
+                    HeatmapMetric::FocusTime => Some(day.focus.num_seconds() as f32 / max_focus),
+                    HeatmapMetric::Productivity => score.map(|score| score as f32 / 100.0),
+                };
+                let mut tooltip = format!(
+                    "{}\nTracked: {}\nFocus: {} ({} sessions)",
+                    day.day.format("%a, %-d %b %Y"),
+                    format_short_duration(day.tracked),
+                    format_short_duration(day.focus),
+                    day.focus_sessions
+                );
+                if let Some(score) = score {
+                    tooltip.push_str(&format!("\nProductivity: {}%", score));
+                }
+                HeatmapCell {
+                    day: day.day,
+                    intensity,
+                    tooltip,
+                }
+            })
+            .collect();
+
+        egui::ScrollArea::horizontal().show(ui, |ui| {
+            if let Some(day) = heatmap(ui, &cells, self.selected_day) {
+                self.selected_day = day;
+            }
+        });
+
+        // Streaks of consecutive days with a focus session: the longest in
+        // this year, and the current one however far back it goes
+        let today = self.day_model.today();
+        let mut longest = 0;
+        let mut run = 0;
+        for day in days.iter().filter(|day| day.day <= today) {
+            if day.focus_sessions > 0 {
+                run += 1;
+                longest = longest.max(run);
+            } else {
+                run = 0;
+            }
+        }
+        let current = match self.calendar.lock() {
+            Ok(calendar) => calendar.current_focus_streak(today),
+            Err(_) => 0,
+        };
+        ui.label(format!("Longest focus streak: {} days   Current: {} days", longest, current));
+    }
+
+    fn render_week(&mut self, ui: &mut egui::Ui) {
+        ui.heading("Week View");
+
+        let week_start = self.selected_day - Duration::days(self.selected_day.weekday().num_days_from_monday() as i64);
+        ui.horizontal(|ui| {
+            if ui.button("←").clicked() {
+                self.selected_day -= Duration::days(7);
+            }
+            ui.label(format!("Week of {}", week_start.format("%B %-d, %Y")));
+            if ui.button("→").clicked() {
+                self.selected_day += Duration::days(7);
+            }
+            if ui.button("This Week").clicked() {
+                self.selected_day = self.day_model.today();
+            }
+        });
+
+        match self.calendar.lock() {
+            Ok(calendar) => {
+                let revision = calendar.revision();
+                let cached = matches!(&self.week_summaries, Some((week, cached, _, _)) if *week == week_start && *cached == revision);
+                if !cached {
+                    self.week_summaries = Some((
+                        week_start,
+                        revision,
+                        calendar.day_summaries(week_start, week_start + Duration::days(6), &self.categories),
+                        calendar.day_summaries(week_start - Duration::days(7), week_start - Duration::days(1), &self.categories),
+                    ));
                 }
             }
+            Err(_) => return,
+        }
+        let (days, previous) = match &self.week_summaries {
+            Some((_, _, days, previous)) => (days.clone(), previous.clone()),
+            None => return,
+        };
+
+        ui.separator();
+        let sum = |days: &[DaySummary], value: fn(&DaySummary) -> Duration| {
+            days.iter().map(value).fold(Duration::zero(), |total, duration| total + duration)
+        };
+        let (tracked, previous_tracked) = (sum(&days, |day| day.tracked), sum(&previous, |day| day.tracked));
+        let (focus, previous_focus) = (sum(&days, |day| day.focus), sum(&previous, |day| day.focus));
+        let sessions: usize = days.iter().map(|day| day.focus_sessions).sum();
+        let previous_sessions: usize = previous.iter().map(|day| day.focus_sessions).sum();
+        egui::Grid::new("week_totals").show(ui, |ui| {
+            ui.label("");
+            ui.strong("This week");
+            ui.strong("Last week");
+            ui.strong("Change");
+            ui.end_row();
+            for (label, current, last) in [("Tracked", tracked, previous_tracked), ("Focus", focus, previous_focus)] {
+                ui.label(label);
+                ui.label(format_short_duration(current));
+                ui.label(format_short_duration(last));
+                ui.label(format_change(current, last));
+                ui.end_row();
+            }
+            ui.label("Focus sessions");
+            ui.label(sessions.to_string());
+            ui.label(previous_sessions.to_string());
+            ui.label(format!("{:+}", sessions as i64 - previous_sessions as i64));
+            ui.end_row();
+        });
+
+        ui.separator();
+        let today = self.day_model.today();
+        let bars: Vec<_> = days
+            .iter()
+            .map(|day| StackedBar {
+                label: day.day.format("%a %-d").to_string(),
+                segments: day.categories.clone(),
+                caption: format!(
+                    "{}\n◎ {}",
+                    format_short_duration(day.tracked),
+                    format_short_duration(day.focus)
+                ),
+                highlighted: day.day == today,
+            })
+            .collect();
+        // Clicking a day opens it in the calendar
+        if let Some(index) = stacked_bars(ui, &bars, 200.0) {
+            self.selected_day = days[index].day;
+            self.current_tab = Tab::Calendar;
+        }
+
+        // Categories of both weeks, busiest this week first
+        let mut category_totals: Vec<(String, Duration, Duration)> = Vec::new();
+        for (summaries, this_week) in [(&days, true), (&previous, false)] {
+            for (category, duration) in summaries.iter().flat_map(|day| day.categories.iter()) {
+                let index = match category_totals.iter().position(|(name, _, _)| name == category) {
+                    Some(index) => index,
+                    None => {
+                        category_totals.push((category.clone(), Duration::zero(), Duration::zero()));
+                        category_totals.len() - 1
+                    }
+                };
+                if this_week {
+                    category_totals[index].1 += *duration;
+                } else {
+                    category_totals[index].2 += *duration;
+                }
+            }
+        }
+        category_totals.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| b.2.cmp(&a.2)));
+        legend(
+            ui,
+            category_totals
+                .iter()
+                .filter(|(_, current, _)| *current > Duration::zero())
+                .map(|(name, _, _)| name.as_str()),
+        );
+
+        ui.separator();
+        ui.collapsing("By Category", |ui| {
+            egui::Grid::new("week_categories").striped(true).show(ui, |ui| {
+                ui.strong("Category");
+                ui.strong("This week");
+                ui.strong("Last week");
+                ui.strong("Change");
+                ui.end_row();
+                for (category, current, last) in &category_totals {
+                    ui.label(category);
+                    ui.label(format_short_duration(*current));
+                    ui.label(format_short_duration(*last));
+                    ui.label(format_change(*current, *last));
+                    ui.end_row();
+                }
+            });
+        });
+    }
+
+    fn render_reports(&mut self, ui: &mut egui::Ui) {
+        ui.heading("Reports");
+
+        ui.horizontal(|ui| {
+            ui.label("Filter sessions (#tag text):");
+            ui.text_edit_singleline(&mut self.session_query);
+        });
+        let filter = SessionFilter::parse(&self.session_query);
+
+        let end = self.day_model.today();
+        let start = end - chrono::Duration::days(6);
+        let report = match self.calendar.lock() {
+            Ok(calendar) => Report::generate(&calendar, start, end, &filter),
+            Err(_) => return,
+        };
+
+        ui.label(format!("{} – {}", report.start, report.end));
+        ui.label(format!("Total Tracked: {}", format_duration(report.total_time())));
+        ui.label(format!(
+            "Focus: {} in {} sessions{}",
+            format_duration(report.focus_time),
+            report.focus_sessions,
+            if filter.is_empty() { "" } else { " (filtered)" }
+        ));
+        if let Some(score) = report.average_quality {
+            ui.label(format!("Focus Quality: {}/100, {} interruptions", score, report.interruptions));
+        }
+
+        ui.collapsing("Focus by Tag", |ui| {
+            for (tag, duration) in &report.tag_totals {
+                ui.horizontal(|ui| {
+                    ui.label(format!("#{}", tag));
+                    ui.label(format_duration(*duration));
+                });
+            }
+        });
+
+        ui.collapsing("Projects", |ui| {
+            for (project, duration) in &report.project_totals {
+                ui.horizontal(|ui| {
+                    ui.label(project);
+                    ui.label(format_duration(*duration));
+                });
+            }
+        });
+
+        ui.collapsing("Websites", |ui| {
+            for (domain, duration) in &report.domain_totals {
+                ui.horizontal(|ui| {
+                    ui.label(domain);
+                    ui.label(format_duration(*duration));
+                });
+            }
+        });
+
+        ui.collapsing("Applications", |ui| {
+            for (app, duration) in &report.app_totals {
+                ui.horizontal(|ui| {
+                    ui.label(app);
+                    ui.label(format_duration(*duration));
+                });
+            }
+        });
+
+        ui.collapsing("Journal", |ui| {
+            for (day, journal) in &report.journal {
+                ui.strong(day.format("%A, %-d %B").to_string());
+                if !journal.note.is_empty() {
+                    ui.label(&journal.note);
+                }
+                for highlight in &journal.went_well {
+                    ui.label(format!("+ {}", highlight));
+                }
+                for blocker in &journal.blockers {
+                    ui.label(format!("− {}", blocker));
+                }
+            }
+        });
+
+        ui.horizontal(|ui| {
+            if ui.button("Copy as Text").clicked() {
+                ui.output_mut(|o| o.copied_text = report.to_text());
+            }
+            if ui.button("Export to Calendar (.ics)").clicked() {
+                self.export_ics();
+            }
+        });
+        if let Some(status) = &self.export_status {
+            ui.label(status);
+        }
+    }
+
+    fn render_reflection(&mut self, ctx: &egui::Context) {
+        let reflection = match &mut self.reflection {
+            Some(reflection) => reflection,
+            None => return,
+        };
+
+        let mut close = false;
+        let mut save = false;
+        egui::Window::new("How did it go?")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::RIGHT_BOTTOM, [-8.0, -8.0])
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Rating:");
+                    for rating in 1..=5 {
+                        let star = if rating <= reflection.rating { "★" } else { "☆" };
+                        if ui.selectable_label(rating == reflection.rating, star).clicked() {
+                            reflection.rating = rating;
+                        }
+                    }
+                });
+                ui.label("Note:");
+                ui.text_edit_multiline(&mut reflection.note);
+                ui.horizontal(|ui| {
+                    save = ui.button("Save").clicked();
+                    close = ui.button("Skip").clicked();
+                });
+            });
+
+        if save {
+            let rating = (reflection.rating > 0).then_some(reflection.rating);
+            let note = reflection.note.trim();
+            let note = (!note.is_empty()).then(|| note.to_string());
+            if let Ok(mut calendar) = self.calendar.lock() {
+                if !calendar.set_reflection(reflection.start_time, rating, note) {
+                    error!("Focus session for the reflection was not found");
+                }
+            }
+        }
+        if save || close {
+            self.reflection = None;
+        }
+    }
+
+    fn render_nag(&mut self, ctx: &egui::Context) {
+        let app_name = match self.blocker.nag() {
+            Some(app_name) => app_name.to_string(),
+            None => return,
+        };
+
+        egui::Window::new("Stay Focused")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.label(format!("{} is blocked while you focus.", app_name));
+                if ui.button("Back to Work").clicked() {
+                    self.blocker.dismiss_nag();
+                }
+
+                ui.separator();
+                ui.label("Emergency override (reason is logged):");
+                ui.text_edit_singleline(&mut self.override_reason);
+                let label = format!("Unblock for {} min", self.config.emergency_override_minutes);
+                let has_reason = !self.override_reason.trim().is_empty();
+                if ui.add_enabled(has_reason, egui::Button::new(label)).clicked() {
+                    self.emergency_override();
+                }
+            });
+    }
+
+    // Lifts app and website blocking for the configured time
+    fn emergency_override(&mut self) {
+        let reason = std::mem::take(&mut self.override_reason);
+        self.blocker.emergency_override(reason.trim(), Utc::now());
+        if let (Some(until), Ok(mut focus)) = (self.blocker.override_until(), self.focus_mode.lock()) {
+            focus.override_website_block(until);
         }
     }
 
@@ -187,28 +1391,509 @@ impl RizeCloneApp {
 
         ui.checkbox(&mut self.config.track_window_titles, "Track Window Titles");
 
+        ui.horizontal(|ui| {
+            ui.label("Timezone (empty for the system's):");
+            ui.text_edit_singleline(&mut self.timezone_text);
+        });
+        ui.horizontal(|ui| {
+            ui.label("Days start at:");
+            ui.add(egui::DragValue::new(&mut self.config.day_start_hour).clamp_range(0..=23).suffix(":00"))
+                .on_hover_text("Recorded days are regrouped when saved; days compacted by retention keep their dates");
+        });
+
+        // Switching modes re-encrypts the loaded history, which needs it loaded
+        let stored_encryption = self.storage.as_ref().map(|store| store.encryption());
+        ui.add_enabled_ui(stored_encryption.is_some(), |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Encrypt activity data:");
+                egui::ComboBox::from_id_source("encryption")
+                    .selected_text(format!("{:?}", self.config.encryption))
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut self.config.encryption, Encryption::Off, "Off");
+                        ui.selectable_value(&mut self.config.encryption, Encryption::Passphrase, "Passphrase");
+                        ui.selectable_value(&mut self.config.encryption, Encryption::Keyring, "Keyring");
+                    });
+            });
+        });
+        if stored_encryption.is_some_and(|stored| stored != self.config.encryption) {
+            if self.config.encryption == Encryption::Passphrase {
+                ui.horizontal(|ui| {
+                    ui.label("New passphrase:");
+                    ui.add(egui::TextEdit::singleline(&mut self.new_passphrase).password(true));
+                });
+            }
+            ui.label("Saving re-encrypts the stored history for the new setting.");
+        }
+        if let Some(status) = &self.encryption_status {
+            ui.label(status);
+        }
+
+        ui.collapsing("Music", |ui| {
+            ui.checkbox(&mut self.config.mpris_enabled, "Control a media player (MPRIS) during focus sessions");
+            ui.horizontal(|ui| {
+                ui.label("Player, e.g. spotify:");
+                ui.text_edit_singleline(&mut self.mpris_player_text);
+            });
+            ui.label("Media player changes take effect after a restart.");
+        });
+
+        ui.collapsing("Focus Blocks", |ui| {
+            ui.label("Scheduled focus sessions, started when auto-start is on. No days selected means every day.");
+            let mut removed = None;
+            for (index, block) in self.config.focus_blocks.iter_mut().enumerate() {
+                ui.horizontal(|ui| {
+                    for day in WEEKDAYS {
+                        let selected = block.days.contains(&day);
+                        if ui.selectable_label(selected, &day.to_string()[..2]).clicked() {
+                            if selected {
+                                block.days.retain(|block_day| *block_day != day);
+                            } else {
+                                block.days.push(day);
+                                block.days.sort_by_key(|day| day.num_days_from_monday());
+                            }
+                        }
+                    }
+
+                    let mut hour = block.start.hour();
+                    let mut minute = block.start.minute();
+                    ui.label("at");
+                    ui.add(egui::DragValue::new(&mut hour).clamp_range(0..=23));
+                    ui.label(":");
+                    ui.add(egui::DragValue::new(&mut minute).clamp_range(0..=59).custom_formatter(|n, _| format!("{:02}", n)));
+                    block.start = NaiveTime::from_hms_opt(hour, minute, 0).unwrap_or(block.start);
+                    ui.label("for");
+                    ui.add(egui::DragValue::new(&mut block.minutes).clamp_range(5..=240).suffix(" min"));
+
+                    if ui.small_button("Remove").clicked() {
+                        removed = Some(index);
+                    }
+                });
+            }
+            if let Some(index) = removed {
+                self.config.focus_blocks.remove(index);
+            }
+            if ui.button("Add Focus Block").clicked() {
+                self.config.focus_blocks.push(FocusBlock {
+                    days: Vec::new(),
+                    start: NaiveTime::from_hms_opt(9, 0, 0).unwrap_or_default(),
+                    minutes: self.config.default_focus_duration,
+                });
+            }
+        });
+
+        ui.collapsing("Distraction Blocking", |ui| {
+            ui.checkbox(&mut self.config.block_distractions, "Block apps during focus phases");
+            ui.horizontal(|ui| {
+                ui.label("Grace period:");
+                ui.add(egui::DragValue::new(&mut self.config.block_grace_seconds).clamp_range(0..=600).suffix(" s"));
+            });
+            ui.horizontal(|ui| {
+                ui.label("Emergency override:");
+                ui.add(egui::DragValue::new(&mut self.config.emergency_override_minutes).clamp_range(1..=120).suffix(" min"));
+            });
+
+            let mut removed = None;
+            for (index, rule) in self.config.blocked_apps.iter_mut().enumerate() {
+                ui.horizontal(|ui| {
+                    ui.text_edit_singleline(&mut rule.app);
+                    egui::ComboBox::from_id_source(("block_action", index))
+                        .selected_text(block_action_label(rule.action))
+                        .show_ui(ui, |ui| {
+                            for action in [BlockAction::Nag, BlockAction::Minimize, BlockAction::Suspend, BlockAction::Kill] {
+                                ui.selectable_value(&mut rule.action, action, block_action_label(action));
+                            }
+                        });
+                    if ui.small_button("Remove").clicked() {
+                        removed = Some(index);
+                    }
+                });
+            }
+            if let Some(index) = removed {
+                self.config.blocked_apps.remove(index);
+            }
+            if ui.button("Add App").clicked() {
+                self.config.blocked_apps.push(BlockRule {
+                    app: String::new(),
+                    action: BlockAction::Nag,
+                });
+            }
+
+            ui.separator();
+            ui.checkbox(&mut self.config.do_not_disturb, "Silence notifications during focus phases");
+            ui.checkbox(&mut self.config.block_websites, "Block websites during focus phases");
+            ui.label("Blocked websites (one domain per line):");
+            ui.text_edit_multiline(&mut self.blocked_websites_text);
+            ui.label(format!(
+                "Websites are blocked through {:?}. Changes take effect after a restart.",
+                self.config.hosts_file
+            ));
+        });
+
+        ui.collapsing("Data Retention", |ui| {
+            retention_days(ui, "Keep detailed intervals for", &mut self.config.keep_intervals_days);
+            retention_days(ui, "Keep window titles for", &mut self.config.keep_window_titles_days);
+
+            ui.separator();
+            ui.label("Forget a date range (YYYY-MM-DD):");
+            ui.horizontal(|ui| {
+                ui.text_edit_singleline(&mut self.forget_start);
+                ui.label("to");
+                ui.text_edit_singleline(&mut self.forget_end);
+            });
+            if ui.button("Forget This Range").clicked() {
+                self.forget_range();
+            }
+            if let Some(status) = &self.forget_status {
+                ui.label(status);
+            }
+        });
+
+        ui.collapsing("Calendar Export", |ui| {
+            ui.horizontal(|ui| {
+                ui.label("ICS file:");
+                ui.text_edit_singleline(&mut self.ics_path_text);
+            });
+            ui.horizontal(|ui| {
+                ui.label("Export the last");
+                ui.add(egui::DragValue::new(&mut self.config.ics_export_days).clamp_range(1..=3650));
+                ui.label("days");
+            });
+            ui.checkbox(&mut self.config.ics_project_blocks, "Include activity blocks per project");
+        });
+
+        ui.collapsing("Calendar Import", |ui| {
+            ui.label("Meetings and planned blocks are shown next to tracked activity in the Calendar tab.");
+            ui.horizontal(|ui| {
+                ui.label("ICS file:");
+                ui.text_edit_singleline(&mut self.ics_import_text);
+            });
+            ui.checkbox(&mut self.config.ics_import_watch, "Re-import when the file changes");
+            if ui.button("Import Now").clicked() {
+                let path = self.ics_import_text.trim();
+                self.config.ics_import_path = (!path.is_empty()).then(|| path.into());
+                self.import_ics(true);
+            }
+            if let Some(status) = &self.import_status {
+                ui.label(status);
+            }
+        });
+
+        ui.collapsing("CalDAV Sync", |ui| {
+            ui.label("Pushes finished focus sessions and pulls planned events. Changes apply after a restart.");
+            ui.checkbox(&mut self.config.caldav_enabled, "Sync with a CalDAV server");
+            optional_text(ui, "Server URL:", &mut self.config.caldav_url);
+            ui.horizontal(|ui| {
+                ui.label("Calendar path:");
+                ui.text_edit_singleline(&mut self.config.caldav_calendar_path);
+            });
+            optional_text(ui, "Planned events path (if different):", &mut self.config.caldav_plan_path);
+            optional_text(ui, "Username:", &mut self.config.caldav_username);
+            ui.horizontal(|ui| {
+                ui.label("Password:");
+                ui.add(egui::TextEdit::singleline(&mut self.caldav_password_text).password(true));
+            });
+            ui.small("Stored in the system keyring on save; leave empty to keep the current one. caldav_password_command in the config file is used instead when set.");
+            ui.horizontal(|ui| {
+                ui.label("Sync every");
+                ui.add(egui::DragValue::new(&mut self.config.caldav_sync_minutes).clamp_range(1..=1440));
+                ui.label("minutes");
+            });
+            if let Some(sync) = &self.caldav_sync {
+                if ui.button("Sync Now").clicked() {
+                    sync.sync_now();
+                }
+                if let Some(status) = sync.status() {
+                    ui.label(status);
+                }
+            }
+        });
+
+        ui.collapsing("Privacy", |ui| {
+            ui.label("Excluded applications (one per line):");
+            ui.text_edit_multiline(&mut self.excluded_apps_text);
+            ui.label("Redact window titles matching (one regex per line):");
+            ui.text_edit_multiline(&mut self.redaction_patterns_text);
+        });
+
         if ui.button("Save Settings").clicked() {
+            self.config.excluded_apps = non_empty_lines(&self.excluded_apps_text);
+            self.config.title_redaction_patterns = non_empty_lines(&self.redaction_patterns_text);
+            self.config.blocked_websites = non_empty_lines(&self.blocked_websites_text);
+            let timezone = self.timezone_text.trim();
+            self.config.timezone = (!timezone.is_empty()).then(|| timezone.to_string());
+            self.day_model = DayModel::from_config(&self.config);
+            self.categories = CategoryResolver::from_config(&self.config);
+            self.day_summaries.clear();
+            self.week_summaries = None;
+            let mpris_player = self.mpris_player_text.trim();
+            self.config.mpris_player = (!mpris_player.is_empty()).then(|| mpris_player.to_string());
+            let ics_path = self.ics_path_text.trim();
+            self.config.ics_export_path = (!ics_path.is_empty()).then(|| ics_path.into());
+            let ics_import = self.ics_import_text.trim();
+            self.config.ics_import_path = (!ics_import.is_empty()).then(|| ics_import.into());
+            self.change_encryption();
+            if !self.caldav_password_text.is_empty() {
+                caldav::store_password(&mut self.config, std::mem::take(&mut self.caldav_password_text));
+            }
             let _ = self.config.save();
+            if let Ok(mut tracker) = self.process_tracker.lock() {
+                tracker.apply_config(&self.config);
+            }
+            self.blocker.apply_config(&self.config);
+            self.auto_starter.apply_config(&self.config);
+            if let Ok(mut focus) = self.focus_mode.lock() {
+                focus.set_do_not_disturb(self.config.do_not_disturb, &self.config.data_dir);
+            }
+            if let Ok(mut calendar) = self.calendar.lock() {
+                calendar.apply_privacy(&PrivacyFilter::from_config(&self.config));
+                calendar.set_day_model(self.day_model);
+            }
         }
     }
 }
 
+fn session_row(ui: &mut egui::Ui, session: &FocusSessionSummary, day_model: &DayModel) {
+    ui.horizontal(|ui| {
+        ui.label(day_model.local_time(session.start_time).format("%H:%M").to_string());
+        ui.label(format_duration(session.duration));
+        if let Some(intent) = &session.intent {
+            ui.label(intent);
+        }
+        for tag in &session.tags {
+            ui.label(format!("#{}", tag));
+        }
+        if let Some(score) = session.quality_score {
+            ui.label(format!("quality {}/100", score));
+        }
+        if session.interruptions > 0 {
+            ui.label(format!("{} interruptions", session.interruptions));
+        }
+        if let Some(rating) = session.rating {
+            ui.label("★".repeat(rating as usize));
+        }
+    })
+    .response
+    .on_hover_text(match &session.note {
+        Some(note) => format!("{}\n\nApps: {}", note, session.apps_used.join(", ")),
+        None => format!("Apps: {}", session.apps_used.join(", ")),
+    });
+}
+
+fn plan_vs_actual(
+    ui: &mut egui::Ui,
+    calendar: &Calendar,
+    planned: &[&PlannedEvent],
+    day_model: &DayModel,
+    categories: &CategoryResolver,
+) {
+    if planned.is_empty() {
+        return;
+    }
+
+    egui::CollapsingHeader::new("Plan vs Actual")
+        .default_open(true)
+        .show(ui, |ui| {
+            let now = Utc::now();
+            let mut planned_total = Duration::zero();
+            let mut on_plan_total = Duration::zero();
+            egui::Grid::new("plan_vs_actual").striped(true).show(ui, |ui| {
+                for event in planned {
+                    let adherence = calendar.adherence(event, categories);
+                    ui.label(format!(
+                        "{} – {}",
+                        day_model.local_time(event.start).format("%H:%M"),
+                        day_model.local_time(event.end).format("%H:%M")
+                    ));
+                    ui.label(&event.summary);
+                    ui.label(categories.planned_category(&event.summary, event.meeting).unwrap_or("Productive work"));
+                    if event.start > now {
+                        ui.label("upcoming");
+                    } else {
+                        ui.label(format!(
+                            "{} of {} ({}%), {} tracked",
+                            format_short_duration(adherence.on_plan),
+                            format_short_duration(adherence.planned),
+                            adherence.percent(),
+                            format_short_duration(adherence.tracked)
+                        ));
+                        planned_total += adherence.planned;
+                        on_plan_total += adherence.on_plan;
+                    }
+                    ui.end_row();
+                }
+            });
+
+            if planned_total > Duration::zero() {
+                ui.label(format!(
+                    "Spent as planned: {} of {} ({:.0}%)",
+                    format_short_duration(on_plan_total),
+                    format_short_duration(planned_total),
+                    on_plan_total.num_seconds() as f64 / planned_total.num_seconds() as f64 * 100.0
+                ));
+            }
+        });
+}
+
+fn optional_text(ui: &mut egui::Ui, label: &str, value: &mut Option<String>) {
+    ui.horizontal(|ui| {
+        ui.label(label);
+        let mut text = value.clone().unwrap_or_default();
+        if ui.text_edit_singleline(&mut text).changed() {
+            *value = (!text.trim().is_empty()).then_some(text);
+        }
+    });
+}
+
+fn add_duration(totals: &mut Vec<(String, Duration)>, name: &str, duration: Duration) {
+    match totals.iter_mut().find(|(existing, _)| existing == name) {
+        Some((_, total)) => *total += duration,
+        None => totals.push((name.to_string(), duration)),
+    }
+}
+
+// "+1h 05m" or "-20m"
+fn format_change(current: Duration, previous: Duration) -> String {
+    let change = current - previous;
+    let sign = if change < Duration::zero() { "-" } else { "+" };
+    format!("{}{}", sign, format_short_duration(change.abs()))
+}
+
+fn block_action_label(action: BlockAction) -> &'static str {
+    match action {
+        BlockAction::Nag => "Remind me",
+        BlockAction::Minimize => "Minimize",
+        BlockAction::Suspend => "Suspend process",
+        BlockAction::Kill => "Close process",
+    }
+}
+
+fn end_behavior_label(behavior: FocusEndBehavior) -> &'static str {
+    match behavior {
+        FocusEndBehavior::NextPhase => "Continue with a break",
+        FocusEndBehavior::Stop => "Stop",
+        FocusEndBehavior::Overtime => "Keep going (overtime)",
+        FocusEndBehavior::Prompt => "Ask to extend",
+    }
+}
+
+// Edits an optional day count, where `None` keeps data forever
+fn retention_days(ui: &mut egui::Ui, label: &str, days: &mut Option<u32>) {
+    ui.horizontal(|ui| {
+        ui.label(label);
+        let mut forever = days.is_none();
+        if ui.checkbox(&mut forever, "forever").changed() {
+            *days = if forever { None } else { Some(30) };
+        }
+        if let Some(days) = days {
+            ui.add(egui::DragValue::new(days).clamp_range(1..=3650).suffix(" days"));
+        }
+    });
+}
+
+fn non_empty_lines(text: &str) -> Vec<String> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
 impl eframe::App for RizeCloneApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         // Update process tracker at regular intervals
         if self.last_update.elapsed() >= self.update_interval {
+            let was_nagging = self.blocker.nag().is_some();
             if let Ok(mut tracker) = self.process_tracker.lock() {
                 let _ = tracker.update();
+                let now = Utc::now();
+                let mut focusing = false;
+                if let Ok(mut focus) = self.focus_mode.lock() {
+                    let interval = tracker.current_interval();
+                    if let Some(interval) = interval {
+                        focus.observe_activity(&interval.app_name, interval.domain.as_deref(), now);
+                    }
+
+                    let auto_start = self.auto_starter.check(
+                        interval.map(|interval| interval.app_name.as_str()),
+                        interval.and_then(|interval| interval.domain.as_deref()),
+                        focus.get_current_session().is_some(),
+                        now,
+                    );
+                    if let Some(auto_start) = auto_start {
+                        // Notify before the session silences notifications
+                        send_notification("Focus session started", &auto_start.reason);
+                        let _ = focus.start_session(auto_start.minutes, self.config.music_enabled(), None, Vec::new());
+                        self.auto_start_notice = Some(auto_start.reason);
+                    }
+
+                    focusing = focus
+                        .get_current_session()
+                        .map(|session| session.phase == Phase::Work && !session.is_paused())
+                        .unwrap_or(false);
+                }
+                self.blocker.check(&tracker, focusing, now);
             }
+            // Bring the reminder in front of the blocked app
+            if !was_nagging && self.blocker.nag().is_some() {
+                ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
+            }
+            self.sync_calendar();
+
+            // Sessions are recorded by the focus timer; only show what happened
+            while let Ok(event) = self.focus_events.try_recv() {
+                if let FocusEvent::PhaseCompleted { next: None, .. } = event {
+                    self.auto_start_notice = None;
+                }
+                if let Some(summary) = event.focus_summary() {
+                    self.reflection = Some(Reflection {
+                        start_time: summary.start_time,
+                        rating: 0,
+                        note: String::new(),
+                    });
+                }
+                self.focus_notice = Some(event.describe());
+            }
+
             self.last_update = Instant::now();
             ctx.request_repaint(); // Request repaint to update the UI
         }
 
+        if self.last_save.elapsed() >= SAVE_INTERVAL {
+            self.save_calendar();
+        }
+
+        if self.config.ics_import_watch && self.last_import_check.elapsed() >= IMPORT_CHECK_INTERVAL {
+            self.last_import_check = Instant::now();
+            self.import_ics(false);
+        }
+
+        if self.is_locked() {
+            egui::CentralPanel::default().show(ctx, |ui| self.render_unlock(ui));
+            return;
+        }
+
         egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
+            if let Some(reason) = self.auto_start_notice.clone() {
+                ui.horizontal(|ui| {
+                    ui.label(format!("Focus session started automatically: {}", reason));
+                    if ui.button("Cancel").clicked() {
+                        if let Ok(mut focus) = self.focus_mode.lock() {
+                            focus.cancel_session();
+                        }
+                        self.auto_start_notice = None;
+                    }
+                    if ui.small_button("Dismiss").clicked() {
+                        self.auto_start_notice = None;
+                    }
+                });
+                ui.separator();
+            }
             ui.horizontal(|ui| {
                 ui.selectable_value(&mut self.current_tab, Tab::Dashboard, "Dashboard");
                 ui.selectable_value(&mut self.current_tab, Tab::Calendar, "Calendar");
+                ui.selectable_value(&mut self.current_tab, Tab::Week, "Week");
                 ui.selectable_value(&mut self.current_tab, Tab::Focus, "Focus");
+                ui.selectable_value(&mut self.current_tab, Tab::Reports, "Reports");
                 ui.selectable_value(&mut self.current_tab, Tab::Settings, "Settings");
             });
         });
@@ -217,9 +1902,26 @@ impl eframe::App for RizeCloneApp {
             match self.current_tab {
                 Tab::Dashboard => self.render_dashboard(ui),
                 Tab::Calendar => self.render_calendar(ui),
+                Tab::Week => self.render_week(ui),
                 Tab::Focus => self.render_focus(ui),
+                Tab::Reports => self.render_reports(ui),
                 Tab::Settings => self.render_settings(ui),
             }
         });
+
+        self.render_reflection(ctx);
+        self.render_nag(ctx);
+    }
+
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        if let Ok(mut focus) = self.focus_mode.lock() {
+            focus.shutdown();
+        }
+        if let Ok(mut tracker) = self.process_tracker.lock() {
+            tracker.finish_current_interval();
+        }
+        self.sync_calendar();
+        self.save_journal();
+        self.save_calendar();
     }
 } 
\ No newline at end of file
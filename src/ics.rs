@@ -0,0 +1,946 @@
+use crate::calendar::{Calendar, DayModel, FocusSessionSummary, Journal, PlannedEvent};
+use crate::process_tracker::ActivityInterval;
+use crate::utils::format_short_duration;
+use chrono::{DateTime, Datelike, Days, Duration, Months, NaiveDate, NaiveDateTime, TimeZone, Utc, Weekday};
+use chrono_tz::Tz;
+use log::warn;
+use std::collections::HashSet;
+
+const PRODUCT_ID: &str = "-//rizeclone//rizeclone//EN";
+const UID_DOMAIN: &str = "rizeclone";
+const MAX_LINE_OCTETS: usize = 75;
+
+/// Intervals of the same project closer together than this are merged
+/// into one block.
+const BLOCK_MERGE_GAP: i64 = 5 * 60;
+/// Shorter project blocks are left out of the export.
+const MIN_BLOCK: i64 = 5 * 60;
+/// Upper bound on the periods (days, weeks, months or years) expanded for
+/// one recurring event.
+const MAX_OCCURRENCES: usize = 5000;
+
+/// Planned events are imported for this many days before and after today.
+pub const PLAN_PAST_DAYS: i64 = 30;
+pub const PLAN_FUTURE_DAYS: i64 = 90;
+
+/// Writes focus sessions, journal entries as all-day events, and optionally
+/// project blocks, between `start` and `end` (inclusive) as an iCalendar
+/// file.
+///
+/// Every event's UID is derived from its start time (and project), so
+/// importing a newer export into a calendar app updates the events from an
+/// earlier one instead of duplicating them.
+pub fn export(calendar: &Calendar, start: NaiveDate, end: NaiveDate, project_blocks: bool) -> String {
+    let stamp = format_time(Utc::now());
+    let mut lines = calendar_header();
+
+    for day in start.iter_days().take_while(|day| *day <= end) {
+        let activity = match calendar.get_activity_for_day(day) {
+            Some(activity) => activity,
+            None => continue,
+        };
+        if !activity.journal.is_empty() {
+            lines.extend(journal_event(day, &activity.journal, &stamp));
+        }
+        for session in &activity.focus_sessions {
+            lines.extend(session_event(session, &stamp));
+        }
+        if project_blocks {
+            for block in merge_project_blocks(&activity.intervals) {
+                lines.extend(block_event(&block, &stamp));
+            }
+        }
+    }
+
+    lines.push("END:VCALENDAR".to_string());
+    lines.iter().map(|line| fold(line)).collect()
+}
+
+/// A calendar holding only the event for `session`, as stored on a
+/// CalDAV server.
+pub fn session_calendar(session: &FocusSessionSummary) -> String {
+    let mut lines = calendar_header();
+    lines.extend(session_event(session, &format_time(Utc::now())));
+    lines.push("END:VCALENDAR".to_string());
+    lines.iter().map(|line| fold(line)).collect()
+}
+
+/// Whether an event was written by `export` or `session_calendar`.
+pub fn is_own_uid(uid: &str) -> bool {
+    uid.ends_with(&format!("@{}", UID_DOMAIN))
+}
+
+fn calendar_header() -> Vec<String> {
+    vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        format!("PRODID:{}", PRODUCT_ID),
+        "CALSCALE:GREGORIAN".to_string(),
+    ]
+}
+
+/// The UID of the event exported for a focus session.
+pub fn session_uid(session: &FocusSessionSummary) -> String {
+    format!("focus-{}@{}", session.start_time.timestamp(), UID_DOMAIN)
+}
+
+fn session_event(session: &FocusSessionSummary, stamp: &str) -> Vec<String> {
+    let summary = match &session.intent {
+        Some(intent) => format!("Focus: {}", intent),
+        None => "Focus session".to_string(),
+    };
+
+    let mut description = vec![format!("Focused for {}", format_short_duration(session.duration))];
+    if let Some(score) = session.quality_score {
+        description.push(format!("Quality {}/100, {} interruptions", score, session.interruptions));
+    }
+    if !session.apps_used.is_empty() {
+        description.push(format!("Apps: {}", session.apps_used.join(", ")));
+    }
+    if let Some(rating) = session.rating {
+        description.push(format!("Rating: {}/5", rating));
+    }
+    if let Some(note) = &session.note {
+        description.push(note.clone());
+    }
+
+    let mut event = vec![
+        "BEGIN:VEVENT".to_string(),
+        format!("UID:{}", session_uid(session)),
+        format!("DTSTAMP:{}", stamp),
+        format!("DTSTART:{}", format_time(session.start_time)),
+        format!("DTEND:{}", format_time(session.start_time + session.duration)),
+        format!("SUMMARY:{}", escape_text(&summary)),
+        format!("DESCRIPTION:{}", escape_text(&description.join("\n"))),
+    ];
+    if !session.tags.is_empty() {
+        let tags: Vec<_> = session.tags.iter().map(|tag| escape_text(tag)).collect();
+        event.push(format!("CATEGORIES:{}", tags.join(",")));
+    }
+    event.push("TRANSP:OPAQUE".to_string());
+    event.push("END:VEVENT".to_string());
+    event
+}
+
+fn journal_event(day: NaiveDate, journal: &Journal, stamp: &str) -> Vec<String> {
+    let mut description = vec![journal.note.trim().to_string()];
+    if !journal.went_well.is_empty() {
+        description.push(format!("Went well:\n{}", journal.went_well.join("\n")));
+    }
+    if !journal.blockers.is_empty() {
+        description.push(format!("Blockers:\n{}", journal.blockers.join("\n")));
+    }
+    description.retain(|part| !part.is_empty());
+
+    vec![
+        "BEGIN:VEVENT".to_string(),
+        format!("UID:journal-{}@{}", day.format("%Y%m%d"), UID_DOMAIN),
+        format!("DTSTAMP:{}", stamp),
+        format!("DTSTART;VALUE=DATE:{}", day.format("%Y%m%d")),
+        format!("DTEND;VALUE=DATE:{}", (day + Duration::days(1)).format("%Y%m%d")),
+        "SUMMARY:Journal".to_string(),
+        format!("DESCRIPTION:{}", escape_text(&description.join("\n\n"))),
+        "TRANSP:TRANSPARENT".to_string(),
+        "END:VEVENT".to_string(),
+    ]
+}
+
+struct ProjectBlock {
+    project: String,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    tracked: Duration, // excludes the gaps that were merged over
+}
+
+fn merge_project_blocks(intervals: &[ActivityInterval]) -> Vec<ProjectBlock> {
+    let mut intervals: Vec<_> = intervals.iter().filter(|interval| interval.project.is_some()).collect();
+    intervals.sort_by_key(|interval| interval.start);
+
+    let mut blocks: Vec<ProjectBlock> = Vec::new();
+    for interval in intervals {
+        let project = interval.project.as_deref().unwrap_or_default();
+        match blocks.last_mut() {
+            Some(block)
+                if block.project == project
+                    && (interval.start - block.end).num_seconds() <= BLOCK_MERGE_GAP =>
+            {
+                block.end = block.end.max(interval.end);
+                block.tracked += interval.duration();
+            }
+            _ => blocks.push(ProjectBlock {
+                project: project.to_string(),
+                start: interval.start,
+                end: interval.end,
+                tracked: interval.duration(),
+            }),
+        }
+    }
+
+    blocks.retain(|block| (block.end - block.start).num_seconds() >= MIN_BLOCK);
+    blocks
+}
+
+fn block_uid(block: &ProjectBlock) -> String {
+    format!("project-{:016x}-{}@{}", fnv1a(&block.project), block.start.timestamp(), UID_DOMAIN)
+}
+
+// 64-bit FNV-1a, which unlike std's hashers gives the same value in every
+// build, so UIDs survive upgrades
+fn fnv1a(text: &str) -> u64 {
+    text.bytes()
+        .fold(0xcbf2_9ce4_8422_2325, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3))
+}
+
+fn block_event(block: &ProjectBlock, stamp: &str) -> Vec<String> {
+    vec![
+        "BEGIN:VEVENT".to_string(),
+        format!("UID:{}", block_uid(block)),
+        format!("DTSTAMP:{}", stamp),
+        format!("DTSTART:{}", format_time(block.start)),
+        format!("DTEND:{}", format_time(block.end)),
+        format!("SUMMARY:{}", escape_text(&block.project)),
+        format!("DESCRIPTION:{}", escape_text(&format!("Tracked {}", format_short_duration(block.tracked)))),
+        "TRANSP:TRANSPARENT".to_string(),
+        "END:VEVENT".to_string(),
+    ]
+}
+
+fn format_time(time: DateTime<Utc>) -> String {
+    time.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+fn escape_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+// Lines longer than 75 octets continue on the next line after a space,
+// never splitting a UTF-8 character
+fn fold(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len() + 8);
+    let mut octets = 0;
+    for c in line.chars() {
+        if octets + c.len_utf8() > MAX_LINE_OCTETS {
+            folded.push_str("\r\n ");
+            octets = 1;
+        }
+        folded.push(c);
+        octets += c.len_utf8();
+    }
+    folded.push_str("\r\n");
+    folded
+}
+
+/// Reads the events of an iCalendar file that overlap `from..until` as
+/// planned events. All-day and cancelled events are skipped. Daily, weekly,
+/// monthly and yearly recurrences are expanded, minus EXDATEs and
+/// occurrences that were moved (RECURRENCE-ID).
+pub fn import(
+    contents: &str,
+    source: &str,
+    day_model: &DayModel,
+    from: DateTime<Utc>,
+    until: DateTime<Utc>,
+) -> Vec<PlannedEvent> {
+    let components = parse_events(contents);
+
+    // Occurrences replaced by a separate component with a RECURRENCE-ID
+    let moved: HashSet<(String, DateTime<Utc>)> = components
+        .iter()
+        .filter_map(|event| {
+            let recurrence_id = event.property("RECURRENCE-ID")?;
+            let (local, zone) = parse_time(recurrence_id)?;
+            Some((event.value("UID")?.to_string(), zone.resolve(local, day_model)))
+        })
+        .collect();
+
+    let mut events = Vec::new();
+    for component in &components {
+        if component.value("STATUS").map(|status| status.eq_ignore_ascii_case("CANCELLED")).unwrap_or(false) {
+            continue;
+        }
+        let (uid, (local_start, zone)) = match (component.value("UID"), component.property("DTSTART").and_then(parse_time)) {
+            (Some(uid), Some(start)) => (uid.to_string(), start),
+            _ => continue, // no UID, all-day or unparsable start
+        };
+
+        let start = zone.resolve(local_start, day_model);
+        let length = match (component.property("DTEND").and_then(parse_time), component.value("DURATION")) {
+            (Some((local_end, end_zone)), _) => end_zone.resolve(local_end, day_model) - start,
+            (None, Some(duration)) => parse_duration(duration).unwrap_or_else(Duration::zero),
+            (None, None) => Duration::zero(),
+        };
+        if length <= Duration::zero() {
+            continue;
+        }
+
+        let summary = component.value("SUMMARY").map(unescape_text).unwrap_or_else(|| "Untitled".to_string());
+        let meeting = component.property("ATTENDEE").is_some();
+        let mut push = |start: DateTime<Utc>| {
+            if start < until && start + length > from {
+                events.push(PlannedEvent {
+                    uid: uid.clone(),
+                    summary: summary.clone(),
+                    start,
+                    end: start + length,
+                    meeting,
+                    source: source.to_string(),
+                });
+            }
+        };
+
+        let rule = component.value("RRULE").filter(|_| component.property("RECURRENCE-ID").is_none());
+        match rule {
+            Some(rule) => {
+                let excluded: HashSet<DateTime<Utc>> = component
+                    .properties
+                    .iter()
+                    .filter(|property| property.name == "EXDATE")
+                    .flat_map(|property| {
+                        property.value.split(',').filter_map(|value| {
+                            let single = Property {
+                                name: property.name.clone(),
+                                params: property.params.clone(),
+                                value: value.to_string(),
+                            };
+                            parse_time(&single).map(|(local, zone)| zone.resolve(local, day_model))
+                        })
+                    })
+                    .collect();
+                for local in recurrences(local_start, rule, &zone, day_model, from - length, until) {
+                    let start = zone.resolve(local, day_model);
+                    if !excluded.contains(&start) && !moved.contains(&(uid.clone(), start)) {
+                        push(start);
+                    }
+                }
+            }
+            None => push(start),
+        }
+    }
+    events
+}
+
+struct Property {
+    name: String,
+    params: Vec<(String, String)>,
+    value: String,
+}
+
+struct Component {
+    properties: Vec<Property>,
+}
+
+impl Component {
+    fn property(&self, name: &str) -> Option<&Property> {
+        self.properties.iter().find(|property| property.name == name)
+    }
+
+    fn value(&self, name: &str) -> Option<&str> {
+        self.property(name).map(|property| property.value.as_str())
+    }
+}
+
+// The properties of every VEVENT, without those of nested components such as VALARM
+fn parse_events(contents: &str) -> Vec<Component> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in contents.lines() {
+        match (line.strip_prefix(' ').or_else(|| line.strip_prefix('\t')), lines.last_mut()) {
+            (Some(continuation), Some(last)) => last.push_str(continuation),
+            _ => lines.push(line.to_string()),
+        }
+    }
+
+    let mut events = Vec::new();
+    let mut current: Option<Component> = None;
+    let mut nested = 0;
+    for line in &lines {
+        let property = match parse_property(line) {
+            Some(property) => property,
+            None => continue,
+        };
+        match (property.name.as_str(), property.value.to_uppercase().as_str()) {
+            ("BEGIN", "VEVENT") => current = Some(Component { properties: Vec::new() }),
+            ("END", "VEVENT") => events.extend(current.take()),
+            ("BEGIN", _) if current.is_some() => nested += 1,
+            ("END", _) if current.is_some() => nested -= 1,
+            _ => {
+                if let Some(event) = current.as_mut().filter(|_| nested == 0) {
+                    event.properties.push(property);
+                }
+            }
+        }
+    }
+    events
+}
+
+// NAME;PARAM=value;PARAM="quoted:value":value
+fn parse_property(line: &str) -> Option<Property> {
+    let mut in_quotes = false;
+    let split = line.char_indices().find(|(_, c)| {
+        if *c == '"' {
+            in_quotes = !in_quotes;
+        }
+        *c == ':' && !in_quotes
+    })?;
+    let (head, value) = (&line[..split.0], &line[split.0 + 1..]);
+
+    let mut parts = head.split(';');
+    let name = parts.next()?.trim().to_uppercase();
+    let params = parts
+        .filter_map(|param| param.split_once('='))
+        .map(|(key, value)| (key.to_uppercase(), value.trim_matches('"').to_string()))
+        .collect();
+    Some(Property {
+        name,
+        params,
+        value: value.to_string(),
+    })
+}
+
+// How a DATE-TIME is anchored
+enum Zone {
+    Utc,
+    Named(Tz),
+    Floating, // the configured timezone
+}
+
+impl Zone {
+    fn resolve(&self, local: NaiveDateTime, day_model: &DayModel) -> DateTime<Utc> {
+        match self {
+            Zone::Utc => local.and_utc(),
+            // Times skipped by a DST change move past the gap
+            Zone::Named(tz) => (0..3)
+                .find_map(|hours| tz.from_local_datetime(&(local + Duration::hours(hours))).earliest())
+                .map(|time| time.with_timezone(&Utc))
+                .unwrap_or_else(|| local.and_utc()),
+            Zone::Floating => day_model.resolve_local(local),
+        }
+    }
+}
+
+// `None` for dates without a time, which are all-day events
+fn parse_time(property: &Property) -> Option<(NaiveDateTime, Zone)> {
+    let value = property.value.trim();
+    if let Some(utc) = value.strip_suffix('Z') {
+        return Some((NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S").ok()?, Zone::Utc));
+    }
+    let local = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok()?;
+    let zone = match property.params.iter().find(|(key, _)| key == "TZID") {
+        Some((_, name)) => match name.parse::<Tz>() {
+            Ok(tz) => Zone::Named(tz),
+            Err(_) => {
+                warn!("Unknown timezone {:?} in calendar import, using the configured one", name);
+                Zone::Floating
+            }
+        },
+        None => Zone::Floating,
+    };
+    Some((local, zone))
+}
+
+// ISO 8601 durations as used by iCalendar, e.g. "PT1H30M" or "P1D"
+fn parse_duration(value: &str) -> Option<Duration> {
+    let (negative, value) = match value.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, value.strip_prefix('+').unwrap_or(value)),
+    };
+    let mut total = Duration::zero();
+    let mut number = String::new();
+    for c in value.strip_prefix('P')?.chars() {
+        match c {
+            '0'..='9' => number.push(c),
+            'T' => {}
+            'W' | 'D' | 'H' | 'M' | 'S' => {
+                let amount: i64 = number.parse().ok()?;
+                number.clear();
+                total += match c {
+                    'W' => Duration::weeks(amount),
+                    'D' => Duration::days(amount),
+                    'H' => Duration::hours(amount),
+                    'M' => Duration::minutes(amount),
+                    _ => Duration::seconds(amount),
+                };
+            }
+            _ => return None,
+        }
+    }
+    Some(if negative { -total } else { total })
+}
+
+// Local start times of a recurring event between `from` and `until`, in the
+// event's own wall-clock time so occurrences keep their time across DST
+// changes. Rules with parts that aren't understood only yield DTSTART,
+// which is always an occurrence, rather than dates that may be wrong.
+fn recurrences(
+    start: NaiveDateTime,
+    rule: &str,
+    zone: &Zone,
+    day_model: &DayModel,
+    from: DateTime<Utc>,
+    until: DateTime<Utc>,
+) -> Vec<NaiveDateTime> {
+    let parts: Vec<(String, &str)> = rule
+        .split(';')
+        .filter_map(|part| part.split_once('='))
+        .map(|(key, value)| (key.to_uppercase(), value))
+        .collect();
+    let get = |key: &str| parts.iter().find(|(name, _)| name == key).map(|(_, value)| *value);
+
+    let freq = get("FREQ").map(str::to_uppercase);
+    let by_day: Option<Vec<(i32, Weekday)>> = get("BYDAY")
+        .map(|days| days.split(',').map(|day| parse_by_day(day.trim())).collect())
+        .unwrap_or(Some(Vec::new()));
+    let by_month_day: Option<Vec<i32>> = get("BYMONTHDAY")
+        .map(|days| days.split(',').map(|day| day.trim().parse().ok()).collect())
+        .unwrap_or(Some(Vec::new()));
+    let supported = parts
+        .iter()
+        .all(|(name, _)| matches!(name.as_str(), "FREQ" | "INTERVAL" | "COUNT" | "UNTIL" | "BYDAY" | "BYMONTHDAY" | "WKST"));
+    let (by_day, by_month_day) = match (freq.as_deref(), by_day, by_month_day) {
+        (Some("DAILY" | "WEEKLY"), Some(by_day), Some(by_month_day))
+            if supported && by_month_day.is_empty() && by_day.iter().all(|(ordinal, _)| *ordinal == 0) =>
+        {
+            (by_day, by_month_day)
+        }
+        (Some("MONTHLY"), Some(by_day), Some(by_month_day)) if supported => (by_day, by_month_day),
+        (Some("YEARLY"), Some(by_day), Some(by_month_day)) if supported && by_day.is_empty() && by_month_day.is_empty() => {
+            (by_day, by_month_day)
+        }
+        _ => {
+            warn!("Recurrence rule {:?} is not supported, importing only its first occurrence", rule);
+            return vec![start];
+        }
+    };
+
+    let interval = get("INTERVAL").and_then(|value| value.parse::<u32>().ok()).unwrap_or(1).max(1);
+    let count = get("COUNT").and_then(|value| value.parse::<usize>().ok());
+    let rule_end = get("UNTIL").and_then(|value| {
+        let property = Property {
+            name: "UNTIL".to_string(),
+            params: Vec::new(),
+            value: value.to_string(),
+        };
+        match parse_time(&property) {
+            Some((local, until_zone)) => Some(until_zone.resolve(local, day_model)),
+            // A date only: the whole day is included
+            None => NaiveDate::parse_from_str(value, "%Y%m%d")
+                .ok()
+                .map(|day| zone.resolve(day.and_hms_opt(23, 59, 59).unwrap_or_default(), day_model)),
+        }
+    });
+    let last = rule_end.map(|end| end.min(until)).unwrap_or(until);
+
+    // COUNT counts from DTSTART, so only rules without it can skip ahead.
+    // A day of slack covers the difference between UTC and the event's zone.
+    let first_period = match count {
+        Some(_) => 0,
+        None => {
+            let skipped = from.naive_utc() - Duration::days(1) - start;
+            let months = (from.year() - start.year()) * 12 + from.month() as i32 - start.month() as i32 - 1;
+            let periods = match freq.as_deref() {
+                Some("DAILY") => skipped.num_days(),
+                Some("WEEKLY") => skipped.num_weeks(),
+                Some("MONTHLY") => months as i64,
+                _ => (months / 12) as i64,
+            };
+            periods.max(0) / interval as i64
+        }
+    };
+
+    let mut occurrences = Vec::new();
+    let mut generated = 0;
+    // Occurrences beyond what dates can represent end the rule
+    let weeks_after = |date: NaiveDate, weeks: i64| Duration::try_weeks(weeks).and_then(|weeks| date.checked_add_signed(weeks));
+    for period in first_period..first_period + MAX_OCCURRENCES as i64 {
+        let step = match period.checked_mul(interval as i64) {
+            Some(step) => step,
+            None => break,
+        };
+        let candidates: Vec<NaiveDateTime> = match freq.as_deref() {
+            Some("DAILY") => {
+                let day = match Duration::try_days(step).and_then(|days| start.checked_add_signed(days)) {
+                    Some(day) => day,
+                    None => break,
+                };
+                let included = by_day.is_empty() || by_day.iter().any(|(_, weekday)| *weekday == day.weekday());
+                if included { vec![day] } else { Vec::new() }
+            }
+            Some("WEEKLY") if !by_day.is_empty() => {
+                let monday = start.date() - Duration::days(start.weekday().num_days_from_monday() as i64);
+                let week = match weeks_after(monday, step) {
+                    Some(week) => week,
+                    None => break,
+                };
+                let mut days: Vec<_> = by_day
+                    .iter()
+                    .filter_map(|(_, day)| week.checked_add_days(Days::new(day.num_days_from_monday() as u64)))
+                    .map(|day| day.and_time(start.time()))
+                    .collect();
+                days.sort();
+                days.dedup();
+                days
+            }
+            Some("WEEKLY") => match weeks_after(start.date(), step) {
+                Some(day) => vec![day.and_time(start.time())],
+                None => break,
+            },
+            Some("MONTHLY") => {
+                let month = NaiveDate::from_ymd_opt(start.year(), start.month(), 1)
+                    .zip(u32::try_from(step).ok())
+                    .and_then(|(first, step)| first.checked_add_months(Months::new(step)));
+                match month {
+                    Some(month) => month_days(month, &by_day, &by_month_day, start.day())
+                        .into_iter()
+                        .map(|day| day.and_time(start.time()))
+                        .collect(),
+                    None => break,
+                }
+            }
+            _ => {
+                let year = u32::try_from(step)
+                    .ok()
+                    .and_then(|step| step.checked_mul(12))
+                    .and_then(|months| start.checked_add_months(Months::new(months)));
+                match year {
+                    // Years without the start's day (29 February) are skipped
+                    Some(candidate) if candidate.day() == start.day() => vec![candidate],
+                    Some(_) => Vec::new(),
+                    None => break,
+                }
+            }
+        };
+
+        for candidate in candidates.into_iter().filter(|candidate| *candidate >= start) {
+            if count.map(|count| generated >= count).unwrap_or(false) || zone.resolve(candidate, day_model) > last {
+                return occurrences;
+            }
+            generated += 1;
+            if zone.resolve(candidate, day_model) >= from {
+                occurrences.push(candidate);
+            }
+        }
+    }
+    occurrences
+}
+
+// The days of `month` (its first day) a monthly rule falls on: the given
+// days of the month, weekdays (all of them, or the nth from either end), or
+// both when both are set, and the start's day of the month otherwise
+fn month_days(month: NaiveDate, by_day: &[(i32, Weekday)], by_month_day: &[i32], start_day: u32) -> Vec<NaiveDate> {
+    let length = month
+        .checked_add_months(Months::new(1))
+        .map(|next| (next - month).num_days() as i32)
+        .unwrap_or(31);
+    let day = |number: i32| {
+        let number = if number < 0 { length + number + 1 } else { number };
+        (1..=length).contains(&number).then(|| month + Duration::days(number as i64 - 1))
+    };
+
+    let month_days: Vec<NaiveDate> = by_month_day.iter().filter_map(|number| day(*number)).collect();
+    let mut days: Vec<NaiveDate> = if by_day.is_empty() {
+        if by_month_day.is_empty() {
+            day(start_day as i32).into_iter().collect()
+        } else {
+            month_days
+        }
+    } else {
+        by_day
+            .iter()
+            .flat_map(|(ordinal, weekday)| {
+                let matching: Vec<NaiveDate> = (1..=length)
+                    .filter_map(day)
+                    .filter(|day| day.weekday() == *weekday)
+                    .collect();
+                match *ordinal {
+                    0 => matching,
+                    n if n > 0 => matching.get(n as usize - 1).copied().into_iter().collect(),
+                    n => matching
+                        .len()
+                        .checked_sub(n.unsigned_abs() as usize)
+                        .and_then(|index| matching.get(index))
+                        .copied()
+                        .into_iter()
+                        .collect(),
+                }
+            })
+            .filter(|day| by_month_day.is_empty() || month_days.contains(day))
+            .collect()
+    };
+    days.sort();
+    days.dedup();
+    days
+}
+
+// "MO", or with an ordinal "1MO" (first Monday) or "-1FR" (last Friday);
+// 0 stands for every such weekday
+fn parse_by_day(day: &str) -> Option<(i32, Weekday)> {
+    let split = day.len().checked_sub(2).filter(|_| day.is_ascii())?;
+    let (ordinal, weekday) = day.split_at(split);
+    let ordinal = match ordinal {
+        "" => 0,
+        ordinal => ordinal.trim_start_matches('+').parse().ok().filter(|ordinal: &i32| *ordinal != 0)?,
+    };
+    let weekday = match weekday {
+        "MO" => Weekday::Mon,
+        "TU" => Weekday::Tue,
+        "WE" => Weekday::Wed,
+        "TH" => Weekday::Thu,
+        "FR" => Weekday::Fri,
+        "SA" => Weekday::Sat,
+        "SU" => Weekday::Sun,
+        _ => return None,
+    };
+    Some((ordinal, weekday))
+}
+
+fn unescape_text(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') | Some('N') => unescaped.push('\n'),
+            Some(other) => unescaped.push(other),
+            None => {}
+        }
+    }
+    unescaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fold_keeps_lines_within_75_octets_without_splitting_characters() {
+        assert_eq!(fold("SUMMARY:short"), "SUMMARY:short\r\n");
+
+        let line = format!("DESCRIPTION:{}", "é".repeat(60));
+        let folded = fold(&line);
+        for part in folded.trim_end_matches("\r\n").split("\r\n") {
+            assert!(part.len() <= MAX_LINE_OCTETS, "{} octets", part.len());
+        }
+        // Unfolding gives back the original line
+        assert_eq!(folded.trim_end_matches("\r\n").replace("\r\n ", ""), line);
+        // A two-octet é would cross the limit at the end of the first line
+        assert_eq!(folded.split("\r\n").next().unwrap().len(), 74);
+    }
+
+    #[test]
+    fn escape_text_escapes_the_ical_specials() {
+        assert_eq!(escape_text("a\\b;c,d\ne"), "a\\\\b\\;c\\,d\\ne");
+        assert_eq!(escape_text("plain text"), "plain text");
+    }
+
+    #[test]
+    fn block_uids_are_stable_across_builds() {
+        let block = ProjectBlock {
+            project: "Website redesign".to_string(),
+            start: Utc.with_ymd_and_hms(2024, 3, 5, 9, 0, 0).unwrap(),
+            end: Utc.with_ymd_and_hms(2024, 3, 5, 10, 0, 0).unwrap(),
+            tracked: Duration::hours(1),
+        };
+        assert_eq!(block_uid(&block), "project-6c44ffa8e020c5e1-1709629200@rizeclone");
+        assert!(is_own_uid(&block_uid(&block)));
+    }
+
+    fn berlin() -> DayModel {
+        let config = crate::config::Config {
+            timezone: Some("Europe/Berlin".to_string()),
+            ..Default::default()
+        };
+        DayModel::from_config(&config)
+    }
+
+    fn utc(month: u32, day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, month, day, hour, minute, 0).unwrap()
+    }
+
+    fn import_lines(lines: &[&str], from: DateTime<Utc>, until: DateTime<Utc>) -> Vec<PlannedEvent> {
+        let contents = format!("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n{}\r\nEND:VCALENDAR\r\n", lines.join("\r\n"));
+        let mut events = import(&contents, "test.ics", &berlin(), from, until);
+        events.sort_by_key(|event| event.start);
+        events
+    }
+
+    fn starts(lines: &[&str], from: DateTime<Utc>, until: DateTime<Utc>) -> Vec<DateTime<Utc>> {
+        import_lines(lines, from, until).iter().map(|event| event.start).collect()
+    }
+
+    #[test]
+    fn import_unfolds_lines_and_reads_named_zones() {
+        let events = import_lines(
+            &[
+                "BEGIN:VEVENT",
+                "UID:planning",
+                "DTSTART;TZID=America/New_York:20240305T090000",
+                "DTEND;TZID=America/New_York:20240305T100000",
+                "SUMMARY:Weekly plan",
+                " ning\\, with team",
+                "ATTENDEE;CN=\"Doe, Jane\":mailto:jane@example.com",
+                "BEGIN:VALARM",
+                "SUMMARY:Reminder",
+                "END:VALARM",
+                "END:VEVENT",
+            ],
+            utc(3, 1, 0, 0),
+            utc(4, 1, 0, 0),
+        );
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].summary, "Weekly planning, with team");
+        assert_eq!((events[0].start, events[0].end), (utc(3, 5, 14, 0), utc(3, 5, 15, 0)));
+        assert!(events[0].meeting);
+    }
+
+    #[test]
+    fn import_reads_utc_and_floating_times() {
+        let events = import_lines(
+            &[
+                "BEGIN:VEVENT",
+                "UID:utc",
+                "DTSTART:20240305T090000Z",
+                "DURATION:PT30M",
+                "END:VEVENT",
+                "BEGIN:VEVENT",
+                "UID:floating",
+                "DTSTART:20240305T090000",
+                "DTEND:20240305T093000",
+                "END:VEVENT",
+                "BEGIN:VEVENT",
+                "UID:all-day",
+                "DTSTART;VALUE=DATE:20240305",
+                "END:VEVENT",
+            ],
+            utc(3, 1, 0, 0),
+            utc(4, 1, 0, 0),
+        );
+        // Floating times are in the configured timezone
+        let spans: Vec<_> = events.iter().map(|event| (event.uid.as_str(), event.start, event.end)).collect();
+        assert_eq!(
+            spans,
+            [("floating", utc(3, 5, 8, 0), utc(3, 5, 8, 30)), ("utc", utc(3, 5, 9, 0), utc(3, 5, 9, 30))]
+        );
+    }
+
+    #[test]
+    fn recurrences_keep_their_wall_clock_time_across_dst() {
+        // New York moves to summer time on 10 March
+        let weekly = [
+            "BEGIN:VEVENT",
+            "UID:standup",
+            "DTSTART;TZID=America/New_York:20240305T090000",
+            "DURATION:PT15M",
+            "RRULE:FREQ=WEEKLY;COUNT=2",
+            "END:VEVENT",
+        ];
+        assert_eq!(starts(&weekly, utc(3, 1, 0, 0), utc(4, 1, 0, 0)), [utc(3, 5, 14, 0), utc(3, 12, 13, 0)]);
+    }
+
+    #[test]
+    fn exdates_and_moved_occurrences_replace_the_rule() {
+        let daily = [
+            "BEGIN:VEVENT",
+            "UID:review",
+            "DTSTART:20240304T090000Z",
+            "DURATION:PT1H",
+            "RRULE:FREQ=DAILY;COUNT=5",
+            "EXDATE:20240305T090000Z",
+            "END:VEVENT",
+            "BEGIN:VEVENT",
+            "UID:review",
+            "RECURRENCE-ID:20240306T090000Z",
+            "DTSTART:20240306T150000Z",
+            "DURATION:PT1H",
+            "END:VEVENT",
+        ];
+        assert_eq!(
+            starts(&daily, utc(3, 1, 0, 0), utc(4, 1, 0, 0)),
+            [utc(3, 4, 9, 0), utc(3, 6, 15, 0), utc(3, 7, 9, 0), utc(3, 8, 9, 0)]
+        );
+    }
+
+    #[test]
+    fn until_includes_its_whole_day() {
+        let daily = [
+            "BEGIN:VEVENT",
+            "UID:review",
+            "DTSTART:20240304T090000Z",
+            "DURATION:PT1H",
+            "RRULE:FREQ=DAILY;UNTIL=20240306",
+            "END:VEVENT",
+        ];
+        assert_eq!(
+            starts(&daily, utc(3, 1, 0, 0), utc(4, 1, 0, 0)),
+            [utc(3, 4, 9, 0), utc(3, 5, 9, 0), utc(3, 6, 9, 0)]
+        );
+    }
+
+    #[test]
+    fn monthly_rules_expand_weekdays_and_month_days() {
+        let monthly = |rule: &str| {
+            let rule = format!("RRULE:FREQ=MONTHLY;COUNT=3;{}", rule);
+            let lines = ["BEGIN:VEVENT", "UID:monthly", "DTSTART:20240101T090000Z", "DURATION:PT1H", &rule, "END:VEVENT"];
+            starts(&lines, utc(1, 1, 0, 0), utc(6, 1, 0, 0))
+        };
+        assert_eq!(monthly("BYDAY=1MO"), [utc(1, 1, 9, 0), utc(2, 5, 9, 0), utc(3, 4, 9, 0)]);
+        assert_eq!(monthly("BYDAY=-1FR"), [utc(1, 26, 9, 0), utc(2, 23, 9, 0), utc(3, 29, 9, 0)]);
+        assert_eq!(monthly("BYMONTHDAY=-1"), [utc(1, 31, 9, 0), utc(2, 29, 9, 0), utc(3, 31, 9, 0)]);
+        assert_eq!(monthly("BYMONTHDAY=15"), [utc(1, 15, 9, 0), utc(2, 15, 9, 0), utc(3, 15, 9, 0)]);
+    }
+
+    #[test]
+    fn long_running_series_are_expanded_near_the_window() {
+        let daily = [
+            "BEGIN:VEVENT",
+            "UID:since-2000",
+            "DTSTART:20000101T090000Z",
+            "DURATION:PT1H",
+            "RRULE:FREQ=DAILY",
+            "END:VEVENT",
+        ];
+        assert_eq!(
+            starts(&daily, utc(3, 1, 0, 0), utc(3, 4, 0, 0)),
+            [utc(3, 1, 9, 0), utc(3, 2, 9, 0), utc(3, 3, 9, 0)]
+        );
+    }
+
+    #[test]
+    fn unsupported_rules_only_yield_their_start() {
+        let monthly = [
+            "BEGIN:VEVENT",
+            "UID:last-workday",
+            "DTSTART:20240131T090000Z",
+            "DURATION:PT1H",
+            "RRULE:FREQ=MONTHLY;BYDAY=MO,TU,WE,TH,FR;BYSETPOS=-1",
+            "END:VEVENT",
+        ];
+        assert_eq!(starts(&monthly, utc(1, 1, 0, 0), utc(6, 1, 0, 0)), [utc(1, 31, 9, 0)]);
+    }
+
+    #[test]
+    fn huge_intervals_end_the_series_instead_of_overflowing() {
+        for (rule, expected) in [
+            ("FREQ=DAILY;INTERVAL=1000000000", vec![utc(3, 5, 9, 0)]),
+            ("FREQ=WEEKLY;INTERVAL=1000000000", vec![utc(3, 5, 9, 0)]),
+            ("FREQ=WEEKLY;BYDAY=TU,TH;INTERVAL=4294967295", vec![utc(3, 5, 9, 0), utc(3, 7, 9, 0)]),
+            ("FREQ=MONTHLY;INTERVAL=4294967295", vec![utc(3, 5, 9, 0)]),
+            ("FREQ=YEARLY;INTERVAL=1000000000;COUNT=5", vec![utc(3, 5, 9, 0)]),
+        ] {
+            let rrule = format!("RRULE:{}", rule);
+            let event = [
+                "BEGIN:VEVENT",
+                "UID:huge",
+                "DTSTART:20240305T090000Z",
+                "DURATION:PT1H",
+                rrule.as_str(),
+                "END:VEVENT",
+            ];
+            assert_eq!(starts(&event, utc(1, 1, 0, 0), utc(6, 1, 0, 0)), expected, "{}", rule);
+        }
+    }
+}
@@ -4,8 +4,25 @@ mod calendar;
 mod config;
 mod utils;
 mod gui;
+mod timeline;
+mod charts;
+mod ics;
+mod caldav;
+mod project;
+mod report;
+mod browser;
+mod category;
+mod auto_start;
+mod blocker;
+mod privacy;
+mod crypto;
+mod storage;
+mod music;
+mod mpris;
+mod dnd;
+mod website_blocker;
 
-use log::info;
+use log::{error, info, warn};
 use std::sync::{Arc, Mutex};
 
 #[tokio::main]
@@ -15,20 +32,117 @@ async fn main() -> eframe::Result<()> {
     info!("Starting RizeClone productivity application");
 
     // Load configuration
-    let config = config::Config::load().expect("Failed to load configuration");
+    let mut config = config::Config::load().expect("Failed to load configuration");
+    // Passwords saved by earlier versions move to the keyring
+    if let Some(password) = config.caldav_password.take() {
+        caldav::store_password(&mut config, password);
+        if config.caldav_password.is_none() {
+            if let Err(e) = config.save() {
+                error!("Failed to save configuration: {:#}", e);
+            }
+        }
+    }
+
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(pos) = args.iter().position(|arg| arg == "--forget") {
+        if let Err(e) = forget_range(&config, args.get(pos + 1), args.get(pos + 2)) {
+            eprintln!("Error: {:#}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
 
     // Initialize components
-    let process_tracker = Arc::new(Mutex::new(process_tracker::ProcessTracker::new()));
-    let focus_mode = Arc::new(Mutex::new(focus_mode::FocusMode::new()));
-    let calendar = Arc::new(Mutex::new(calendar::Calendar::new()));
+    let mut tracker = process_tracker::ProcessTracker::new();
+    tracker.apply_config(&config);
+    let process_tracker = Arc::new(Mutex::new(tracker));
+    let mut focus = focus_mode::FocusMode::new();
+    focus.apply_config(&config);
+    if let Some(music_dir) = &config.music_dir {
+        let mut player = music::MusicPlayer::with_default_sink();
+        match player.scan(music_dir) {
+            Ok(_) => focus.set_music_player(player),
+            Err(e) => error!("Failed to scan music directory {:?}: {}", music_dir, e),
+        }
+    }
+    // Only a named player is controlled, so unrelated videos or podcasts are left alone
+    match (&config.mpris_player, config.mpris_enabled) {
+        (Some(player), true) => {
+            let controller = match &config.mpris_bus_address {
+                Some(address) => mpris::MprisController::connect(address, player),
+                None => mpris::MprisController::connect_session(player),
+            };
+            match controller {
+                Ok(controller) => focus.set_mpris_controller(controller),
+                Err(e) => error!("Failed to connect to D-Bus for MPRIS: {}", e),
+            }
+        }
+        (None, true) => warn!("MPRIS control is on but no player is set in mpris_player"),
+        _ => {}
+    }
+    focus.set_website_blocker(website_blocker::WebsiteBlocker::from_config(&config));
+    focus.set_do_not_disturb(config.do_not_disturb, &config.data_dir);
+    if let Err(e) = focus.load_state() {
+        error!("Failed to restore focus session: {:#}", e);
+    }
+    let focus_mode = Arc::new(Mutex::new(focus));
+    let mut calendar = calendar::Calendar::new();
+    calendar.set_day_model(calendar::DayModel::from_config(&config));
+    let calendar = Arc::new(Mutex::new(calendar));
+
+    // Open activity storage. A passphrase is asked for in the GUI unless
+    // `--unlock` was given, in which case it is read from the terminal.
+    let passphrase = if config.encryption == config::Encryption::Passphrase
+        && args.iter().any(|arg| arg == "--unlock")
+    {
+        utils::read_passphrase("Passphrase: ").ok()
+    } else {
+        None
+    };
+    let storage = if config.encryption == config::Encryption::Passphrase && passphrase.is_none() {
+        None
+    } else {
+        match storage::ActivityStore::unlock(&config, passphrase.as_deref()) {
+            Ok(store) => Some(store),
+            Err(e) => {
+                error!("Failed to open activity storage: {:#}", e);
+                None
+            }
+        }
+    };
+
+    // Focus events are recorded here and forwarded to the GUI for display
+    let (focus_events_tx, focus_events_rx) = std::sync::mpsc::channel();
+    let timer_focus_mode = focus_mode.clone();
+    let timer_calendar = calendar.clone();
+
+    let caldav_sync = match caldav::CalDavClient::from_config(&config) {
+        Ok(Some(client)) => Some(caldav::spawn_sync(
+            client,
+            calendar.clone(),
+            calendar::DayModel::from_config(&config),
+            chrono::Duration::minutes(config.caldav_sync_minutes.max(1) as i64),
+        )),
+        Ok(None) => None,
+        Err(e) => {
+            error!("Failed to set up CalDAV sync: {:#}", e);
+            None
+        }
+    };
+    let timer_caldav_sync = caldav_sync.clone();
 
     // Create the GUI application
-    let app = gui::RizeCloneApp::new(
+    let mut app = gui::RizeCloneApp::new(
         config,
         process_tracker,
         focus_mode,
         calendar,
+        storage,
+        focus_events_rx,
     );
+    if let Some(sync) = caldav_sync {
+        app.set_caldav_sync(sync);
+    }
 
     // Run the GUI
     let options = eframe::NativeOptions {
@@ -41,6 +155,52 @@ async fn main() -> eframe::Result<()> {
     eframe::run_native(
         "RizeClone",
         options,
-        Box::new(|_cc| Box::new(app)),
+        Box::new(move |cc| {
+            let ctx = cc.egui_ctx.clone();
+            focus_mode::spawn_timer(timer_focus_mode, move |event| {
+                if let Some(summary) = event.focus_summary() {
+                    if let Ok(mut calendar) = timer_calendar.lock() {
+                        let _ = calendar.add_focus_session(summary);
+                    }
+                    if let Some(sync) = &timer_caldav_sync {
+                        sync.sync_now();
+                    }
+                }
+                let _ = focus_events_tx.send(event);
+                ctx.request_repaint();
+            });
+            Box::new(app)
+        }),
     )
 }
+
+// `--forget START END` removes all stored data for a date range and exits
+fn forget_range(
+    config: &config::Config,
+    start: Option<&String>,
+    end: Option<&String>,
+) -> anyhow::Result<()> {
+    let parse = |date: Option<&String>| {
+        let date = date.ok_or_else(|| anyhow::anyhow!("Usage: --forget YYYY-MM-DD YYYY-MM-DD"))?;
+        chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d")
+            .map_err(|e| anyhow::anyhow!("Invalid date {:?}: {}", date, e))
+    };
+    let (start, end) = (parse(start)?, parse(end)?);
+    if start > end {
+        anyhow::bail!("The start date must not be after the end date");
+    }
+
+    let passphrase = if config.encryption == config::Encryption::Passphrase {
+        Some(utils::read_passphrase("Passphrase: ")?)
+    } else {
+        None
+    };
+    let store = storage::ActivityStore::unlock(config, passphrase.as_deref())?;
+    let mut calendar = store.load_calendar()?;
+    let sessions = calendar.forget_range(start, end);
+    store.save(&mut calendar)?;
+    caldav::queue_deletions(config, &sessions);
+
+    println!("Forgot all data from {} to {}", start, end);
+    Ok(())
+}
@@ -0,0 +1,291 @@
+use anyhow::{anyhow, Result};
+use log::{debug, info, warn};
+use std::collections::HashMap;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use zbus::blocking::{fdo::DBusProxy, Connection, Proxy};
+use zbus::zvariant::OwnedValue;
+
+const MPRIS_PREFIX: &str = "org.mpris.MediaPlayer2.";
+const MPRIS_PATH: &str = "/org/mpris/MediaPlayer2";
+const PLAYER_INTERFACE: &str = "org.mpris.MediaPlayer2.Player";
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrackInfo {
+    pub player: String,
+    pub title: String,
+    pub artist: Option<String>,
+}
+
+impl TrackInfo {
+    pub fn display(&self) -> String {
+        match &self.artist {
+            Some(artist) => format!("{} – {}", artist, self.title),
+            None => self.title.clone(),
+        }
+    }
+}
+
+/// Drives an external media player (Spotify, mpv, ...) through the MPRIS
+/// D-Bus interface. Only the configured player is controlled, never whatever
+/// else happens to be playing. D-Bus calls block, so they run on a worker
+/// thread and this handle only queues commands and reads the last result.
+pub struct MprisController {
+    commands: mpsc::Sender<Command>,
+    now_playing: Arc<Mutex<Option<TrackInfo>>>,
+}
+
+enum Command {
+    Play,
+    Pause,
+}
+
+impl MprisController {
+    /// Connects to the session bus.
+    pub fn connect_session(player: &str) -> Result<Self> {
+        Ok(Self::spawn(Connection::session()?, player))
+    }
+
+    /// Connects to the bus at `address`, e.g. a private bus running a mock player.
+    pub fn connect(address: &str, player: &str) -> Result<Self> {
+        let connection = zbus::blocking::connection::Builder::address(address)?.build()?;
+        Ok(Self::spawn(connection, player))
+    }
+
+    fn spawn(connection: Connection, player: &str) -> Self {
+        let (commands, receiver) = mpsc::channel();
+        let now_playing = Arc::new(Mutex::new(None));
+        let worker_now_playing = now_playing.clone();
+        let bus = PlayerBus {
+            connection,
+            player: player.to_lowercase(),
+        };
+
+        std::thread::spawn(move || loop {
+            match receiver.recv_timeout(POLL_INTERVAL) {
+                Ok(Command::Play) => {
+                    if let Err(e) = bus.call("Play") {
+                        warn!("Failed to start MPRIS playback: {}", e);
+                    }
+                }
+                Ok(Command::Pause) => {
+                    if let Err(e) = bus.call("Pause") {
+                        warn!("Failed to pause MPRIS playback: {}", e);
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+
+            match bus.current_track() {
+                Ok(track) => {
+                    if let Ok(mut now_playing) = worker_now_playing.lock() {
+                        if track != *now_playing {
+                            if let Some(track) = &track {
+                                info!("MPRIS player is playing {}", track.display());
+                            }
+                            *now_playing = track;
+                        }
+                    }
+                }
+                Err(e) => debug!("Failed to read MPRIS metadata: {}", e),
+            }
+        });
+
+        Self { commands, now_playing }
+    }
+
+    pub fn play(&mut self) {
+        let _ = self.commands.send(Command::Play);
+    }
+
+    pub fn pause(&mut self) {
+        let _ = self.commands.send(Command::Pause);
+    }
+
+    /// What the player was playing when it was last asked.
+    pub fn now_playing(&self) -> Option<TrackInfo> {
+        self.now_playing.lock().ok().and_then(|track| track.clone())
+    }
+}
+
+// The worker's side: the connection and the player it may control
+struct PlayerBus {
+    connection: Connection,
+    player: String, // lowercase part of the bus name after the MPRIS prefix
+}
+
+impl PlayerBus {
+    // The bus name of the configured player, if it is running
+    fn find_player(&self) -> Result<Option<String>> {
+        Ok(DBusProxy::new(&self.connection)?
+            .list_names()?
+            .into_iter()
+            .map(|name| name.to_string())
+            .filter(|name| name.starts_with(MPRIS_PREFIX))
+            .find(|name| name[MPRIS_PREFIX.len()..].to_lowercase().contains(self.player.as_str())))
+    }
+
+    fn player_proxy(&self, name: &str) -> Result<Proxy<'_>> {
+        Ok(Proxy::new(
+            &self.connection,
+            name.to_string(),
+            MPRIS_PATH,
+            PLAYER_INTERFACE,
+        )?)
+    }
+
+    fn call(&self, method: &str) -> Result<()> {
+        let player = self
+            .find_player()?
+            .ok_or_else(|| anyhow!("MPRIS player {:?} is not running", self.player))?;
+        debug!("MPRIS: {} on {}", method, player);
+        self.player_proxy(&player)?.call_method(method, &())?;
+        Ok(())
+    }
+
+    fn current_track(&self) -> Result<Option<TrackInfo>> {
+        let player = match self.find_player()? {
+            Some(player) => player,
+            None => return Ok(None),
+        };
+        let proxy = self.player_proxy(&player)?;
+        if proxy.get_property::<String>("PlaybackStatus")? != "Playing" {
+            return Ok(None);
+        }
+
+        let metadata: HashMap<String, OwnedValue> = proxy.get_property("Metadata")?;
+        let title = metadata
+            .get("xesam:title")
+            .and_then(|value| String::try_from(value.try_clone().ok()?).ok());
+        let artist = metadata
+            .get("xesam:artist")
+            .and_then(|value| Vec::<String>::try_from(value.try_clone().ok()?).ok())
+            .map(|artists| artists.join(", "))
+            .filter(|artists| !artists.is_empty());
+
+        Ok(title.map(|title| TrackInfo {
+            player: player[MPRIS_PREFIX.len()..].to_string(),
+            title,
+            artist,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader};
+    use std::process::{Child, Command, Stdio};
+    use std::time::Instant;
+    use zbus::zvariant::{Str, Value};
+
+    // A private bus, so the test never touches the user's players
+    struct PrivateBus {
+        daemon: Child,
+        address: String,
+    }
+
+    impl PrivateBus {
+        fn start() -> Option<Self> {
+            let mut daemon = Command::new("dbus-daemon")
+                .args(["--session", "--nofork", "--print-address"])
+                .stdout(Stdio::piped())
+                .stderr(Stdio::null())
+                .spawn()
+                .ok()?;
+            let mut address = String::new();
+            BufReader::new(daemon.stdout.take()?).read_line(&mut address).ok()?;
+            Some(Self {
+                daemon,
+                address: address.trim().to_string(),
+            })
+        }
+    }
+
+    impl Drop for PrivateBus {
+        fn drop(&mut self) {
+            let _ = self.daemon.kill();
+            let _ = self.daemon.wait();
+        }
+    }
+
+    struct MockPlayer {
+        status: Arc<Mutex<String>>,
+    }
+
+    #[zbus::interface(name = "org.mpris.MediaPlayer2.Player")]
+    impl MockPlayer {
+        fn play(&mut self) {
+            *self.status.lock().unwrap() = "Playing".to_string();
+        }
+
+        fn pause(&mut self) {
+            *self.status.lock().unwrap() = "Paused".to_string();
+        }
+
+        #[zbus(property)]
+        fn playback_status(&self) -> String {
+            self.status.lock().unwrap().clone()
+        }
+
+        #[zbus(property)]
+        fn metadata(&self) -> HashMap<String, OwnedValue> {
+            let mut metadata = HashMap::new();
+            metadata.insert("xesam:title".to_string(), OwnedValue::from(Str::from("Deep Focus")));
+            let artists = Value::from(vec!["Someone"]).try_into().unwrap();
+            metadata.insert("xesam:artist".to_string(), artists);
+            metadata
+        }
+    }
+
+    fn serve_player(address: &str, name: &str) -> (Connection, Arc<Mutex<String>>) {
+        let status = Arc::new(Mutex::new("Stopped".to_string()));
+        let player = MockPlayer { status: status.clone() };
+        let connection = zbus::blocking::connection::Builder::address(address)
+            .unwrap()
+            .name(format!("{}{}", MPRIS_PREFIX, name))
+            .unwrap()
+            .serve_at(MPRIS_PATH, player)
+            .unwrap()
+            .build()
+            .unwrap();
+        (connection, status)
+    }
+
+    fn wait_for(mut done: impl FnMut() -> bool) {
+        let started = Instant::now();
+        while !done() {
+            assert!(started.elapsed() < Duration::from_secs(10), "timed out");
+            std::thread::sleep(Duration::from_millis(50));
+        }
+    }
+
+    #[test]
+    fn controls_only_the_configured_player() {
+        let bus = match PrivateBus::start() {
+            Some(bus) => bus,
+            None => {
+                eprintln!("dbus-daemon is not available, skipping");
+                return;
+            }
+        };
+        let (_spotify, spotify_status) = serve_player(&bus.address, "spotify");
+        let (_podcasts, podcasts_status) = serve_player(&bus.address, "podcasts");
+
+        let mut controller = MprisController::connect(&bus.address, "Spotify").unwrap();
+        controller.play();
+        wait_for(|| *spotify_status.lock().unwrap() == "Playing");
+        wait_for(|| controller.now_playing().is_some());
+        let track = controller.now_playing().unwrap();
+        assert_eq!(track.player, "spotify");
+        assert_eq!(track.display(), "Someone – Deep Focus");
+
+        controller.pause();
+        wait_for(|| *spotify_status.lock().unwrap() == "Paused");
+        wait_for(|| controller.now_playing().is_none());
+        assert_eq!(*podcasts_status.lock().unwrap(), "Stopped");
+    }
+}
@@ -0,0 +1,766 @@
+use anyhow::{anyhow, Result};
+use log::{info, warn};
+use rand::seq::SliceRandom;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+const AUDIO_EXTENSIONS: [&str; 8] = ["mp3", "flac", "ogg", "opus", "wav", "m4a", "aac", "wma"];
+const FADE_DURATION: Duration = Duration::from_secs(3);
+// How often the worker asks a playing sink whether the track ended
+const FINISHED_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Where audio actually goes. The player only talks to this trait, so it can
+/// run against `NullSink` where no audio output is available.
+pub trait AudioSink: Send {
+    fn play(&mut self, path: &Path) -> Result<()>;
+    fn pause(&mut self) -> Result<()>;
+    fn resume(&mut self) -> Result<()>;
+    fn stop(&mut self) -> Result<()>;
+    /// `volume` ranges from 0.0 to 1.0.
+    fn set_volume(&mut self, volume: f32) -> Result<()>;
+    /// Whether the current track has played to its end.
+    fn is_finished(&mut self) -> bool;
+}
+
+/// Discards audio but keeps track of what would be playing.
+#[derive(Debug, Default)]
+pub struct NullSink {
+    pub current: Option<PathBuf>,
+    pub paused: bool,
+    pub volume: f32,
+    pub finished: bool, // set to simulate the end of a track
+}
+
+impl AudioSink for NullSink {
+    fn play(&mut self, path: &Path) -> Result<()> {
+        self.current = Some(path.to_path_buf());
+        self.paused = false;
+        self.finished = false;
+        Ok(())
+    }
+
+    fn pause(&mut self) -> Result<()> {
+        self.paused = true;
+        Ok(())
+    }
+
+    fn resume(&mut self) -> Result<()> {
+        self.paused = false;
+        Ok(())
+    }
+
+    fn stop(&mut self) -> Result<()> {
+        self.current = None;
+        self.paused = false;
+        Ok(())
+    }
+
+    fn set_volume(&mut self, volume: f32) -> Result<()> {
+        self.volume = volume;
+        Ok(())
+    }
+
+    fn is_finished(&mut self) -> bool {
+        self.finished
+    }
+}
+
+enum SinkCommand {
+    Play(PathBuf, u64),
+    Pause,
+    Resume,
+    Stop,
+    SetVolume(f32),
+}
+
+/// Runs another sink on a worker thread. Commands are queued and return at
+/// once, so a slow audio backend never blocks the caller. Errors are logged
+/// by the worker.
+pub struct ThreadedSink {
+    commands: mpsc::Sender<SinkCommand>,
+    playing: u64,             // number of the last track started
+    finished: Arc<AtomicU64>, // number of the last track that played to its end
+}
+
+impl ThreadedSink {
+    pub fn spawn(mut sink: Box<dyn AudioSink>) -> Self {
+        let (commands, receiver) = mpsc::channel();
+        let finished = Arc::new(AtomicU64::new(0));
+        let worker_finished = finished.clone();
+
+        std::thread::spawn(move || {
+            let mut playing = None;
+            loop {
+                let command = match receiver.recv_timeout(FINISHED_POLL_INTERVAL) {
+                    Ok(command) => command,
+                    Err(RecvTimeoutError::Timeout) => {
+                        if let Some(track) = playing {
+                            if sink.is_finished() {
+                                worker_finished.store(track, Ordering::SeqCst);
+                                playing = None;
+                            }
+                        }
+                        continue;
+                    }
+                    Err(RecvTimeoutError::Disconnected) => break,
+                };
+
+                let result = match command {
+                    SinkCommand::Play(path, track) => {
+                        playing = Some(track);
+                        sink.play(&path)
+                    }
+                    SinkCommand::Pause => sink.pause(),
+                    SinkCommand::Resume => sink.resume(),
+                    SinkCommand::Stop => {
+                        playing = None;
+                        sink.stop()
+                    }
+                    SinkCommand::SetVolume(volume) => sink.set_volume(volume),
+                };
+                if let Err(e) = result {
+                    warn!("Music playback failed: {:#}", e);
+                }
+            }
+        });
+
+        Self {
+            commands,
+            playing: 0,
+            finished,
+        }
+    }
+
+    fn send(&self, command: SinkCommand) -> Result<()> {
+        self.commands
+            .send(command)
+            .map_err(|_| anyhow!("The music worker stopped"))
+    }
+}
+
+impl AudioSink for ThreadedSink {
+    fn play(&mut self, path: &Path) -> Result<()> {
+        self.playing += 1;
+        self.send(SinkCommand::Play(path.to_path_buf(), self.playing))
+    }
+
+    fn pause(&mut self) -> Result<()> {
+        self.send(SinkCommand::Pause)
+    }
+
+    fn resume(&mut self) -> Result<()> {
+        self.send(SinkCommand::Resume)
+    }
+
+    fn stop(&mut self) -> Result<()> {
+        self.send(SinkCommand::Stop)
+    }
+
+    fn set_volume(&mut self, volume: f32) -> Result<()> {
+        self.send(SinkCommand::SetVolume(volume))
+    }
+
+    // Only the end of the current track counts, not of one replaced meanwhile
+    fn is_finished(&mut self) -> bool {
+        self.playing > 0 && self.finished.load(Ordering::SeqCst) == self.playing
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlaybackState {
+    Stopped,
+    Playing,
+    Paused,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AfterFade {
+    Pause,
+    Stop,
+}
+
+struct Fade {
+    started: Instant,
+    then: AfterFade,
+}
+
+#[derive(Debug, Clone)]
+pub struct NowPlaying {
+    pub path: PathBuf,
+    pub title: String,
+    pub state: PlaybackState,
+}
+
+/// Plays a shuffled playlist built from the music directory.
+pub struct MusicPlayer {
+    sink: Box<dyn AudioSink>,
+    library: Vec<PathBuf>,
+    playlist: Vec<PathBuf>,
+    position: usize,
+    state: PlaybackState,
+    volume: f32,
+    fade: Option<Fade>,
+}
+
+impl MusicPlayer {
+    pub fn new(sink: Box<dyn AudioSink>) -> Self {
+        Self {
+            sink,
+            library: Vec::new(),
+            playlist: Vec::new(),
+            position: 0,
+            state: PlaybackState::Stopped,
+            volume: 1.0,
+            fade: None,
+        }
+    }
+
+    /// Uses mpv when it is installed and falls back to a silent sink. mpv is
+    /// controlled from a worker thread, its IPC calls can take seconds.
+    pub fn with_default_sink() -> Self {
+        #[cfg(unix)]
+        if MpvSink::is_available() {
+            return Self::new(Box::new(ThreadedSink::spawn(Box::new(MpvSink::new()))));
+        }
+        warn!("mpv not found, focus music will not be audible");
+        Self::new(Box::new(NullSink::default()))
+    }
+
+    /// Collects the audio files under `dir` (recursively) and reshuffles.
+    pub fn scan(&mut self, dir: &Path) -> Result<usize> {
+        if !dir.is_dir() {
+            return Err(anyhow!("{:?} is not a directory", dir));
+        }
+        let mut files = Vec::new();
+        collect_audio_files(dir, &mut files, &mut HashSet::new());
+        files.sort();
+        info!("Found {} music files in {:?}", files.len(), dir);
+
+        self.library = files;
+        self.shuffle();
+        Ok(self.library.len())
+    }
+
+    pub fn shuffle(&mut self) {
+        self.playlist = self.library.clone();
+        self.playlist.shuffle(&mut rand::thread_rng());
+        self.position = 0;
+    }
+
+    pub fn current_track(&self) -> Option<&PathBuf> {
+        match self.state {
+            PlaybackState::Stopped => None,
+            _ => self.playlist.get(self.position),
+        }
+    }
+
+    /// Starts playback, or resumes it at full volume if it was paused or fading.
+    pub fn play(&mut self) {
+        self.fade = None;
+        self.apply_volume(self.volume);
+        match self.state {
+            PlaybackState::Playing => {}
+            PlaybackState::Paused => {
+                if let Err(e) = self.sink.resume() {
+                    warn!("Failed to resume music: {}", e);
+                }
+                self.state = PlaybackState::Playing;
+            }
+            PlaybackState::Stopped => self.play_current(),
+        }
+    }
+
+    pub fn pause(&mut self) {
+        self.fade = None;
+        if self.state == PlaybackState::Playing {
+            if let Err(e) = self.sink.pause() {
+                warn!("Failed to pause music: {}", e);
+            }
+            self.state = PlaybackState::Paused;
+        }
+    }
+
+    pub fn stop(&mut self) {
+        self.fade = None;
+        if self.state != PlaybackState::Stopped {
+            if let Err(e) = self.sink.stop() {
+                warn!("Failed to stop music: {}", e);
+            }
+            self.state = PlaybackState::Stopped;
+        }
+    }
+
+    pub fn next_track(&mut self) {
+        if self.playlist.is_empty() {
+            return;
+        }
+        self.position += 1;
+        if self.position >= self.playlist.len() {
+            self.shuffle();
+        }
+        if self.state != PlaybackState::Stopped {
+            self.play_current();
+        }
+    }
+
+    /// Lowers the volume to silence, then pauses (for breaks).
+    pub fn fade_out_and_pause(&mut self) {
+        self.start_fade(AfterFade::Pause);
+    }
+
+    /// Lowers the volume to silence, then stops (at the end of a session).
+    pub fn fade_out_and_stop(&mut self) {
+        self.start_fade(AfterFade::Stop);
+    }
+
+    fn start_fade(&mut self, then: AfterFade) {
+        if self.state == PlaybackState::Playing {
+            self.fade = Some(Fade {
+                started: Instant::now(),
+                then,
+            });
+        } else if then == AfterFade::Stop {
+            self.stop();
+        }
+    }
+
+    /// Advances fades and moves on when a track ends. Call regularly.
+    pub fn tick(&mut self) {
+        if let Some(fade) = &self.fade {
+            let progress = fade.started.elapsed().as_secs_f32() / FADE_DURATION.as_secs_f32();
+            if progress >= 1.0 {
+                let then = fade.then;
+                match then {
+                    AfterFade::Pause => self.pause(),
+                    AfterFade::Stop => self.stop(),
+                }
+                // Restore the volume so the next play starts audible
+                self.apply_volume(self.volume);
+            } else {
+                self.apply_volume(self.volume * (1.0 - progress));
+            }
+        }
+
+        if self.state == PlaybackState::Playing && self.sink.is_finished() {
+            self.next_track();
+        }
+    }
+
+    pub fn now_playing(&self) -> Option<NowPlaying> {
+        let path = self.current_track()?.clone();
+        let title = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+        Some(NowPlaying {
+            path,
+            title,
+            state: self.state,
+        })
+    }
+
+    fn play_current(&mut self) {
+        let path = match self.playlist.get(self.position) {
+            Some(path) => path.clone(),
+            None => return,
+        };
+        match self.sink.play(&path) {
+            Ok(()) => self.state = PlaybackState::Playing,
+            Err(e) => {
+                warn!("Failed to play {:?}: {}", path, e);
+                self.state = PlaybackState::Stopped;
+            }
+        }
+    }
+
+    fn apply_volume(&mut self, volume: f32) {
+        if let Err(e) = self.sink.set_volume(volume) {
+            warn!("Failed to set music volume: {}", e);
+        }
+    }
+}
+
+// Unreadable directories are skipped, and each directory is visited once even
+// when symlinks lead back to it
+fn collect_audio_files(dir: &Path, files: &mut Vec<PathBuf>, visited: &mut HashSet<PathBuf>) {
+    let real_dir = match dir.canonicalize() {
+        Ok(real_dir) => real_dir,
+        Err(e) => {
+            warn!("Skipping {:?}: {}", dir, e);
+            return;
+        }
+    };
+    if !visited.insert(real_dir) {
+        return;
+    }
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            warn!("Skipping {:?}: {}", dir, e);
+            return;
+        }
+    };
+
+    for entry in entries {
+        let path = match entry {
+            Ok(entry) => entry.path(),
+            Err(e) => {
+                warn!("Skipping an entry of {:?}: {}", dir, e);
+                continue;
+            }
+        };
+        if path.is_dir() {
+            collect_audio_files(&path, files, visited);
+        } else if is_audio_file(&path) {
+            files.push(path);
+        }
+    }
+}
+
+fn is_audio_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| AUDIO_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+/// Plays audio through an `mpv` process controlled over its JSON IPC socket.
+#[cfg(unix)]
+pub struct MpvSink {
+    process: Option<std::process::Child>,
+    socket_path: PathBuf,
+    connection: Option<std::io::BufReader<std::os::unix::net::UnixStream>>,
+    next_request_id: u64,
+    loaded_at: Option<Instant>,
+}
+
+#[cfg(unix)]
+impl MpvSink {
+    pub fn new() -> Self {
+        Self {
+            process: None,
+            socket_path: std::env::temp_dir().join(format!("rizeclone-mpv-{}.sock", std::process::id())),
+            connection: None,
+            next_request_id: 1,
+            loaded_at: None,
+        }
+    }
+
+    pub fn is_available() -> bool {
+        std::process::Command::new("mpv")
+            .arg("--version")
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .status()
+            .map(|status| status.success())
+            .unwrap_or(false)
+    }
+
+    // Starts mpv on first use and connects to its IPC socket
+    fn connect(&mut self) -> Result<()> {
+        if self.connection.is_some() {
+            return Ok(());
+        }
+
+        let _ = std::fs::remove_file(&self.socket_path);
+        let process = std::process::Command::new("mpv")
+            .args(["--no-video", "--idle=yes", "--no-terminal"])
+            .arg(format!("--input-ipc-server={}", self.socket_path.display()))
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .spawn()?;
+        self.process = Some(process);
+
+        let started = Instant::now();
+        while started.elapsed() < Duration::from_secs(3) {
+            if let Ok(stream) = std::os::unix::net::UnixStream::connect(&self.socket_path) {
+                stream.set_read_timeout(Some(Duration::from_millis(500)))?;
+                self.connection = Some(std::io::BufReader::new(stream));
+                return Ok(());
+            }
+            std::thread::sleep(Duration::from_millis(50));
+        }
+        Err(anyhow!("Timed out waiting for mpv to start"))
+    }
+
+    // Sends a command and waits for its reply, skipping unrelated events
+    fn command(&mut self, command: serde_json::Value) -> Result<serde_json::Value> {
+        use std::io::{BufRead, Write};
+
+        self.connect()?;
+        let request_id = self.next_request_id;
+        self.next_request_id += 1;
+        let request = serde_json::json!({ "command": command, "request_id": request_id });
+
+        let connection = self.connection.as_mut().ok_or_else(|| anyhow!("mpv is not connected"))?;
+        let result = (|| {
+            writeln!(connection.get_mut(), "{}", request)?;
+            loop {
+                let mut line = String::new();
+                if connection.read_line(&mut line)? == 0 {
+                    return Err(anyhow!("mpv closed the connection"));
+                }
+                let reply: serde_json::Value = serde_json::from_str(&line)?;
+                if reply.get("request_id").and_then(|id| id.as_u64()) == Some(request_id) {
+                    return match reply.get("error").and_then(|error| error.as_str()) {
+                        Some("success") | None => Ok(reply.get("data").cloned().unwrap_or_default()),
+                        Some(error) => Err(anyhow!("mpv: {}", error)),
+                    };
+                }
+            }
+        })();
+
+        // Reconnect (and restart mpv if needed) next time
+        if result.is_err() {
+            self.connection = None;
+        }
+        result
+    }
+}
+
+#[cfg(unix)]
+impl AudioSink for MpvSink {
+    fn play(&mut self, path: &Path) -> Result<()> {
+        self.command(serde_json::json!(["loadfile", path.to_string_lossy(), "replace"]))?;
+        self.command(serde_json::json!(["set_property", "pause", false]))?;
+        self.loaded_at = Some(Instant::now());
+        Ok(())
+    }
+
+    fn pause(&mut self) -> Result<()> {
+        self.command(serde_json::json!(["set_property", "pause", true]))?;
+        Ok(())
+    }
+
+    fn resume(&mut self) -> Result<()> {
+        self.command(serde_json::json!(["set_property", "pause", false]))?;
+        Ok(())
+    }
+
+    fn stop(&mut self) -> Result<()> {
+        self.command(serde_json::json!(["stop"]))?;
+        Ok(())
+    }
+
+    fn set_volume(&mut self, volume: f32) -> Result<()> {
+        self.command(serde_json::json!(["set_property", "volume", (volume * 100.0).round()]))?;
+        Ok(())
+    }
+
+    fn is_finished(&mut self) -> bool {
+        // mpv stays idle for a moment after loadfile while it opens the track
+        let loading = self
+            .loaded_at
+            .map(|loaded_at| loaded_at.elapsed() < Duration::from_secs(2))
+            .unwrap_or(true);
+        if loading {
+            return false;
+        }
+        self.command(serde_json::json!(["get_property", "idle-active"]))
+            .map(|idle| idle.as_bool().unwrap_or(false))
+            .unwrap_or(false)
+    }
+}
+
+#[cfg(unix)]
+impl Drop for MpvSink {
+    fn drop(&mut self) {
+        if let Some(mut process) = self.process.take() {
+            let _ = process.kill();
+            let _ = process.wait();
+        }
+        let _ = std::fs::remove_file(&self.socket_path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    // Lets a test look at the sink after handing it to the player
+    #[derive(Clone, Default)]
+    struct SharedSink(Arc<Mutex<NullSink>>);
+
+    impl AudioSink for SharedSink {
+        fn play(&mut self, path: &Path) -> Result<()> {
+            self.0.lock().unwrap().play(path)
+        }
+
+        fn pause(&mut self) -> Result<()> {
+            self.0.lock().unwrap().pause()
+        }
+
+        fn resume(&mut self) -> Result<()> {
+            self.0.lock().unwrap().resume()
+        }
+
+        fn stop(&mut self) -> Result<()> {
+            self.0.lock().unwrap().stop()
+        }
+
+        fn set_volume(&mut self, volume: f32) -> Result<()> {
+            self.0.lock().unwrap().set_volume(volume)
+        }
+
+        fn is_finished(&mut self) -> bool {
+            self.0.lock().unwrap().is_finished()
+        }
+    }
+
+    fn player(tracks: &[&str]) -> (MusicPlayer, Arc<Mutex<NullSink>>) {
+        let sink = SharedSink::default();
+        let state = sink.0.clone();
+        let mut player = MusicPlayer::new(Box::new(sink));
+        player.library = tracks.iter().map(PathBuf::from).collect();
+        player.shuffle();
+        (player, state)
+    }
+
+    // Lets the running fade reach its end without waiting for it
+    fn finish_fade(player: &mut MusicPlayer) {
+        if let Some(fade) = &mut player.fade {
+            fade.started = Instant::now() - FADE_DURATION;
+        }
+        player.tick();
+    }
+
+    #[test]
+    fn play_pause_and_resume() {
+        let (mut player, sink) = player(&["a.mp3", "b.mp3"]);
+        assert!(player.now_playing().is_none());
+
+        player.play();
+        let track = player.current_track().cloned().unwrap();
+        assert_eq!(sink.lock().unwrap().current.as_ref(), Some(&track));
+        assert_eq!(player.now_playing().unwrap().state, PlaybackState::Playing);
+
+        player.pause();
+        assert!(sink.lock().unwrap().paused);
+        assert_eq!(player.now_playing().unwrap().state, PlaybackState::Paused);
+
+        player.play();
+        assert!(!sink.lock().unwrap().paused);
+        assert_eq!(player.current_track(), Some(&track));
+    }
+
+    #[test]
+    fn fade_out_lowers_the_volume_then_pauses() {
+        let (mut player, sink) = player(&["a.mp3"]);
+        player.play();
+        player.fade_out_and_pause();
+
+        player.fade.as_mut().unwrap().started = Instant::now() - FADE_DURATION / 2;
+        player.tick();
+        let volume = sink.lock().unwrap().volume;
+        assert!(volume > 0.0 && volume < 1.0, "volume {}", volume);
+        assert_eq!(player.now_playing().unwrap().state, PlaybackState::Playing);
+
+        finish_fade(&mut player);
+        assert!(sink.lock().unwrap().paused);
+        assert_eq!(sink.lock().unwrap().volume, 1.0);
+        assert_eq!(player.now_playing().unwrap().state, PlaybackState::Paused);
+
+        // Playing again cancels nothing and starts at full volume
+        player.play();
+        assert!(player.fade.is_none());
+        assert!(!sink.lock().unwrap().paused);
+    }
+
+    #[test]
+    fn play_during_a_fade_cancels_it() {
+        let (mut player, sink) = player(&["a.mp3"]);
+        player.play();
+        player.fade_out_and_stop();
+        player.play();
+        finish_fade(&mut player);
+
+        assert_eq!(player.now_playing().unwrap().state, PlaybackState::Playing);
+        assert_eq!(sink.lock().unwrap().volume, 1.0);
+    }
+
+    #[test]
+    fn fade_out_and_stop_ends_playback() {
+        let (mut player, sink) = player(&["a.mp3"]);
+        player.play();
+        player.fade_out_and_stop();
+        finish_fade(&mut player);
+
+        assert!(player.now_playing().is_none());
+        assert!(sink.lock().unwrap().current.is_none());
+
+        // Stopping while not playing needs no fade
+        player.fade_out_and_stop();
+        assert!(player.fade.is_none());
+    }
+
+    #[test]
+    fn finished_tracks_advance_and_reshuffle() {
+        let (mut player, sink) = player(&["a.mp3", "b.mp3"]);
+        player.play();
+        let first = player.current_track().cloned().unwrap();
+
+        sink.lock().unwrap().finished = true;
+        player.tick();
+        let second = player.current_track().cloned().unwrap();
+        assert_ne!(first, second);
+        assert_eq!(sink.lock().unwrap().current.as_ref(), Some(&second));
+
+        // After the last track the playlist starts over in a new order
+        sink.lock().unwrap().finished = true;
+        player.tick();
+        assert_eq!(player.position, 0);
+        assert_eq!(player.now_playing().unwrap().state, PlaybackState::Playing);
+    }
+
+    #[test]
+    fn threaded_sink_forwards_commands() {
+        let sink = SharedSink::default();
+        let state = sink.0.clone();
+        let mut threaded = ThreadedSink::spawn(Box::new(sink));
+        assert!(!threaded.is_finished());
+
+        let wait_for = |done: &dyn Fn() -> bool| {
+            let started = Instant::now();
+            while !done() {
+                assert!(started.elapsed() < Duration::from_secs(5), "timed out");
+                std::thread::sleep(Duration::from_millis(20));
+            }
+        };
+
+        threaded.play(Path::new("a.mp3")).unwrap();
+        threaded.set_volume(0.5).unwrap();
+        wait_for(&|| state.lock().unwrap().volume == 0.5);
+        assert_eq!(state.lock().unwrap().current.as_deref(), Some(Path::new("a.mp3")));
+
+        state.lock().unwrap().finished = true;
+        let finished = threaded.finished.clone();
+        wait_for(&|| finished.load(Ordering::SeqCst) == 1);
+        assert!(threaded.is_finished());
+
+        // A new track is not finished just because the previous one was
+        threaded.play(Path::new("b.mp3")).unwrap();
+        assert!(!threaded.is_finished());
+    }
+
+    #[test]
+    fn scan_survives_symlink_loops() {
+        let dir = std::env::temp_dir().join(format!("rizeclone-music-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("album")).unwrap();
+        std::fs::write(dir.join("album/one.MP3"), b"").unwrap();
+        std::fs::write(dir.join("album/cover.jpg"), b"").unwrap();
+        std::fs::write(dir.join("two.flac"), b"").unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(&dir, dir.join("album/loop")).unwrap();
+
+        let (mut player, _) = player(&[]);
+        assert_eq!(player.scan(&dir).unwrap(), 2);
+        assert!(player.scan(&dir.join("missing")).is_err());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
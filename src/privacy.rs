@@ -0,0 +1,107 @@
+use crate::config::Config;
+use log::warn;
+use regex::Regex;
+
+/// Decides what the tracker is allowed to record about a window. Everything
+/// captured goes through this before it is logged, stored or exported.
+pub struct PrivacyFilter {
+    track_window_titles: bool,
+    excluded_apps: Vec<String>, // lowercase
+    redaction_patterns: Vec<Regex>,
+}
+
+impl PrivacyFilter {
+    pub fn new() -> Self {
+        Self {
+            track_window_titles: true,
+            excluded_apps: Vec::new(),
+            redaction_patterns: Vec::new(),
+        }
+    }
+
+    pub fn from_config(config: &Config) -> Self {
+        let redaction_patterns = config
+            .title_redaction_patterns
+            .iter()
+            .filter_map(|pattern| match Regex::new(pattern) {
+                Ok(regex) => Some(regex),
+                Err(_) => {
+                    // The regex error quotes the pattern, which may itself be sensitive
+                    warn!("Ignoring invalid title redaction pattern");
+                    None
+                }
+            })
+            .collect();
+
+        Self {
+            track_window_titles: config.track_window_titles,
+            excluded_apps: config
+                .excluded_apps
+                .iter()
+                .map(|app| app.to_lowercase())
+                .collect(),
+            redaction_patterns,
+        }
+    }
+
+    /// Excluded apps are not tracked at all: no durations, intervals or logs.
+    pub fn is_excluded(&self, app_name: &str) -> bool {
+        let app = app_name.to_lowercase();
+        self.excluded_apps.iter().any(|excluded| app.contains(excluded.as_str()))
+    }
+
+    pub fn is_redacted(&self, title: &str) -> bool {
+        self.redaction_patterns.iter().any(|regex| regex.is_match(title))
+    }
+
+    pub fn tracks_window_titles(&self) -> bool {
+        self.track_window_titles
+    }
+
+    /// Returns the title that may be recorded, or `None` when titles are not
+    /// tracked or this one matches a redaction pattern.
+    pub fn filter_title(&self, title: Option<String>) -> Option<String> {
+        if !self.track_window_titles {
+            return None;
+        }
+        title.filter(|title| !self.is_redacted(title))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(track_window_titles: bool) -> PrivacyFilter {
+        let config = Config {
+            track_window_titles,
+            excluded_apps: vec!["KeePass".to_string()],
+            title_redaction_patterns: vec!["(?i)bank".to_string(), "(".to_string()],
+            ..Default::default()
+        };
+        PrivacyFilter::from_config(&config)
+    }
+
+    #[test]
+    fn excluded_apps_match_case_insensitively() {
+        let privacy = filter(true);
+        assert!(privacy.is_excluded("KeePassXC"));
+        assert!(privacy.is_excluded("keepass"));
+        assert!(!privacy.is_excluded("firefox"));
+    }
+
+    #[test]
+    fn matching_titles_are_redacted_and_invalid_patterns_ignored() {
+        let privacy = filter(true);
+        assert_eq!(privacy.filter_title(Some("My Bank - Firefox".to_string())), None);
+        assert_eq!(privacy.filter_title(Some("notes.md".to_string())).as_deref(), Some("notes.md"));
+        assert_eq!(privacy.filter_title(None), None);
+    }
+
+    #[test]
+    fn no_titles_are_kept_when_title_tracking_is_off() {
+        let privacy = filter(false);
+        assert!(!privacy.tracks_window_titles());
+        assert_eq!(privacy.filter_title(Some("notes.md".to_string())), None);
+    }
+}
@@ -1,9 +1,14 @@
+use crate::browser::{self, ActiveTabSource};
+use crate::config::Config;
+use crate::privacy::PrivacyFilter;
+use crate::project::ProjectResolver;
 use anyhow::Result;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, Utc};
 use log::{debug, info, warn};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use sysinfo::{System, Process};
+use std::path::PathBuf;
+use sysinfo::{Pid, System};
 use std::process::Command;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -13,6 +18,32 @@ pub struct AppInfo {
     pub duration: i64, // in seconds
     pub window_title: Option<String>,
     pub is_active: bool,
+    pub project: Option<String>,
+}
+
+/// A continuous stretch of time spent in one app, window and project.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ActivityInterval {
+    pub app_name: String,
+    pub window_title: Option<String>,
+    pub project: Option<String>,
+    #[serde(default)]
+    pub domain: Option<String>, // site shown in a browser window
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+}
+
+impl ActivityInterval {
+    pub fn duration(&self) -> chrono::Duration {
+        self.end - self.start
+    }
+}
+
+#[derive(Debug, Default)]
+struct FocusedWindow {
+    app_name: Option<String>,
+    window_title: Option<String>,
+    pid: Option<u32>,
 }
 
 pub struct ProcessTracker {
@@ -20,6 +51,14 @@ pub struct ProcessTracker {
     active_apps: HashMap<String, AppInfo>,
     last_update: DateTime<Utc>,
     current_focused: Option<String>,
+    current_pid: Option<u32>,
+    project_resolver: ProjectResolver,
+    privacy: PrivacyFilter,
+    project_durations: HashMap<String, Duration>,
+    active_tab_source: Option<ActiveTabSource>,
+    domain_durations: HashMap<String, Duration>,
+    current_interval: Option<ActivityInterval>,
+    completed_intervals: Vec<ActivityInterval>,
 }
 
 impl ProcessTracker {
@@ -30,21 +69,80 @@ impl ProcessTracker {
             active_apps: HashMap::new(),
             last_update: Utc::now(),
             current_focused: None,
+            current_pid: None,
+            project_resolver: ProjectResolver::new(),
+            privacy: PrivacyFilter::new(),
+            project_durations: HashMap::new(),
+            active_tab_source: None,
+            domain_durations: HashMap::new(),
+            current_interval: None,
+            completed_intervals: Vec::new(),
+        }
+    }
+
+    pub fn apply_config(&mut self, config: &Config) {
+        self.project_resolver = ProjectResolver::from_config(config);
+        self.active_tab_source = config.browser_tab_report.clone().map(ActiveTabSource::new);
+        self.privacy = PrivacyFilter::from_config(config);
+
+        // Scrub anything recorded before the current rules applied
+        let privacy = &self.privacy;
+        self.active_apps.retain(|name, _| !privacy.is_excluded(name));
+        for info in self.active_apps.values_mut() {
+            info.window_title = privacy.filter_title(info.window_title.take());
+        }
+        for interval in self.completed_intervals.iter_mut().chain(self.current_interval.as_mut()) {
+            if interval.window_title.as_deref().map(|title| privacy.is_redacted(title)).unwrap_or(false) {
+                interval.domain = None;
+            }
+            interval.window_title = privacy.filter_title(interval.window_title.take());
+        }
+        self.completed_intervals.retain(|interval| !privacy.is_excluded(&interval.app_name));
+        if self.current_interval.as_ref().map(|interval| privacy.is_excluded(&interval.app_name)).unwrap_or(false) {
+            self.current_interval = None;
         }
     }
 
     pub fn update(&mut self) -> Result<()> {
-        info!("Updating process tracker");
+        debug!("Updating process tracker");
         self.sys.refresh_all();
         let now = Utc::now();
-        let elapsed = (now - self.last_update).num_seconds();
+        // Ticks are far shorter than a second, so project and site totals add up
+        // the exact time while app totals keep their whole seconds
+        let elapsed_time = now - self.last_update;
+        let elapsed = elapsed_time.num_seconds();
         
-        // Get the currently focused window
-        let (focused_app, window_title) = self.get_focused_app();
-        info!("Current focused app: {:?}, Window title: {:?}", focused_app, window_title);
+        // Get the currently focused window and drop whatever privacy settings forbid
+        // before it is used anywhere else
+        let FocusedWindow { app_name: focused_app, window_title, pid } = self.get_focused_app();
+        let focused_app = focused_app.filter(|app_name| !self.privacy.is_excluded(app_name));
+        let redacted = window_title.as_deref().map(|title| self.privacy.is_redacted(title)).unwrap_or(false);
+        let window_title = focused_app.as_ref().and_then(|_| self.privacy.filter_title(window_title));
+        debug!("Current focused app: {:?}", focused_app);
+        
+        let project = focused_app.as_ref().and_then(|app_name| {
+            let cwd = pid.and_then(|pid| self.working_directory(pid));
+            self.project_resolver.resolve(app_name, window_title.as_deref(), cwd.as_deref())
+        });
+        if let Some(project) = &project {
+            *self.project_durations.entry(project.clone()).or_insert(Duration::zero()) += elapsed_time;
+        }
+        
+        let domain = focused_app
+            .as_deref()
+            .filter(|app_name| !redacted && browser::is_browser(app_name))
+            .and_then(|_| {
+                self.active_tab_source
+                    .as_ref()
+                    .and_then(|source| source.current_domain())
+                    .or_else(|| window_title.as_deref().and_then(browser::domain_from_title))
+            });
+        if let Some(domain) = &domain {
+            *self.domain_durations.entry(domain.clone()).or_insert(Duration::zero()) += elapsed_time;
+        }
         
         // Log current state of active apps
-        info!("Current active apps: {:?}", self.active_apps.keys().collect::<Vec<_>>());
+        debug!("Current active apps: {:?}", self.active_apps.keys().collect::<Vec<_>>());
         
         // Update durations for all tracked apps
         for (name, info) in self.active_apps.iter_mut() {
@@ -55,34 +153,186 @@ impl ProcessTracker {
                 if let Some(title) = &window_title {
                     info.window_title = Some(title.clone());
                 }
-                info!("Updated duration for {}: {} seconds (was active: {})", name, info.duration, was_active);
+                info.project = project.clone();
+                debug!("Updated duration for {}: {} seconds (was active: {})", name, info.duration, was_active);
             }
         }
         
         // Add new app if it's not tracked yet
         if let Some(app_name) = &focused_app {
             if !self.active_apps.contains_key(app_name) {
-                info!("Adding new app to track: {} with title: {:?}", app_name, window_title);
+                info!("Adding new app to track: {}", app_name);
                 self.active_apps.insert(
                     app_name.clone(),
                     AppInfo {
                         name: app_name.clone(),
                         start_time: now,
                         duration: 0,
-                        window_title,
+                        window_title: window_title.clone(),
                         is_active: true,
+                        project: project.clone(),
                     },
                 );
-                info!("Current active apps after adding: {:?}", self.active_apps.keys().collect::<Vec<_>>());
+                debug!("Current active apps after adding: {:?}", self.active_apps.keys().collect::<Vec<_>>());
             }
         }
         
+        self.update_interval(focused_app.as_deref(), window_title, project, domain, now);
+        self.current_pid = focused_app.as_ref().and(pid);
         self.current_focused = focused_app;
         self.last_update = now;
         Ok(())
     }
 
-    fn get_focused_app(&self) -> (Option<String>, Option<String>) {
+    // Extends the current interval, or closes it and opens a new one when the
+    // focused app, window title, project or site changed.
+    fn update_interval(
+        &mut self,
+        app_name: Option<&str>,
+        window_title: Option<String>,
+        project: Option<String>,
+        domain: Option<String>,
+        now: DateTime<Utc>,
+    ) {
+        if let Some(current) = &mut self.current_interval {
+            current.end = now;
+            let unchanged = Some(current.app_name.as_str()) == app_name
+                && current.window_title == window_title
+                && current.project == project
+                && current.domain == domain;
+            if unchanged {
+                return;
+            }
+        }
+
+        if let Some(finished) = self.current_interval.take() {
+            if finished.end > finished.start {
+                self.completed_intervals.push(finished);
+            }
+        }
+
+        self.current_interval = app_name.map(|app_name| ActivityInterval {
+            app_name: app_name.to_string(),
+            window_title,
+            project,
+            domain,
+            start: now,
+            end: now,
+        });
+    }
+
+    /// Closes the current interval, e.g. before shutting down.
+    pub fn finish_current_interval(&mut self) {
+        if let Some(mut current) = self.current_interval.take() {
+            current.end = Utc::now();
+            if current.end > current.start {
+                self.completed_intervals.push(current);
+            }
+        }
+    }
+
+    /// Drops not yet stored intervals that overlap `start..end`. Live totals
+    /// cannot be split by time, so they are reset when the range reaches now.
+    pub fn forget_range(&mut self, start: DateTime<Utc>, end: DateTime<Utc>) {
+        let overlaps = |interval: &ActivityInterval| interval.start < end && interval.end > start;
+        self.completed_intervals.retain(|interval| !overlaps(interval));
+        if self.current_interval.as_ref().map(overlaps).unwrap_or(false) {
+            self.current_interval = None;
+        }
+
+        if end > Utc::now() {
+            self.active_apps.clear();
+            self.project_durations.clear();
+            self.domain_durations.clear();
+        }
+    }
+
+    /// The interval of the app currently in focus, if it is tracked.
+    pub fn current_interval(&self) -> Option<&ActivityInterval> {
+        self.current_interval.as_ref()
+    }
+
+    /// The process owning the focused window, if it is tracked.
+    pub fn current_pid(&self) -> Option<u32> {
+        self.current_pid
+    }
+
+    /// Minimizes the focused window. Sway has no minimized state, so the
+    /// window is moved to the scratchpad instead.
+    pub fn minimize_focused_window(&self) -> Result<()> {
+        #[cfg(target_os = "linux")]
+        {
+            if let Some(pid) = self.current_pid {
+                let moved = Command::new("swaymsg")
+                    .arg(format!("[pid={}] move scratchpad", pid))
+                    .output()
+                    .map(|output| output.status.success())
+                    .unwrap_or(false);
+                if moved {
+                    return Ok(());
+                }
+            }
+
+            let status = Command::new("xdotool")
+                .args(["getactivewindow", "windowminimize"])
+                .status()?;
+            if !status.success() {
+                anyhow::bail!("xdotool failed to minimize the window");
+            }
+        }
+
+        #[cfg(target_os = "macos")]
+        {
+            let status = Command::new("osascript")
+                .args(["-e", "tell application \"System Events\" to set visible of first process where it is frontmost to false"])
+                .status()?;
+            if !status.success() {
+                anyhow::bail!("osascript failed to hide the app");
+            }
+        }
+
+        #[cfg(target_os = "windows")]
+        {
+            use windows::Win32::UI::WindowsAndMessaging::{GetForegroundWindow, ShowWindow, SW_MINIMIZE};
+
+            unsafe {
+                ShowWindow(GetForegroundWindow(), SW_MINIMIZE);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the intervals that ended since the last call.
+    pub fn take_completed_intervals(&mut self) -> Vec<ActivityInterval> {
+        std::mem::take(&mut self.completed_intervals)
+    }
+
+    // Terminals report their own cwd, so prefer the most recently started
+    // descendant (usually the shell or the command running in it).
+    fn working_directory(&self, pid: u32) -> Option<PathBuf> {
+        let root = Pid::from_u32(pid);
+        let processes = self.sys.processes();
+        let mut current = root;
+        loop {
+            let child = processes
+                .values()
+                .filter(|process| process.parent() == Some(current))
+                .max_by_key(|process| process.start_time());
+            match child {
+                Some(child) => current = child.pid(),
+                None => break,
+            }
+        }
+
+        processes
+            .get(&current)
+            .and_then(|process| process.cwd())
+            .or_else(|| processes.get(&root).and_then(|process| process.cwd()))
+            .map(|cwd| cwd.to_path_buf())
+    }
+
+    fn get_focused_app(&self) -> FocusedWindow {
         #[cfg(target_os = "linux")]
         {
             // First try Wayland using swaymsg
@@ -91,18 +341,16 @@ impl ProcessTracker {
                 .output()
             {
                 if let Ok(output_str) = String::from_utf8(output.stdout) {
-                    info!("Sway tree: {}", output_str);
-                    
                     // Try to find the focused window
                     if let Ok(json) = serde_json::from_str::<serde_json::Value>(&output_str) {
                         if let Some(focused) = find_focused_window(&json) {
                             if let (Some(name), Some(title)) = (focused.get("name"), focused.get("title")) {
                                 let app_name = name.as_str().unwrap_or("").to_string();
                                 let window_title = title.as_str().unwrap_or("").to_string();
+                                let pid = focused.get("pid").and_then(|pid| pid.as_u64()).map(|pid| pid as u32);
                                 
                                 if !app_name.is_empty() {
-                                    info!("Found Wayland window: {} with title: {:?}", app_name, window_title);
-                                    return (Some(app_name), Some(window_title));
+                                    return FocusedWindow { app_name: Some(app_name), window_title: Some(window_title), pid };
                                 }
                             }
                         }
@@ -118,8 +366,6 @@ impl ProcessTracker {
             {
                 Ok(output) => {
                     if let Ok(output_str) = String::from_utf8(output.stdout) {
-                        info!("Window tree: {}", output_str);
-                        
                         // Find windows that have a name (visible windows)
                         for line in output_str.lines() {
                             if line.contains("has no name") {
@@ -128,16 +374,12 @@ impl ProcessTracker {
                             
                             // Extract window ID
                             if let Some(window_id) = line.split_whitespace().next() {
-                                info!("Found window: {}", line);
-                                
                                 // Get window properties using xprop
                                 if let Ok(xprop_output) = Command::new("xprop")
                                     .args(["-id", window_id])
                                     .output()
                                 {
                                     if let Ok(xprop_str) = String::from_utf8(xprop_output.stdout) {
-                                        info!("Window properties: {}", xprop_str);
-                                        
                                         // Check if window is visible and mapped
                                         let is_visible = xprop_str.contains("_NET_WM_STATE(ATOM)") && 
                                                        !xprop_str.contains("_NET_WM_STATE_HIDDEN");
@@ -149,8 +391,6 @@ impl ProcessTracker {
                                                 .and_then(|line| line.split('"').nth(1))
                                                 .map(|s| s.trim().to_string());
                                             
-                                            info!("Window title: {:?}", window_title);
-                                            
                                             // Get window class
                                             let window_class = xprop_str.lines()
                                                 .find(|line| line.contains("WM_CLASS"))
@@ -163,7 +403,10 @@ impl ProcessTracker {
                                                     }
                                                 });
                                             
-                                            info!("Window class: {:?}", window_class);
+                                            let pid = xprop_str.lines()
+                                                .find(|line| line.starts_with("_NET_WM_PID"))
+                                                .and_then(|line| line.split('=').nth(1))
+                                                .and_then(|pid| pid.trim().parse::<u32>().ok());
                                             
                                             // Use class name if available, otherwise use title
                                             if let Some(class) = &window_class {
@@ -177,8 +420,7 @@ impl ProcessTracker {
                                                         .to_string();
                                                     
                                                     if !clean_name.is_empty() {
-                                                        info!("Found window with class: {} and title: {:?}", clean_name, window_title);
-                                                        return (Some(clean_name), window_title);
+                                                        return FocusedWindow { app_name: Some(clean_name), window_title, pid };
                                                     }
                                                 }
                                             }
@@ -202,8 +444,7 @@ impl ProcessTracker {
                                                             .to_string();
                                                         
                                                         if !clean_name.is_empty() {
-                                                            info!("Found window with title: {} and class: {:?}", clean_name, window_class);
-                                                            return (Some(clean_name), Some(title));
+                                                            return FocusedWindow { app_name: Some(clean_name), window_title: Some(title), pid };
                                                         }
                                                     }
                                                 }
@@ -238,8 +479,7 @@ impl ProcessTracker {
                             .and_then(|output| String::from_utf8(output.stdout).ok())
                             .map(|title| title.trim().to_string());
                         
-                        info!("Found active process on macOS: {} with title: {:?}", name, window_title);
-                        return (Some(name.to_string()), window_title);
+                        return FocusedWindow { app_name: Some(name.to_string()), window_title, pid: None };
                     }
                 }
             }
@@ -268,20 +508,27 @@ impl ProcessTracker {
                             None
                         };
                         
-                        info!("Found active process on Windows: {} with title: {:?}", name, window_title);
-                        return (Some(name), window_title);
+                        return FocusedWindow { app_name: Some(name), window_title, pid: Some(process_id) };
                     }
                 }
             }
         }
         
         warn!("No active window found");
-        (None, None)
+        FocusedWindow::default()
     }
 
     pub fn get_active_apps(&self) -> &HashMap<String, AppInfo> {
         &self.active_apps
     }
+
+    pub fn get_project_durations(&self) -> &HashMap<String, Duration> {
+        &self.project_durations
+    }
+
+    pub fn get_domain_durations(&self) -> &HashMap<String, Duration> {
+        &self.domain_durations
+    }
 }
 
 // Helper function to find the focused window in the sway tree
@@ -303,4 +550,51 @@ fn find_focused_window(node: &serde_json::Value) -> Option<&serde_json::Value> {
     }
     
     None
-} 
\ No newline at end of file
+} 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn interval(app_name: &str, title: &str, domain: Option<&str>) -> ActivityInterval {
+        let start = Utc::now() - Duration::minutes(10);
+        ActivityInterval {
+            app_name: app_name.to_string(),
+            window_title: Some(title.to_string()),
+            project: None,
+            domain: domain.map(str::to_string),
+            start,
+            end: start + Duration::minutes(5),
+        }
+    }
+
+    #[test]
+    fn apply_config_scrubs_intervals_recorded_under_earlier_rules() {
+        let mut tracker = ProcessTracker::new();
+        tracker.completed_intervals = vec![
+            interval("KeePassXC", "Passwords", None),
+            interval("firefox", "My Bank - Firefox", Some("bank.example")),
+            interval("editor", "notes.md", None),
+        ];
+        tracker.current_interval = Some(interval("keepassxc", "Passwords", None));
+
+        let config = Config {
+            excluded_apps: vec!["keepass".to_string()],
+            title_redaction_patterns: vec!["(?i)bank".to_string()],
+            ..Default::default()
+        };
+        tracker.apply_config(&config);
+        assert!(tracker.current_interval().is_none());
+        let kept: Vec<_> = tracker
+            .take_completed_intervals()
+            .into_iter()
+            .map(|interval| (interval.app_name, interval.window_title, interval.domain))
+            .collect();
+        assert_eq!(
+            kept,
+            [
+                ("firefox".to_string(), None, None),
+                ("editor".to_string(), Some("notes.md".to_string()), None),
+            ]
+        );
+    }
+}
@@ -0,0 +1,165 @@
+use crate::config::Config;
+use log::warn;
+use regex::Regex;
+use std::path::{Path, PathBuf};
+
+// Title suffixes used by VS Code style editors: "file.rs - myrepo - Visual Studio Code"
+const VSCODE_TITLE_SUFFIXES: [&str; 4] = ["Visual Studio Code", "VSCodium", "Cursor", "Code - OSS"];
+
+// JetBrains IDEs put the project first: "myrepo – src/main.rs"
+const JETBRAINS_APPS: [&str; 8] = [
+    "jetbrains", "idea", "pycharm", "clion", "rustrover", "goland", "webstorm", "rider",
+];
+
+pub struct ProjectResolver {
+    path_mappings: Vec<(PathBuf, String)>,
+    title_rules: Vec<(Regex, String)>,
+}
+
+impl ProjectResolver {
+    pub fn new() -> Self {
+        Self {
+            path_mappings: Vec::new(),
+            title_rules: Vec::new(),
+        }
+    }
+
+    pub fn from_config(config: &Config) -> Self {
+        let mut path_mappings: Vec<(PathBuf, String)> = config
+            .project_paths
+            .iter()
+            .map(|mapping| (mapping.path.clone(), mapping.project.clone()))
+            .collect();
+        // Longest paths first so nested mappings win over their parents
+        path_mappings.sort_by_key(|(path, _)| std::cmp::Reverse(path.components().count()));
+
+        let title_rules = config
+            .project_title_rules
+            .iter()
+            .filter_map(|rule| match Regex::new(&rule.pattern) {
+                Ok(regex) => Some((regex, rule.project.clone())),
+                Err(e) => {
+                    warn!("Ignoring invalid project title rule {:?}: {}", rule.pattern, e);
+                    None
+                }
+            })
+            .collect();
+
+        Self {
+            path_mappings,
+            title_rules,
+        }
+    }
+
+    /// Resolves the project for a focused window. User title rules win, then
+    /// editor titles, then the working directory (explicit mappings before git roots).
+    pub fn resolve(
+        &self,
+        app_name: &str,
+        window_title: Option<&str>,
+        cwd: Option<&Path>,
+    ) -> Option<String> {
+        if let Some(title) = window_title {
+            for (regex, project) in &self.title_rules {
+                if regex.is_match(title) {
+                    return Some(project.clone());
+                }
+            }
+
+            if let Some(name) = editor_project_name(app_name, title) {
+                return Some(self.project_for_name(&name));
+            }
+        }
+
+        let cwd = cwd?;
+        if let Some(project) = self.project_for_path(cwd) {
+            return Some(project);
+        }
+
+        let root = find_git_root(cwd)?;
+        self.project_for_path(&root)
+            .or_else(|| root.file_name().map(|name| name.to_string_lossy().to_string()))
+    }
+
+    fn project_for_path(&self, path: &Path) -> Option<String> {
+        self.path_mappings
+            .iter()
+            .find(|(prefix, _)| path.starts_with(prefix))
+            .map(|(_, project)| project.clone())
+    }
+
+    // Editor titles only carry the folder name, so map it through any path
+    // mapping whose directory has the same name.
+    fn project_for_name(&self, name: &str) -> String {
+        self.path_mappings
+            .iter()
+            .find(|(path, _)| path.file_name().map(|n| n == name).unwrap_or(false))
+            .map(|(_, project)| project.clone())
+            .unwrap_or_else(|| name.to_string())
+    }
+}
+
+/// Walks up from `path` looking for the directory that contains `.git`.
+pub fn find_git_root(path: &Path) -> Option<PathBuf> {
+    path.ancestors()
+        .find(|dir| dir.join(".git").exists())
+        .map(|dir| dir.to_path_buf())
+}
+
+fn editor_project_name(app_name: &str, title: &str) -> Option<String> {
+    let title = title.trim_start_matches('●').trim();
+
+    if let Some(suffix) = VSCODE_TITLE_SUFFIXES
+        .iter()
+        .find(|suffix| title.ends_with(*suffix))
+    {
+        let rest = title[..title.len() - suffix.len()].trim_end();
+        let rest = rest.strip_suffix('-')?.trim_end();
+        // Remote windows append e.g. " [SSH: host]" to the folder name
+        let rest = match rest.rfind(" [") {
+            Some(idx) if rest.ends_with(']') => &rest[..idx],
+            _ => rest,
+        };
+        let folder = rest.rsplit(" - ").next()?.trim();
+        return if folder.is_empty() {
+            None
+        } else {
+            Some(folder.to_string())
+        };
+    }
+
+    let app = app_name.to_lowercase();
+    if JETBRAINS_APPS.iter().any(|name| app.contains(name)) {
+        let project = title.split(" – ").next()?.trim();
+        if !project.is_empty() && project != title {
+            return Some(project.to_string());
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vscode_titles_name_the_folder() {
+        let name = |title| editor_project_name("code", title);
+        assert_eq!(name("main.rs - rizeclone - Visual Studio Code").as_deref(), Some("rizeclone"));
+        assert_eq!(name("● main.rs - rizeclone - Visual Studio Code").as_deref(), Some("rizeclone"));
+        assert_eq!(name("rizeclone - Cursor").as_deref(), Some("rizeclone"));
+        assert_eq!(name("lib.rs - api [SSH: build-host] - VSCodium").as_deref(), Some("api"));
+        assert_eq!(name("Visual Studio Code"), None);
+    }
+
+    #[test]
+    fn jetbrains_titles_start_with_the_project() {
+        assert_eq!(
+            editor_project_name("rustrover", "rizeclone – src/main.rs").as_deref(),
+            Some("rizeclone")
+        );
+        assert_eq!(editor_project_name("rustrover", "Welcome to RustRover"), None);
+        assert_eq!(editor_project_name("firefox", "rizeclone – src/main.rs"), None);
+    }
+}
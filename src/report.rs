@@ -0,0 +1,143 @@
+use crate::calendar::{Calendar, Journal, SessionFilter};
+use crate::utils::format_duration;
+use chrono::{Duration, NaiveDate};
+use std::collections::HashMap;
+
+pub struct Report {
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+    pub app_totals: Vec<(String, Duration)>,
+    pub project_totals: Vec<(String, Duration)>,
+    pub domain_totals: Vec<(String, Duration)>,
+    pub tag_totals: Vec<(String, Duration)>, // focus time per session tag
+    pub focus_time: Duration,
+    pub focus_sessions: usize,
+    pub interruptions: u32,
+    pub average_quality: Option<u8>, // weighted by session length
+    pub journal: Vec<(NaiveDate, Journal)>,
+}
+
+impl Report {
+    /// Builds the report for `start..=end`. Focus figures only count the
+    /// sessions that match `filter`.
+    pub fn generate(calendar: &Calendar, start: NaiveDate, end: NaiveDate, filter: &SessionFilter) -> Self {
+        let mut apps: HashMap<String, Duration> = HashMap::new();
+        let mut projects: HashMap<String, Duration> = HashMap::new();
+        let mut domains: HashMap<String, Duration> = HashMap::new();
+        let mut tags: HashMap<String, Duration> = HashMap::new();
+        let mut focus_time = Duration::zero();
+        let mut focus_sessions = 0;
+        let mut interruptions = 0;
+        let mut quality_weighted = 0.0;
+        let mut quality_seconds = 0.0;
+
+        for activity in calendar.activities_in_range(start, end) {
+            for (app, duration) in &activity.process_durations {
+                *apps.entry(app.clone()).or_insert(Duration::zero()) += *duration;
+            }
+            for (project, duration) in &activity.project_durations {
+                *projects.entry(project.clone()).or_insert(Duration::zero()) += *duration;
+            }
+            for (domain, duration) in &activity.domain_durations {
+                *domains.entry(domain.clone()).or_insert(Duration::zero()) += *duration;
+            }
+            for session in activity.focus_sessions.iter().filter(|session| filter.matches(session)) {
+                for tag in &session.tags {
+                    *tags.entry(tag.clone()).or_insert(Duration::zero()) += session.duration;
+                }
+                focus_time += session.duration;
+                focus_sessions += 1;
+                interruptions += session.interruptions;
+                if let Some(score) = session.quality_score {
+                    let seconds = session.duration.num_seconds() as f64;
+                    quality_weighted += score as f64 * seconds;
+                    quality_seconds += seconds;
+                }
+            }
+        }
+
+        Self {
+            start,
+            end,
+            app_totals: sorted_totals(apps),
+            project_totals: sorted_totals(projects),
+            domain_totals: sorted_totals(domains),
+            tag_totals: sorted_totals(tags),
+            focus_time,
+            focus_sessions,
+            interruptions,
+            average_quality: (quality_seconds > 0.0).then(|| (quality_weighted / quality_seconds).round() as u8),
+            journal: calendar
+                .journals_in_range(start, end)
+                .into_iter()
+                .map(|(day, journal)| (day, journal.clone()))
+                .collect(),
+        }
+    }
+
+    pub fn total_time(&self) -> Duration {
+        self.app_totals.iter().map(|(_, duration)| *duration).sum()
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = format!("Report {} – {}\n", self.start, self.end);
+        text.push_str(&format!("Total tracked: {}\n", format_duration(self.total_time())));
+        text.push_str(&format!(
+            "Focus: {} in {} sessions\n",
+            format_duration(self.focus_time),
+            self.focus_sessions
+        ));
+        if let Some(score) = self.average_quality {
+            text.push_str(&format!(
+                "Focus quality: {}/100, {} interruptions\n",
+                score, self.interruptions
+            ));
+        }
+
+        if !self.tag_totals.is_empty() {
+            text.push_str("\nFocus by tag:\n");
+            for (tag, duration) in &self.tag_totals {
+                text.push_str(&format!("  #{:<29} {}\n", tag, format_duration(*duration)));
+            }
+        }
+
+        text.push_str("\nProjects:\n");
+        for (project, duration) in &self.project_totals {
+            text.push_str(&format!("  {:<30} {}\n", project, format_duration(*duration)));
+        }
+
+        text.push_str("\nWebsites:\n");
+        for (domain, duration) in &self.domain_totals {
+            text.push_str(&format!("  {:<30} {}\n", domain, format_duration(*duration)));
+        }
+
+        text.push_str("\nApplications:\n");
+        for (app, duration) in &self.app_totals {
+            text.push_str(&format!("  {:<30} {}\n", app, format_duration(*duration)));
+        }
+
+        if !self.journal.is_empty() {
+            text.push_str("\nJournal:\n");
+            for (day, journal) in &self.journal {
+                text.push_str(&format!("  {}\n", day));
+                for line in journal.note.lines().filter(|line| !line.trim().is_empty()) {
+                    text.push_str(&format!("    {}\n", line));
+                }
+                for highlight in &journal.went_well {
+                    text.push_str(&format!("    + {}\n", highlight));
+                }
+                for blocker in &journal.blockers {
+                    text.push_str(&format!("    - {}\n", blocker));
+                }
+            }
+        }
+
+        text
+    }
+}
+
+fn sorted_totals(totals: HashMap<String, Duration>) -> Vec<(String, Duration)> {
+    let mut totals: Vec<_> = totals.into_iter().collect();
+    totals.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    totals
+}
@@ -0,0 +1,344 @@
+use crate::calendar::{Calendar, DailyActivity};
+use crate::config::{Config, Encryption};
+use crate::crypto::{self, DataKey};
+use crate::utils::ensure_directory;
+use anyhow::{anyhow, Context, Result};
+use chrono::Utc;
+use log::{info, warn};
+use std::path::{Path, PathBuf};
+
+const PLAIN_EXTENSION: &str = "json";
+const ENCRYPTED_EXTENSION: &str = "json.enc";
+const CORRUPT_DIR: &str = "activity-corrupt";
+
+/// Persists the calendar as one file per day under `<data_dir>/activity`,
+/// encrypted when a key is set.
+pub struct ActivityStore {
+    data_dir: PathBuf,
+    dir: PathBuf,
+    key: Option<DataKey>,
+    encryption: Encryption,
+}
+
+impl ActivityStore {
+    pub fn open(data_dir: &Path, key: Option<DataKey>, encryption: Encryption) -> Result<Self> {
+        let dir = data_dir.join("activity");
+        ensure_directory(&dir)?;
+        Ok(Self {
+            data_dir: data_dir.to_path_buf(),
+            dir,
+            key,
+            encryption,
+        })
+    }
+
+    /// Opens the store according to `Config::encryption`. `passphrase` is only
+    /// used in passphrase mode.
+    pub fn unlock(config: &Config, passphrase: Option<&str>) -> Result<Self> {
+        let key = match config.encryption {
+            Encryption::Off => None,
+            Encryption::Passphrase => {
+                let passphrase = passphrase.ok_or_else(|| anyhow!("A passphrase is required"))?;
+                Some(DataKey::from_passphrase(&config.data_dir, passphrase)?)
+            }
+            Encryption::Keyring => Some(DataKey::from_keyring(&config.data_dir)?),
+        };
+        Self::open(&config.data_dir, key, config.encryption)
+    }
+
+    /// The mode the stored days are protected with.
+    pub fn encryption(&self) -> Encryption {
+        self.encryption
+    }
+
+    /// Rewrites every day under a new key for `encryption`, so switching modes
+    /// never leaves data behind that the new mode cannot read. `calendar` must
+    /// hold the whole loaded history. The days are written to a new directory
+    /// that replaces the old one once all of them are written.
+    pub fn change_encryption(
+        &self,
+        encryption: Encryption,
+        passphrase: Option<&str>,
+        calendar: &mut Calendar,
+    ) -> Result<Self> {
+        let key = match encryption {
+            Encryption::Off => None,
+            Encryption::Passphrase => {
+                let passphrase = passphrase.ok_or_else(|| anyhow!("A passphrase is required"))?;
+                Some(DataKey::generate_from_passphrase(passphrase)?)
+            }
+            Encryption::Keyring => Some(DataKey::generate_in_keyring()?),
+        };
+
+        let staging_dir = self.data_dir.join("activity.new");
+        if staging_dir.exists() {
+            std::fs::remove_dir_all(&staging_dir)?;
+        }
+        ensure_directory(&staging_dir)?;
+        let staging = Self {
+            data_dir: self.data_dir.clone(),
+            dir: staging_dir,
+            key,
+            encryption,
+        };
+        calendar.mark_all_dirty();
+        if let Err(e) = staging.save(calendar).and_then(|_| self.replace_with(&staging)) {
+            calendar.mark_all_dirty();
+            return Err(e.context("Failed to re-encrypt activity history"));
+        }
+
+        info!("Re-encrypted activity history as {:?}", encryption);
+        Ok(Self {
+            dir: self.dir.clone(),
+            ..staging
+        })
+    }
+
+    // Swaps in the days written by `staging` and records its key
+    fn replace_with(&self, staging: &Self) -> Result<()> {
+        let old_dir = self.data_dir.join("activity.old");
+        if old_dir.exists() {
+            std::fs::remove_dir_all(&old_dir)?;
+        }
+        std::fs::rename(&self.dir, &old_dir)?;
+        std::fs::rename(&staging.dir, &self.dir)?;
+        match &staging.key {
+            Some(key) => key.write_key_file(&self.data_dir)?,
+            None => crypto::remove_key_file(&self.data_dir)?,
+        }
+
+        // Encrypted days are not loaded while encryption is off, keep them
+        for entry in std::fs::read_dir(&old_dir)? {
+            let path = entry?.path();
+            if let Some(file_name) = path.file_name().and_then(|name| name.to_str()) {
+                if self.key.is_none() && file_name.ends_with(ENCRYPTED_EXTENSION) {
+                    self.set_aside(&path, file_name);
+                }
+            }
+        }
+        std::fs::remove_dir_all(&old_dir)?;
+        Ok(())
+    }
+
+    pub fn load_calendar(&self) -> Result<Calendar> {
+        let mut calendar = Calendar::new();
+
+        for entry in std::fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            let file_name = match path.file_name().and_then(|name| name.to_str()) {
+                Some(name) => name.to_string(),
+                None => continue,
+            };
+
+            let (date_key, encrypted) = if let Some(key) = file_name.strip_suffix(&format!(".{}", ENCRYPTED_EXTENSION)) {
+                (key.to_string(), true)
+            } else if let Some(key) = file_name.strip_suffix(&format!(".{}", PLAIN_EXTENSION)) {
+                (key.to_string(), false)
+            } else {
+                continue;
+            };
+
+            if !encrypted || self.key.is_some() {
+                match self.read_day(&path, encrypted) {
+                    Ok(activity) => calendar.load_day(date_key.clone(), activity),
+                    Err(e) => {
+                        // One unreadable day must not cost the rest of the history
+                        warn!("Skipping unreadable activity file {:?}: {:#}", path, e);
+                        self.set_aside(&path, &file_name);
+                        continue;
+                    }
+                }
+            } else {
+                warn!("Skipping encrypted activity file {:?}: encryption is turned off", path);
+                continue;
+            }
+
+            // Plaintext days are rewritten encrypted on the next save
+            if self.key.is_some() && !encrypted {
+                calendar.mark_dirty(&date_key);
+            }
+        }
+
+        info!("Loaded activity history from {:?}", self.dir);
+        Ok(calendar)
+    }
+
+    fn read_day(&self, path: &Path, encrypted: bool) -> Result<DailyActivity> {
+        let contents = std::fs::read(path)?;
+        let contents = match &self.key {
+            Some(key) if encrypted => key.decrypt(&contents)?,
+            _ => contents,
+        };
+        Ok(serde_json::from_slice(&contents)?)
+    }
+
+    // Moves a day that could not be read out of the way, so it is neither
+    // retried on every start nor overwritten by the next save
+    fn set_aside(&self, path: &Path, file_name: &str) {
+        let corrupt_dir = self.data_dir.join(CORRUPT_DIR);
+        let target = corrupt_dir.join(format!("{}.{}", file_name, Utc::now().timestamp()));
+        let result = ensure_directory(&corrupt_dir)
+            .and_then(|_| std::fs::rename(path, &target).context("rename failed"));
+        match result {
+            Ok(()) => warn!("Moved unreadable activity file to {:?}", target),
+            Err(e) => warn!("Failed to move unreadable activity file {:?}: {:#}", path, e),
+        }
+    }
+
+    /// Writes the days that changed since the last save.
+    pub fn save(&self, calendar: &mut Calendar) -> Result<()> {
+        for date_key in calendar.take_dirty_keys() {
+            match calendar.get_day_by_key(&date_key) {
+                Some(activity) => self.write_day(&date_key, activity)?,
+                None => self.remove_day(&date_key)?,
+            }
+        }
+        Ok(())
+    }
+
+    fn write_day(&self, date_key: &str, activity: &DailyActivity) -> Result<()> {
+        let json = serde_json::to_vec(activity)?;
+        let (contents, extension, stale_extension) = match &self.key {
+            Some(key) => (key.encrypt(&json)?, ENCRYPTED_EXTENSION, PLAIN_EXTENSION),
+            None => (json, PLAIN_EXTENSION, ENCRYPTED_EXTENSION),
+        };
+
+        // Write to a temporary file first so a crash never leaves a half-written day
+        let path = self.dir.join(format!("{}.{}", date_key, extension));
+        let tmp_path = self.dir.join(format!("{}.{}.tmp", date_key, extension));
+        std::fs::write(&tmp_path, contents)?;
+        std::fs::rename(&tmp_path, &path)?;
+
+        // A leftover copy in the other format would be loaded next to this one
+        self.discard_copy(date_key, stale_extension)
+    }
+
+    fn remove_day(&self, date_key: &str) -> Result<()> {
+        for extension in [PLAIN_EXTENSION, ENCRYPTED_EXTENSION] {
+            self.discard_copy(date_key, extension)?;
+        }
+        Ok(())
+    }
+
+    /// Deletes a copy of a day that was merged into the calendar; a copy that
+    /// was never loaded (encrypted while encryption is off) is set aside instead.
+    fn discard_copy(&self, date_key: &str, extension: &str) -> Result<()> {
+        let file_name = format!("{}.{}", date_key, extension);
+        let path = self.dir.join(&file_name);
+        if !path.exists() {
+            return Ok(());
+        }
+        if extension == ENCRYPTED_EXTENSION && self.key.is_none() {
+            self.set_aside(&path, &file_name);
+        } else {
+            std::fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process_tracker::ActivityInterval;
+    use chrono::{Duration, TimeZone};
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("rizeclone-storage-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn calendar_with_day() -> Calendar {
+        let start = Utc.with_ymd_and_hms(2024, 3, 5, 10, 0, 0).unwrap();
+        let mut calendar = Calendar::new();
+        calendar
+            .add_interval(ActivityInterval {
+                app_name: "editor".to_string(),
+                window_title: None,
+                project: None,
+                domain: None,
+                start,
+                end: start + Duration::minutes(30),
+            })
+            .unwrap();
+        calendar
+    }
+
+    fn file_names(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = std::fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn unreadable_day_is_set_aside() {
+        let data_dir = temp_dir("corrupt");
+        let store = ActivityStore::open(&data_dir, None, Encryption::Off).unwrap();
+        store.save(&mut calendar_with_day()).unwrap();
+        std::fs::write(data_dir.join("activity/2024-03-06.json"), b"{ not json").unwrap();
+
+        let calendar = store.load_calendar().unwrap();
+        assert!(calendar.get_day_by_key("2024-03-05").is_some());
+        assert_eq!(file_names(&data_dir.join("activity")), ["2024-03-05.json"]);
+        assert_eq!(file_names(&data_dir.join(CORRUPT_DIR)).len(), 1);
+        std::fs::remove_dir_all(data_dir).unwrap();
+    }
+
+    #[test]
+    fn writing_a_day_removes_the_loaded_plaintext_copy() {
+        let data_dir = temp_dir("stale");
+        let store = ActivityStore::open(&data_dir, None, Encryption::Off).unwrap();
+        store.save(&mut calendar_with_day()).unwrap();
+
+        let key = DataKey::generate_from_passphrase("secret").unwrap();
+        let store = ActivityStore::open(&data_dir, Some(key), Encryption::Passphrase).unwrap();
+        let mut calendar = store.load_calendar().unwrap();
+        store.save(&mut calendar).unwrap();
+
+        assert_eq!(file_names(&data_dir.join("activity")), ["2024-03-05.json.enc"]);
+        assert!(!data_dir.join(CORRUPT_DIR).exists());
+        std::fs::remove_dir_all(data_dir).unwrap();
+    }
+
+    #[test]
+    fn encrypted_day_survives_a_plaintext_save() {
+        let data_dir = temp_dir("unloaded");
+        let store = ActivityStore::open(&data_dir, None, Encryption::Off).unwrap();
+        std::fs::write(data_dir.join("activity/2024-03-05.json.enc"), b"sealed").unwrap();
+        assert!(store.load_calendar().unwrap().get_day_by_key("2024-03-05").is_none());
+        store.save(&mut calendar_with_day()).unwrap();
+
+        assert_eq!(file_names(&data_dir.join("activity")), ["2024-03-05.json"]);
+        let set_aside = file_names(&data_dir.join(CORRUPT_DIR));
+        assert_eq!(set_aside.len(), 1);
+        assert!(set_aside[0].starts_with("2024-03-05.json.enc."));
+        let contents = std::fs::read(data_dir.join(CORRUPT_DIR).join(&set_aside[0])).unwrap();
+        assert_eq!(contents, b"sealed");
+        std::fs::remove_dir_all(data_dir).unwrap();
+    }
+
+    #[test]
+    fn changing_encryption_rewrites_every_day() {
+        let data_dir = temp_dir("reencrypt");
+        let store = ActivityStore::open(&data_dir, None, Encryption::Off).unwrap();
+        let mut calendar = calendar_with_day();
+        store.save(&mut calendar).unwrap();
+
+        let store = store
+            .change_encryption(Encryption::Passphrase, Some("secret"), &mut calendar)
+            .unwrap();
+        assert_eq!(file_names(&data_dir.join("activity")), ["2024-03-05.json.enc"]);
+        let key = DataKey::from_passphrase(&data_dir, "secret").unwrap();
+        let reopened = ActivityStore::open(&data_dir, Some(key), Encryption::Passphrase).unwrap();
+        assert!(reopened.load_calendar().unwrap().get_day_by_key("2024-03-05").is_some());
+
+        store.change_encryption(Encryption::Off, None, &mut calendar).unwrap();
+        assert_eq!(file_names(&data_dir.join("activity")), ["2024-03-05.json"]);
+        assert!(!data_dir.join("encryption.json").exists());
+        std::fs::remove_dir_all(data_dir).unwrap();
+    }
+}
@@ -0,0 +1,212 @@
+use crate::calendar::{DailyActivity, DayModel, PlannedEvent};
+use crate::category::CategoryResolver;
+use crate::charts::category_color;
+use crate::process_tracker::ActivityInterval;
+use crate::utils::format_short_duration;
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use eframe::egui;
+
+const ROW_HEIGHT: f32 = 18.0;
+const LABEL_WIDTH: f32 = 44.0;
+const FOCUS_BAR_HEIGHT: f32 = 4.0;
+
+/// Hour-by-hour view of one day: a band per hour with a block for every
+/// tracked interval, colored by category (or app), focus sessions marked
+/// underneath, planned events outlined and untracked time left empty.
+pub fn show_day_timeline(
+    ui: &mut egui::Ui,
+    activity: &DailyActivity,
+    planned: &[&PlannedEvent],
+    day: NaiveDate,
+    day_model: &DayModel,
+    categories: &CategoryResolver,
+) {
+    if activity.compacted {
+        ui.label("Detailed intervals were removed by the retention policy.");
+        return;
+    }
+
+    let focus_color = ui.visuals().selection.bg_fill;
+    let idle_color = ui.visuals().faint_bg_color;
+    let planned_stroke = egui::Stroke::new(1.5, ui.visuals().warn_fg_color);
+
+    for row in hour_rows(day, day_model) {
+        ui.horizontal(|ui| {
+            ui.add_sized(
+                [LABEL_WIDTH, ROW_HEIGHT],
+                egui::Label::new(day_model.local_time(row.start).format("%H:%M").to_string()),
+            );
+
+            let width = ui.available_width();
+            let (rect, response) = ui.allocate_exact_size(egui::vec2(width, ROW_HEIGHT), egui::Sense::hover());
+            let painter = ui.painter_at(rect);
+            painter.rect_filled(rect, 2.0, idle_color);
+
+            let x_of = |fraction: f32| rect.left() + fraction * rect.width();
+
+            let mut hovered = None;
+            for interval in &activity.intervals {
+                let (from, to) = match row.span(interval.start, interval.end) {
+                    Some(span) => span,
+                    None => continue,
+                };
+                let block = egui::Rect::from_x_y_ranges(
+                    x_of(from)..=x_of(to).max(x_of(from) + 1.0),
+                    rect.top()..=rect.bottom() - FOCUS_BAR_HEIGHT,
+                );
+                let category = categories.categorize(&interval.app_name, interval.domain.as_deref());
+                painter.rect_filled(block, 0.0, category_color(category.unwrap_or(&interval.app_name)));
+
+                if response.hover_pos().map(|pos| block.contains(pos)).unwrap_or(false) {
+                    hovered = Some((interval, category.map(str::to_string)));
+                }
+            }
+
+            for session in &activity.focus_sessions {
+                if let Some((from, to)) = row.span(session.start_time, session.start_time + session.duration) {
+                    let bar = egui::Rect::from_x_y_ranges(
+                        x_of(from)..=x_of(to),
+                        rect.bottom() - FOCUS_BAR_HEIGHT..=rect.bottom(),
+                    );
+                    painter.rect_filled(bar, 0.0, focus_color);
+                }
+            }
+
+            for event in planned {
+                if let Some((from, to)) = row.span(event.start, event.end) {
+                    let outline = egui::Rect::from_x_y_ranges(x_of(from)..=x_of(to), rect.y_range());
+                    painter.rect_stroke(outline.shrink(0.75), 2.0, planned_stroke);
+                }
+            }
+
+            if let Some((interval, category)) = hovered {
+                response.on_hover_ui_at_pointer(|ui| interval_tooltip(ui, interval, category, day_model));
+            }
+        });
+    }
+
+    ui.horizontal(|ui| {
+        let (rect, _) = ui.allocate_exact_size(egui::vec2(16.0, FOCUS_BAR_HEIGHT), egui::Sense::hover());
+        ui.painter().rect_filled(rect, 0.0, focus_color);
+        ui.label("Focus session");
+        let (rect, _) = ui.allocate_exact_size(egui::vec2(16.0, 10.0), egui::Sense::hover());
+        ui.painter().rect_stroke(rect.shrink(0.75), 2.0, planned_stroke);
+        ui.label("Planned");
+        let (rect, _) = ui.allocate_exact_size(egui::vec2(16.0, 10.0), egui::Sense::hover());
+        ui.painter().rect_filled(rect, 0.0, idle_color);
+        ui.label("Idle or untracked");
+    });
+}
+
+/// One band of the timeline: an hour of real time from the start of the
+/// day, the last one cut short at the end of the day. DST days have 23 or 25.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct HourRow {
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+}
+
+impl HourRow {
+    /// The part of `start..end` inside this row, as fractions of its width.
+    fn span(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Option<(f32, f32)> {
+        if end <= self.start || start >= self.end {
+            return None;
+        }
+        let length = (self.end - self.start).num_seconds().max(1) as f32;
+        let fraction = |time: DateTime<Utc>| ((time - self.start).num_seconds() as f32 / length).clamp(0.0, 1.0);
+        Some((fraction(start), fraction(end)))
+    }
+}
+
+fn hour_rows(day: NaiveDate, day_model: &DayModel) -> Vec<HourRow> {
+    let day_end = day_model.end_of(day);
+    let mut rows = Vec::new();
+    let mut start = day_model.start_of(day);
+    while start < day_end {
+        let end = (start + Duration::hours(1)).min(day_end);
+        rows.push(HourRow { start, end });
+        start = end;
+    }
+    rows
+}
+
+fn interval_tooltip(ui: &mut egui::Ui, interval: &ActivityInterval, category: Option<String>, day_model: &DayModel) {
+    ui.strong(&interval.app_name);
+    if let Some(title) = &interval.window_title {
+        ui.label(title);
+    }
+    if let Some(project) = &interval.project {
+        ui.label(format!("Project: {}", project));
+    }
+    if let Some(domain) = &interval.domain {
+        ui.label(format!("Website: {}", domain));
+    }
+    if let Some(category) = category {
+        ui.label(format!("Category: {}", category));
+    }
+    ui.label(format!(
+        "{} – {} ({})",
+        day_model.local_time(interval.start).format("%H:%M:%S"),
+        day_model.local_time(interval.end).format("%H:%M:%S"),
+        format_short_duration(interval.duration())
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use chrono::TimeZone;
+
+    fn berlin() -> DayModel {
+        let config = Config {
+            timezone: Some("Europe/Berlin".to_string()),
+            ..Config::default()
+        };
+        DayModel::from_config(&config)
+    }
+
+    fn labels(day: NaiveDate, day_model: &DayModel) -> Vec<String> {
+        hour_rows(day, day_model)
+            .iter()
+            .map(|row| day_model.local_time(row.start).format("%H").to_string())
+            .collect()
+    }
+
+    #[test]
+    fn dst_days_have_one_row_per_real_hour() {
+        let day_model = berlin();
+
+        let spring = NaiveDate::from_ymd_opt(2024, 3, 31).unwrap();
+        let rows = labels(spring, &day_model);
+        assert_eq!(rows.len(), 23);
+        assert_eq!(rows[..4], ["00", "01", "03", "04"]);
+
+        let autumn = NaiveDate::from_ymd_opt(2024, 10, 27).unwrap();
+        let rows = labels(autumn, &day_model);
+        assert_eq!(rows.len(), 25);
+        assert_eq!(rows[..5], ["00", "01", "02", "02", "03"]);
+        assert_eq!(rows.last().unwrap(), "23");
+
+        for day in [spring, autumn] {
+            let rows = hour_rows(day, &day_model);
+            assert_eq!(rows[0].start, day_model.start_of(day));
+            assert_eq!(rows.last().unwrap().end, day_model.end_of(day));
+            assert!(rows.windows(2).all(|pair| pair[0].end == pair[1].start));
+        }
+    }
+
+    #[test]
+    fn interval_across_a_row_boundary_is_split_between_the_rows() {
+        let day_model = berlin();
+        let rows = hour_rows(NaiveDate::from_ymd_opt(2024, 3, 5).unwrap(), &day_model);
+        // 10:30 to 11:15 local time
+        let start = Utc.with_ymd_and_hms(2024, 3, 5, 9, 30, 0).unwrap();
+        let end = start + Duration::minutes(45);
+
+        assert_eq!(rows[10].span(start, end), Some((0.5, 1.0)));
+        assert_eq!(rows[11].span(start, end), Some((0.0, 0.25)));
+        assert_eq!(rows[9].span(start, end), None);
+        assert_eq!(rows[12].span(start, end), None);
+    }
+}
@@ -1,5 +1,8 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use chrono::{DateTime, Duration, Utc};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use crossterm::terminal;
+use std::io::Write;
 use std::path::PathBuf;
 
 pub fn format_duration(duration: Duration) -> String {
@@ -14,6 +17,79 @@ pub fn format_duration(duration: Duration) -> String {
     }
 }
 
+/// Compact form for tight spaces such as calendar cells, e.g. "3h 20m".
+pub fn format_short_duration(duration: Duration) -> String {
+    let hours = duration.num_hours();
+    let minutes = duration.num_minutes() % 60;
+
+    if hours > 0 {
+        format!("{}h {:02}m", hours, minutes)
+    } else {
+        format!("{}m", minutes)
+    }
+}
+
+/// Shows a desktop notification. Failures are ignored, the GUI shows the
+/// same information. Returns at once, the command is waited for on its own
+/// thread.
+pub fn send_notification(summary: &str, body: &str) {
+    #[cfg(target_os = "linux")]
+    let child = std::process::Command::new("notify-send")
+        .args(["--app-name=RizeClone", summary, body])
+        .spawn();
+
+    #[cfg(target_os = "macos")]
+    let child = std::process::Command::new("osascript")
+        .args([
+            "-e",
+            &format!(
+                "display notification {:?} with title \"RizeClone\" subtitle {:?}",
+                body, summary
+            ),
+        ])
+        .spawn();
+
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    if let Ok(mut child) = child {
+        std::thread::spawn(move || child.wait());
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+    let _ = (summary, body);
+}
+
+/// Reads a line from the terminal without echoing it.
+pub fn read_passphrase(prompt: &str) -> Result<String> {
+    print!("{}", prompt);
+    std::io::stdout().flush()?;
+
+    terminal::enable_raw_mode()?;
+    let mut passphrase = String::new();
+    let result = loop {
+        match event::read() {
+            Ok(Event::Key(key)) if key.kind == KeyEventKind::Press => match key.code {
+                KeyCode::Enter => break Ok(()),
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    break Err(anyhow!("Cancelled"))
+                }
+                KeyCode::Esc => break Err(anyhow!("Cancelled")),
+                KeyCode::Backspace => {
+                    passphrase.pop();
+                }
+                KeyCode::Char(c) => passphrase.push(c),
+                _ => {}
+            },
+            Ok(_) => {}
+            Err(e) => break Err(e.into()),
+        }
+    };
+    terminal::disable_raw_mode()?;
+    println!();
+
+    result.map(|_| passphrase)
+}
+
+#[allow(dead_code)]
 pub fn get_window_title() -> Result<Option<String>> {
     // TODO: Implement cross-platform window title detection
     // This will require platform-specific code for each OS
@@ -27,6 +103,7 @@ pub fn ensure_directory(path: &PathBuf) -> Result<()> {
     Ok(())
 }
 
+#[allow(dead_code)]
 pub fn get_timestamp() -> DateTime<Utc> {
     Utc::now()
-} 
\ No newline at end of file
+}
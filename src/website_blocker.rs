@@ -0,0 +1,201 @@
+use crate::config::Config;
+use anyhow::{anyhow, Result};
+use log::info;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+const SECTION_BEGIN: &str = "# BEGIN rizeclone focus block";
+const SECTION_END: &str = "# END rizeclone focus block";
+
+/// Blocks websites during focus phases by pointing them at a local address
+/// in a marked section of the hosts file. Browsers may keep serving
+/// cached DNS entries for a minute or so after the block starts.
+///
+/// The section is the only state: whatever is left over after a crash is
+/// removed by the next `unblock`, which runs on startup.
+pub struct WebsiteBlocker {
+    hosts_path: PathBuf,
+    domains: Vec<String>,
+    helper: Vec<String>, // privileged command that writes stdin to the path given last
+}
+
+impl WebsiteBlocker {
+    pub fn from_config(config: &Config) -> Self {
+        let domains = if config.block_websites {
+            config
+                .blocked_websites
+                .iter()
+                .map(|domain| domain.trim().trim_start_matches("www.").to_lowercase())
+                .filter(|domain| !domain.is_empty())
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        Self {
+            hosts_path: config.hosts_file.clone(),
+            domains,
+            helper: config.hosts_helper.clone(),
+        }
+    }
+
+    pub fn block(&self) -> Result<()> {
+        if self.domains.is_empty() {
+            return Ok(());
+        }
+
+        let contents = std::fs::read_to_string(&self.hosts_path)?;
+        let mut blocked = strip_section(&contents);
+        if !blocked.is_empty() && !blocked.ends_with('\n') {
+            blocked.push('\n');
+        }
+        blocked.push_str(&self.section());
+
+        info!("Blocking {} websites for focus", self.domains.len());
+        self.write_hosts(&blocked)
+    }
+
+    /// Removes the block section if there is one.
+    pub fn unblock(&self) -> Result<()> {
+        let contents = match std::fs::read_to_string(&self.hosts_path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e.into()),
+        };
+        if !contents.contains(SECTION_BEGIN) {
+            return Ok(());
+        }
+
+        info!("Unblocking websites");
+        self.write_hosts(&strip_section(&contents))
+    }
+
+    fn section(&self) -> String {
+        let mut section = format!("{}\n", SECTION_BEGIN);
+        for domain in &self.domains {
+            for host in [domain.clone(), format!("www.{}", domain)] {
+                section.push_str(&format!("0.0.0.0 {}\n::1 {}\n", host, host));
+            }
+        }
+        section.push_str(SECTION_END);
+        section.push('\n');
+        section
+    }
+
+    // Replaces the file through a temporary copy when allowed, so a crash
+    // never leaves a truncated hosts file, otherwise writes through the
+    // privileged helper
+    fn write_hosts(&self, contents: &str) -> Result<()> {
+        match replace_file(&self.hosts_path, contents) {
+            Ok(()) => return Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied && !self.helper.is_empty() => {}
+            Err(e) => return Err(e.into()),
+        }
+
+        let mut child = Command::new(&self.helper[0])
+            .args(&self.helper[1..])
+            .arg(&self.hosts_path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .spawn()?;
+        child
+            .stdin
+            .take()
+            .ok_or_else(|| anyhow!("Failed to open helper stdin"))?
+            .write_all(contents.as_bytes())?;
+        let status = child.wait()?;
+        if !status.success() {
+            return Err(anyhow!("{} exited with {}", self.helper[0], status));
+        }
+        Ok(())
+    }
+}
+
+fn replace_file(path: &Path, contents: &str) -> std::io::Result<()> {
+    let permissions = std::fs::metadata(path)?.permissions();
+    let file_name = path.file_name().map(|name| name.to_string_lossy()).unwrap_or_default();
+    let tmp_path = path.with_file_name(format!(".{}.rizeclone-tmp", file_name));
+    let result = std::fs::write(&tmp_path, contents)
+        .and_then(|_| std::fs::set_permissions(&tmp_path, permissions))
+        .and_then(|_| std::fs::rename(&tmp_path, path));
+    if result.is_err() {
+        let _ = std::fs::remove_file(&tmp_path);
+    }
+    result
+}
+
+// Removes complete BEGIN…END sections. A BEGIN line without a matching END
+// is kept with everything after it, as that part was not written by us.
+fn strip_section(contents: &str) -> String {
+    let lines: Vec<&str> = contents.lines().collect();
+    let mut stripped = String::with_capacity(contents.len());
+    let mut i = 0;
+    while i < lines.len() {
+        if lines[i].trim() == SECTION_BEGIN {
+            if let Some(end) = lines[i + 1..].iter().position(|line| line.trim() == SECTION_END) {
+                i += end + 2;
+                continue;
+            }
+        }
+        stripped.push_str(lines[i]);
+        stripped.push('\n');
+        i += 1;
+    }
+    stripped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn blocker(hosts_path: PathBuf) -> WebsiteBlocker {
+        WebsiteBlocker {
+            hosts_path,
+            domains: vec!["example.com".to_string()],
+            helper: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn strips_only_complete_sections() {
+        let hosts = format!(
+            "127.0.0.1 localhost\n{}\n0.0.0.0 example.com\n{}\n10.0.0.2 nas\n",
+            SECTION_BEGIN, SECTION_END
+        );
+        assert_eq!(strip_section(&hosts), "127.0.0.1 localhost\n10.0.0.2 nas\n");
+
+        let unterminated = format!("127.0.0.1 localhost\n{}\n10.0.0.2 nas\n", SECTION_BEGIN);
+        assert_eq!(strip_section(&unterminated), unterminated);
+    }
+
+    #[test]
+    fn block_and_unblock_keep_the_users_entries() {
+        let dir = std::env::temp_dir().join(format!("rizeclone-hosts-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("hosts");
+        let original = "127.0.0.1 localhost\n10.0.0.2 nas";
+        std::fs::write(&path, original).unwrap();
+        let blocker = blocker(path.clone());
+
+        blocker.block().unwrap();
+        let blocked = std::fs::read_to_string(&path).unwrap();
+        assert!(blocked.starts_with("127.0.0.1 localhost\n10.0.0.2 nas\n"));
+        assert!(blocked.contains("0.0.0.0 www.example.com\n"));
+
+        // Blocking twice keeps a single section
+        blocker.block().unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), blocked);
+
+        blocker.unblock().unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "127.0.0.1 localhost\n10.0.0.2 nas\n");
+        // No temporary copy is left next to the hosts file
+        let files: Vec<_> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        assert_eq!(files, ["hosts"]);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}